@@ -0,0 +1,44 @@
+//! Replays arbitrary data as the answer to a Minecraft Bedrock query, with the ping's nonce put in it (as
+//! the nonce is random, the parsing wouldn't get past it otherwise).
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use gamedig::GDResult;
+use gamedig::protocols::minecraft;
+use gamedig::protocols::types::TimeoutSettings;
+use gamedig::socket::{MockSocket, Socket};
+
+struct NonceSocket {
+    socket: MockSocket,
+    nonce: Vec<u8>
+}
+
+impl Socket for NonceSocket {
+    fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        self.socket.apply_timeout(timeout_settings)
+    }
+
+    fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        self.nonce = data.get(1..9).unwrap_or_default().to_vec();
+        self.socket.send(data)
+    }
+
+    fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        let mut data = self.socket.receive(size)?;
+        if data.len() >= 9 && self.nonce.len() == 8 {
+            data[1..9].copy_from_slice(&self.nonce);
+        }
+
+        Ok(data)
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let socket = NonceSocket {
+        socket: MockSocket::new(vec![data.to_vec()]),
+        nonce: vec![]
+    };
+
+    let _ = minecraft::query_bedrock_with_socket(socket, None);
+});
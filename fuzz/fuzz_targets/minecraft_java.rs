@@ -0,0 +1,11 @@
+//! Replays arbitrary data as the stream of a Minecraft Java query (the status and the pong packets).
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use gamedig::protocols::minecraft;
+use gamedig::socket::MockSocket;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = minecraft::query_java_with_socket(MockSocket::new(vec![data.to_vec()]), None);
+});
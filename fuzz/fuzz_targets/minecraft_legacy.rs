@@ -0,0 +1,18 @@
+//! Replays arbitrary data as the answer to a Minecraft legacy query, of every group.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use gamedig::protocols::minecraft::{self, LegacyGroup};
+use gamedig::socket::MockSocket;
+
+fuzz_target!(|input: (u8, Vec<u8>)| {
+    let (group, data) = input;
+    let group = match group % 3 {
+        0 => LegacyGroup::V1_6,
+        1 => LegacyGroup::V1_4,
+        _ => LegacyGroup::VB1_8
+    };
+
+    let _ = minecraft::query_legacy_specific_with_socket(group, MockSocket::new(vec![data]), None);
+});
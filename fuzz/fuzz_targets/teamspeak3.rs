@@ -0,0 +1,12 @@
+//! Replays arbitrary data as the stream of a TeamSpeak 3 ServerQuery session (the greeting and the
+//! responses to the commands), which should fail with an error, never panic.
+
+#![no_main]
+
+use std::io;
+use libfuzzer_sys::fuzz_target;
+use gamedig::services::teamspeak3;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = teamspeak3::query_with_stream(data, io::sink(), teamspeak3::DEFAULT_VIRTUAL_SERVER_PORT, None);
+});
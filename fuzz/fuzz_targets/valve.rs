@@ -0,0 +1,20 @@
+//! Replays arbitrary packets as the answers to a Valve query (info, players and rules, split and compressed
+//! responses included), which should fail with an error, never panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use gamedig::protocols::valve::{self, App};
+use gamedig::socket::MockSocket;
+
+fuzz_target!(|input: (u8, Vec<Vec<u8>>)| {
+    let (app, packets) = input;
+    let app = match app % 4 {
+        0 => App::GoldSrc(false),
+        1 => App::GoldSrc(true),
+        2 => App::Source(None),
+        _ => App::Source(Some(2400)) //The Ship, which has more fields
+    };
+
+    let _ = valve::query_with_socket(MockSocket::new(packets), app, None, None);
+});
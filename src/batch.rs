@@ -0,0 +1,382 @@
+//! Query many servers at once.
+//!
+//! The queries are run by a bounded number of workers, the UDP ones (Valve and Minecraft Bedrock)
+//! share a few sockets instead of opening one for every server, the responses being routed by
+//! their source address.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io;
+use std::net::{self, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+use crate::{GDErrorKind, GDResult};
+use crate::protocols::minecraft::{self, auto_query_error, BedrockResponse, LegacyGroup};
+use crate::protocols::types::TimeoutSettings;
+use crate::protocols::valve::{self, App, GatheringSettings};
+use crate::socket::{Socket, UdpSocket};
+use crate::utils::{address_and_port_as_string, retry};
+
+/// How many concurrent queries share a single UDP socket.
+static QUERIES_PER_SOCKET: usize = 128;
+/// How often the routing thread checks if the batch has finished.
+static ROUTER_POLL_INTERVAL: Duration = Duration::from_millis(50);
+static PACKET_SIZE: usize = 6144;
+
+/// The protocol (and its settings) to query a target with.
+#[derive(Debug, Clone)]
+pub enum Protocol {
+    /// Valve Protocol, with the app and what data to gather.
+    Valve(App, GatheringSettings),
+    /// Minecraft, with all the protocol variants one by one (Java -> Bedrock -> Legacy (1.6 -> 1.4 -> Beta 1.8)).
+    Minecraft,
+    /// Minecraft Java Edition.
+    MinecraftJava,
+    /// Minecraft (Java) Legacy, a specific group.
+    MinecraftLegacy(LegacyGroup),
+    /// Minecraft Bedrock Edition.
+    MinecraftBedrock
+}
+
+/// A server to be queried.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub address: String,
+    pub port: u16,
+    pub protocol: Protocol
+}
+
+impl Target {
+    pub fn new(address: &str, port: u16, protocol: Protocol) -> Self {
+        Self {
+            address: address.to_string(),
+            port,
+            protocol
+        }
+    }
+}
+
+/// A response from one of the supported protocols.
+#[derive(Debug)]
+pub enum BatchResponse {
+    Valve(Box<valve::Response>),
+    Minecraft(minecraft::Response),
+    MinecraftBedrock(BedrockResponse)
+}
+
+/// The outcome of querying a target.
+#[derive(Debug)]
+pub struct BatchResult {
+    /// The position of the target in the given targets.
+    pub index: usize,
+    pub target: Target,
+    pub result: GDResult<BatchResponse>
+}
+
+type Routes = Mutex<HashMap<SocketAddr, Sender<Vec<u8>>>>;
+
+/// A UDP socket shared by many queries, the received packets are routed by their source address.
+struct Multiplexer {
+    socket: net::UdpSocket,
+    routes: Routes
+}
+
+impl Multiplexer {
+    fn new() -> GDResult<Arc<Self>> {
+        let socket = net::UdpSocket::bind("0.0.0.0:0").map_err(|e| GDErrorKind::SocketBind(e.to_string()).context(e))?;
+        let router_socket = socket.try_clone().map_err(|e| GDErrorKind::SocketBind(e.to_string()).context(e))?;
+        router_socket.set_read_timeout(Some(ROUTER_POLL_INTERVAL)).map_err(|e| GDErrorKind::SocketBind(e.to_string()).context(e))?;
+
+        let multiplexer = Arc::new(Self {
+            socket,
+            routes: Mutex::new(HashMap::new())
+        });
+
+        let weak_multiplexer = Arc::downgrade(&multiplexer);
+        thread::spawn(move || Multiplexer::route(router_socket, weak_multiplexer));
+
+        Ok(multiplexer)
+    }
+
+    /// Routes the received packets until the multiplexer is dropped.
+    fn route(socket: net::UdpSocket, multiplexer: Weak<Multiplexer>) {
+        let mut buf = vec![0; PACKET_SIZE];
+
+        loop {
+            let received = socket.recv_from(&mut buf);
+
+            let multiplexer = match multiplexer.upgrade() {
+                None => return,
+                Some(multiplexer) => multiplexer
+            };
+
+            if let Ok((size, source)) = received {
+                let routes = multiplexer.routes.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(sender) = routes.get(&source) {
+                    let _ = sender.send(buf[..size].to_vec());
+                }
+            }
+        }
+    }
+
+    /// Get a socket that talks only with the address, None if the address is already in use.
+    fn socket_for(self: &Arc<Self>, address: SocketAddr, read_timeout: Option<Duration>) -> Option<MultiplexedSocket> {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+
+        match routes.entry(address) {
+            Entry::Occupied(_) => None,
+            Entry::Vacant(entry) => {
+                let (sender, receiver) = mpsc::channel();
+                entry.insert(sender);
+
+                Some(MultiplexedSocket {
+                    multiplexer: self.clone(),
+                    address,
+                    receiver,
+                    read_timeout: Cell::new(read_timeout)
+                })
+            }
+        }
+    }
+}
+
+/// A socket that sends and receives through a [Multiplexer].
+struct MultiplexedSocket {
+    multiplexer: Arc<Multiplexer>,
+    address: SocketAddr,
+    receiver: Receiver<Vec<u8>>,
+    read_timeout: Cell<Option<Duration>>
+}
+
+impl Socket for MultiplexedSocket {
+    fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        //the underlying socket is shared, so only the wait for a routed packet is limited
+        self.read_timeout.set(timeout_settings.unwrap_or_default().get_read());
+        Ok(())
+    }
+
+    fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        self.multiplexer.socket.send_to(data, self.address).map_err(|e| GDErrorKind::PacketSend(e.to_string()).context(e))?;
+        Ok(())
+    }
+
+    fn receive(&mut self, _size: Option<usize>) -> GDResult<Vec<u8>> {
+        match self.read_timeout.get() {
+            None => self.receiver.recv().map_err(|e| GDErrorKind::PacketReceive(e.to_string()).context(e)),
+            Some(timeout) => self.receiver.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => GDErrorKind::PacketReceive("Timed out.".to_string()).context(io::Error::from(io::ErrorKind::TimedOut)),
+                RecvTimeoutError::Disconnected => GDErrorKind::PacketReceive("Socket closed.".to_string()).context(e)
+            })
+        }
+    }
+}
+
+impl Drop for MultiplexedSocket {
+    fn drop(&mut self) {
+        let mut routes = self.multiplexer.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes.remove(&self.address);
+    }
+}
+
+fn resolve(address: &str, port: u16) -> GDResult<SocketAddr> {
+    address_and_port_as_string(address, port).to_socket_addrs()
+        .map_err(|e| GDErrorKind::InvalidInput(e.to_string()).context(e))?
+        .next()
+        .ok_or_else(|| GDErrorKind::InvalidInput(format!("Couldn't resolve {address}.")).into())
+}
+
+/// Run a UDP query through the multiplexer, or through an own socket if the multiplexer is
+/// already talking with that address, retrying it as the timeout settings say.
+fn query_udp<T>(multiplexer: &Arc<Multiplexer>, target: &Target, timeout_settings: &Option<TimeoutSettings>,
+                mut with_multiplexed: impl FnMut(MultiplexedSocket, Option<TimeoutSettings>) -> GDResult<T>,
+                mut with_own: impl FnMut(UdpSocket, Option<TimeoutSettings>) -> GDResult<T>) -> GDResult<T> {
+    let address = resolve(&target.address, target.port)?;
+
+    retry(timeout_settings.clone(), |timeout_settings| {
+        //the shared sockets are IPv4 ones
+        let multiplexed = match address.is_ipv4() {
+            true => multiplexer.socket_for(address, timeout_settings.clone().unwrap_or_default().get_read()),
+            false => None
+        };
+
+        match multiplexed {
+            None => with_own(UdpSocket::new_with_address(address)?, timeout_settings),
+            Some(socket) => with_multiplexed(socket, timeout_settings)
+        }
+    })
+}
+
+fn query_bedrock(multiplexer: &Arc<Multiplexer>, target: &Target, timeout_settings: &Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+    query_udp(multiplexer, target, timeout_settings,
+              minecraft::query_bedrock_with_socket,
+              minecraft::query_bedrock_with_socket)
+}
+
+fn query_target(multiplexer: &Arc<Multiplexer>, target: &Target, timeout_settings: &Option<TimeoutSettings>) -> GDResult<BatchResponse> {
+    match &target.protocol {
+        Protocol::Valve(app, gathering_settings) => query_udp(multiplexer, target, timeout_settings,
+            |socket, timeout_settings| valve::query_with_socket(socket, app.clone(), Some(gathering_settings.clone()), timeout_settings),
+            |socket, timeout_settings| valve::query_with_socket(socket, app.clone(), Some(gathering_settings.clone()), timeout_settings))
+            .map(|response| BatchResponse::Valve(Box::new(response))),
+        Protocol::Minecraft => {
+            let java_error = match minecraft::query_java(&target.address, target.port, timeout_settings.clone()) {
+                Ok(response) => return Ok(BatchResponse::Minecraft(response)),
+                Err(error) => error
+            };
+
+            let bedrock_error = match query_bedrock(multiplexer, target, timeout_settings) {
+                Ok(response) => return Ok(BatchResponse::MinecraftBedrock(response)),
+                Err(error) => error
+            };
+
+            let legacy_error = match minecraft::query_legacy(&target.address, target.port, timeout_settings.clone()) {
+                Ok(response) => return Ok(BatchResponse::Minecraft(response)),
+                Err(error) => error
+            };
+
+            Err(auto_query_error(&[("Java", java_error), ("Bedrock", bedrock_error), ("Legacy", legacy_error)]))
+        }
+        Protocol::MinecraftJava => minecraft::query_java(&target.address, target.port, timeout_settings.clone())
+            .map(BatchResponse::Minecraft),
+        Protocol::MinecraftLegacy(group) => minecraft::query_legacy_specific(group.clone(), &target.address, target.port, timeout_settings.clone())
+            .map(BatchResponse::Minecraft),
+        Protocol::MinecraftBedrock => query_bedrock(multiplexer, target, timeout_settings)
+            .map(BatchResponse::MinecraftBedrock)
+    }
+}
+
+/// The results of a batch, in the order that the queries complete.
+pub struct Batch {
+    receiver: Receiver<BatchResult>
+}
+
+impl Iterator for Batch {
+    type Item = BatchResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Query the targets with at most `concurrency` queries running at once, every query using the
+/// given timeout settings (None meaning the default ones).
+/// The results are returned as they complete, each one having its own outcome.
+///
+/// Every concurrent query runs on its own OS thread (at most as many as the targets, plus one routing
+/// thread for every 128 of them), so a high concurrency costs a thread stack each,
+/// a few hundred being a reasonable upper bound.
+pub fn query_many(targets: Vec<Target>, concurrency: usize, timeout_settings: Option<TimeoutSettings>) -> GDResult<Batch> {
+    if concurrency == 0 {
+        return Err(GDErrorKind::InvalidInput("The concurrency must be at least 1.".to_string()).into());
+    }
+
+    let workers = concurrency.min(targets.len());
+    let sockets_count = workers / QUERIES_PER_SOCKET + 1;
+    let multiplexers = (0..sockets_count).map(|_| Multiplexer::new()).collect::<GDResult<Vec<_>>>()?;
+
+    let queue = Arc::new(Mutex::new(targets.into_iter().enumerate()));
+    let (sender, receiver) = mpsc::channel();
+
+    for worker in 0..workers {
+        let multiplexer = multiplexers[worker % sockets_count].clone();
+        let queue = queue.clone();
+        let sender = sender.clone();
+        let timeout_settings = timeout_settings.clone();
+
+        thread::spawn(move || loop {
+            let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+            let (index, target) = match next {
+                None => return,
+                Some(next) => next
+            };
+
+            let result = query_target(&multiplexer, &target, &timeout_settings);
+            if sender.send(BatchResult { index, target, result }).is_err() {
+                return;
+            }
+        });
+    }
+
+    Ok(Batch {
+        receiver
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::types::RetrySettings;
+
+    fn info_response(name: &str) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x49, 17];
+        for text in [name, "map", "folder", "game"] {
+            data.extend(text.as_bytes());
+            data.push(0);
+        }
+        data.extend([0x00, 0x00, 1, 8, 0, b'd', b'l', 0, 1]);
+        data.extend(b"1.0\0");
+
+        data
+    }
+
+    #[test]
+    fn zero_concurrency() {
+        assert!(query_many(vec![], 0, None).is_err());
+    }
+
+    #[test]
+    fn many_servers_through_a_shared_socket() {
+        let servers: Vec<net::UdpSocket> = (0..4).map(|_| net::UdpSocket::bind("127.0.0.1:0").unwrap()).collect();
+        let targets = servers.iter().map(|server| Target::new("127.0.0.1", server.local_addr().unwrap().port(),
+            Protocol::Valve(App::Source(None), GatheringSettings { players: false, rules: false, split_retransmissions: 0, lenient: false }))).collect();
+
+        let mut client_addresses = Vec::new();
+        let batch = query_many(targets, 4, None).unwrap();
+
+        for (i, server) in servers.iter().enumerate() {
+            let mut buf = [0; 64];
+            let (_, client) = server.recv_from(&mut buf).unwrap();
+            server.send_to(&info_response(&format!("server {i}")), client).unwrap();
+            client_addresses.push(client);
+        }
+
+        let mut results: Vec<BatchResult> = batch.collect();
+        results.sort_by_key(|result| result.index);
+
+        assert_eq!(results.len(), 4);
+        for (i, result) in results.iter().enumerate() {
+            match &result.result {
+                Ok(BatchResponse::Valve(response)) => assert_eq!(response.info.name, format!("server {i}")),
+                other => panic!("Unexpected result: {other:?}")
+            }
+        }
+
+        client_addresses.dedup();
+        assert_eq!(client_addresses.len(), 1);
+    }
+
+    #[test]
+    fn retries_through_a_shared_socket() {
+        let server = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = Target::new("127.0.0.1", server.local_addr().unwrap().port(),
+            Protocol::Valve(App::Source(None), GatheringSettings { players: false, rules: false, split_retransmissions: 0, lenient: false }));
+
+        let retry = RetrySettings::new(1, Duration::from_millis(1), None).unwrap();
+        let timeout_settings = TimeoutSettings::new_with_retry(Some(Duration::from_millis(200)), None, retry).unwrap();
+        let mut batch = query_many(vec![target], 1, Some(timeout_settings)).unwrap();
+
+        //the first request is left unanswered, so it times out and gets sent again
+        let mut buf = [0; 64];
+        server.recv_from(&mut buf).unwrap();
+        let (_, client) = server.recv_from(&mut buf).unwrap();
+        server.send_to(&info_response("retried"), client).unwrap();
+
+        match batch.next().unwrap().result {
+            Ok(BatchResponse::Valve(response)) => assert_eq!(response.info.name, "retried"),
+            other => panic!("Unexpected result: {other:?}")
+        }
+    }
+}
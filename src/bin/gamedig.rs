@@ -0,0 +1,388 @@
+//! The gamedig command line interface, enabled by the `cli` feature.
+
+use std::process::exit;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use gamedig::{ALIASES, find_game, GAMES, GDError, GDErrorKind, GDResult, query_by_id_with_options, QueryOptions};
+use gamedig::protocols::types::{GenericResponse, RetrySettings, TimeoutSettings};
+
+/// The longest accepted timeout, one day.
+static MAXIMUM_TIMEOUT_SECONDS: f64 = 86400.0;
+
+static USAGE: &str = "Usage: gamedig <game> <host[:port]>... [options]
+       gamedig --list
+
+Options:
+    -p, --port <port>         the port to use for the hosts that don't specify one (default: the game's one)
+    -t, --timeout <seconds>   how long to wait for every host to respond, at most a day (default: the queries' own timeouts)
+    -f, --format <format>     pretty (default), json or line
+    -l, --list                list the supported games
+    -h, --help                print this message
+
+Exit codes:
+    0 - every host responded
+    1 - bad usage or unknown game
+    2 - network error (couldn't connect, send, receive or timed out)
+    3 - bad response (the server replied with something unexpected)
+    4 - the server isn't running the queried game
+    5 - invalid input
+    6 - the service replied with an error
+    7 - every protocol variant failed (example: Minecraft's Java, Bedrock and legacy ones)
+When querying multiple hosts, the exit code is the one of the first host that failed.";
+
+#[derive(PartialEq, Eq)]
+enum Format {
+    Pretty,
+    Json,
+    Line
+}
+
+struct Arguments {
+    game: String,
+    hosts: Vec<(String, Option<u16>)>,
+    port: Option<u16>,
+    timeout: Option<Duration>,
+    /// The queries' options, their timeouts (and deadline) being the `--timeout` one.
+    options: QueryOptions,
+    format: Format
+}
+
+enum Command {
+    Help,
+    List,
+    Query(Arguments)
+}
+
+/// Why a host's query failed.
+enum Failure {
+    Error(GDError),
+    Timeout
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Timeout => 2,
+            Failure::Error(error) => match error.kind() {
+                GDErrorKind::SocketBind(_) | GDErrorKind::SocketConnect(_) | GDErrorKind::PacketSend(_)
+                | GDErrorKind::PacketReceive(_) => 2,
+                GDErrorKind::PacketOverflow(_) | GDErrorKind::PacketUnderflow(_) | GDErrorKind::PacketBad(_)
+                | GDErrorKind::Decompress(_) | GDErrorKind::UnknownEnumCast(_) | GDErrorKind::JsonParse(_)
+                | GDErrorKind::ProtocolFormat(_) | GDErrorKind::TypeParse(_) => 3,
+                GDErrorKind::BadGame(_) => 4,
+                GDErrorKind::InvalidInput(_) => 5,
+                GDErrorKind::ServiceError(_, _) => 6,
+                GDErrorKind::AutoQuery(_) => 7
+            }
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Failure::Timeout => "Timed out.".to_string(),
+            Failure::Error(error) => error.to_string()
+        }
+    }
+}
+
+/// Splits a `host[:port]`, an IPv6 address with a port has to be in brackets (`[::1]:27015`).
+fn parse_host(host: &str) -> Result<(String, Option<u16>), String> {
+    let (address, port) = match host.strip_prefix('[') {
+        Some(bracketed) => match bracketed.split_once(']') {
+            Some((address, "")) => (address, None),
+            Some((address, rest)) => (address, Some(rest.strip_prefix(':').ok_or(format!("Invalid host: {host}"))?)),
+            None => return Err(format!("Invalid host: {host}"))
+        },
+        None if host.matches(':').count() > 1 => (host, None), //an IPv6 address without a port
+        None => match host.split_once(':') {
+            None => (host, None),
+            Some((address, port)) => (address, Some(port))
+        }
+    };
+
+    match port {
+        None => Ok((address.to_string(), None)),
+        Some(port) => port.parse()
+            .map(|port| (address.to_string(), Some(port)))
+            .map_err(|_| format!("Invalid port in {host}"))
+    }
+}
+
+/// The queries' options for a `--timeout`: every read and write, and the whole query (its deadline), are
+/// limited to it.
+fn query_options(timeout: Option<Duration>) -> GDResult<QueryOptions> {
+    let timeout_settings = match timeout {
+        None => None,
+        Some(timeout) => Some(TimeoutSettings::new_with_retry(Some(timeout), Some(timeout), RetrySettings::new(0, Duration::default(), Some(timeout))?)?)
+    };
+
+    Ok(QueryOptions {
+        timeout_settings,
+        gather_settings: None
+    })
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Command, String> {
+    let mut positionals = Vec::new();
+    let mut port = None;
+    let mut timeout = None;
+    let mut format = Format::Pretty;
+
+    let mut iter = arguments.iter();
+    while let Some(argument) = iter.next() {
+        let mut value = |name: &str| iter.next().ok_or(format!("Missing the value of {name}"));
+
+        match argument.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-l" | "--list" => return Ok(Command::List),
+            "-p" | "--port" => port = Some(value(argument)?.parse().map_err(|_| "Invalid port")?),
+            "-t" | "--timeout" => {
+                let seconds: f64 = value(argument)?.parse().map_err(|_| "Invalid timeout")?;
+                //NaN fails every comparison, so it's checked by what's accepted
+                if !(seconds > 0.0 && seconds <= MAXIMUM_TIMEOUT_SECONDS) {
+                    return Err(format!("The timeout must be greater than 0 and at most {MAXIMUM_TIMEOUT_SECONDS} seconds"));
+                }
+
+                timeout = Some(Duration::from_secs_f64(seconds));
+            }
+            "-f" | "--format" => format = match value(argument)?.as_str() {
+                "pretty" => Format::Pretty,
+                "json" => Format::Json,
+                "line" => Format::Line,
+                other => return Err(format!("Unknown format: {other}"))
+            },
+            other if other.starts_with('-') => return Err(format!("Unknown option: {other}")),
+            other => positionals.push(other.to_string())
+        }
+    }
+
+    if positionals.len() < 2 {
+        return Err("Expected a game and at least one host".to_string());
+    }
+
+    let game = positionals.remove(0);
+    if find_game(&game).is_none() {
+        return Err(format!("Unknown game: {game} (see --list)"));
+    }
+
+    Ok(Command::Query(Arguments {
+        game,
+        hosts: positionals.iter().map(|host| parse_host(host)).collect::<Result<_, _>>()?,
+        port,
+        timeout,
+        options: query_options(timeout).map_err(|e| e.to_string())?,
+        format
+    }))
+}
+
+/// Queries every host on its own thread, waiting (at most the timeout, in case a query doesn't keep to its
+/// deadline) for all of them.
+fn query_hosts(arguments: &Arguments) -> Vec<Result<GenericResponse, Failure>> {
+    let (sender, receiver) = mpsc::channel();
+
+    for (index, (address, port)) in arguments.hosts.iter().enumerate() {
+        let sender = sender.clone();
+        let game = arguments.game.clone();
+        let address = address.clone();
+        let port = port.or(arguments.port);
+        let options = arguments.options.clone();
+
+        thread::spawn(move || {
+            let result: GDResult<GenericResponse> = query_by_id_with_options(&game, &address, port, &options);
+            sender.send((index, result)).ok();
+        });
+    }
+
+    drop(sender);
+
+    let mut results: Vec<Option<GDResult<GenericResponse>>> = arguments.hosts.iter().map(|_| None).collect();
+    let deadline = arguments.timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let received = match deadline {
+            None => receiver.recv().ok(),
+            Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())).ok()
+        };
+
+        match received {
+            None => break,
+            Some((index, result)) => results[index] = Some(result)
+        }
+    }
+
+    results.into_iter()
+        .map(|result| match result {
+            None => Err(Failure::Timeout),
+            Some(result) => result.map_err(Failure::Error)
+        })
+        .collect()
+}
+
+fn host_name(host: &(String, Option<u16>), port: Option<u16>) -> String {
+    match host.1.or(port) {
+        None => host.0.clone(),
+        Some(port) if host.0.contains(':') => format!("[{}]:{port}", host.0),
+        Some(port) => format!("{}:{port}", host.0)
+    }
+}
+
+fn print_pretty(host: &str, result: &Result<GenericResponse, Failure>) {
+    println!("{host}");
+
+    match result {
+        Err(failure) => println!("  Error: {}", failure.message()),
+        Ok(response) => {
+            let unknown = "-".to_string();
+
+            println!("  Name: {}", response.name.as_ref().unwrap_or(&unknown));
+            if let Some(description) = &response.description {
+                println!("  Description: {description}");
+            }
+            println!("  Game: {} ({})", response.game.as_ref().unwrap_or(&unknown), response.game_version.as_ref().unwrap_or(&unknown));
+            println!("  Map: {}", response.map.as_ref().unwrap_or(&unknown));
+            println!("  Players: {}/{}", response.players_online, response.players_maximum);
+            if let Some(has_password) = response.has_password {
+                println!("  Password: {}", if has_password { "yes" } else { "no" });
+            }
+            if let Some(ping) = response.ping {
+                println!("  Ping: {} ms", ping.as_millis());
+            }
+
+            for player in &response.players {
+                match player.score {
+                    None => println!("    {}", player.name),
+                    Some(score) => println!("    {} ({score})", player.name)
+                }
+            }
+        }
+    }
+}
+
+fn print_line(host: &str, result: &Result<GenericResponse, Failure>) {
+    match result {
+        Err(failure) => println!("{host}\terror\t{}", failure.message()),
+        Ok(response) => println!("{host}\t{}\t{}\t{}/{}",
+                                 response.name.as_deref().unwrap_or("-"),
+                                 response.map.as_deref().unwrap_or("-"),
+                                 response.players_online,
+                                 response.players_maximum)
+    }
+}
+
+fn to_json(host: &str, result: &Result<GenericResponse, Failure>) -> serde_json::Value {
+    match result {
+        Err(failure) => serde_json::json!({
+            "host": host,
+            "error": failure.message()
+        }),
+        Ok(response) => serde_json::json!({
+            "host": host,
+            "response": response
+        })
+    }
+}
+
+fn main() {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+
+    let arguments = match parse_arguments(&arguments) {
+        Ok(Command::Query(arguments)) => arguments,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Ok(Command::List) => {
+            for game in GAMES {
+                println!("{}\t{}", game.id, game.name);
+            }
+            for (alias, id) in ALIASES.iter() {
+                println!("{alias}\t{id}");
+            }
+            return;
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!("{USAGE}");
+            exit(1);
+        }
+    };
+
+    let results = query_hosts(&arguments);
+    let hosts: Vec<String> = arguments.hosts.iter().map(|host| host_name(host, arguments.port)).collect();
+
+    match arguments.format {
+        Format::Pretty => hosts.iter().zip(&results).for_each(|(host, result)| print_pretty(host, result)),
+        Format::Line => hosts.iter().zip(&results).for_each(|(host, result)| print_line(host, result)),
+        Format::Json => {
+            let values: Vec<serde_json::Value> = hosts.iter().zip(&results).map(|(host, result)| to_json(host, result)).collect();
+            println!("{}", serde_json::to_string_pretty(&values).unwrap_or_default());
+        }
+    }
+
+    let exit_code = results.iter()
+        .find_map(|result| result.as_ref().err())
+        .map_or(0, Failure::exit_code);
+
+    exit(exit_code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(arguments: &[&str]) -> Result<Command, String> {
+        parse_arguments(&arguments.iter().map(|argument| argument.to_string()).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn arguments() {
+        let arguments = match parse(&["tf2", "127.0.0.1:27016", "[::1]", "-p", "27015", "-t", "1.5", "-f", "json"]) {
+            Ok(Command::Query(arguments)) => arguments,
+            _ => panic!("expected a query")
+        };
+
+        assert_eq!(arguments.game, "tf2");
+        assert_eq!(arguments.hosts, vec![("127.0.0.1".to_string(), Some(27016)), ("::1".to_string(), None)]);
+        assert_eq!(arguments.port, Some(27015));
+        assert!(arguments.format == Format::Json);
+
+        let timeout_settings = arguments.options.timeout_settings.unwrap();
+        assert_eq!(timeout_settings.get_read(), Some(Duration::from_millis(1500)));
+        assert_eq!(timeout_settings.get_retry().get_deadline(), Some(Duration::from_millis(1500)));
+
+        match parse(&["tf2", "127.0.0.1"]) {
+            Ok(Command::Query(arguments)) => assert!(arguments.options.timeout_settings.is_none()),
+            _ => panic!("expected a query")
+        }
+
+        assert!(matches!(parse(&["--list"]), Ok(Command::List)));
+        assert!(matches!(parse(&["tf2", "-h"]), Ok(Command::Help)));
+    }
+
+    #[test]
+    fn bad_arguments() {
+        assert!(parse(&["tf2"]).is_err());
+        assert!(parse(&["not_a_game", "127.0.0.1"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1", "-t", "0"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1", "-t", "inf"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1", "-t", "NaN"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1", "-t", "1e30"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1", "-t"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1", "-f", "xml"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1:port"]).is_err());
+        assert!(parse(&["tf2", "127.0.0.1", "--unknown"]).is_err());
+    }
+
+    #[test]
+    fn exit_codes() {
+        let code = |kind: GDErrorKind| Failure::Error(kind.into()).exit_code();
+
+        assert_eq!(Failure::Timeout.exit_code(), 2);
+        assert_eq!(code(GDErrorKind::PacketReceive(String::new())), 2);
+        assert_eq!(code(GDErrorKind::PacketBad(String::new())), 3);
+        assert_eq!(code(GDErrorKind::BadGame(String::new())), 4);
+        assert_eq!(code(GDErrorKind::InvalidInput(String::new())), 5);
+        assert_eq!(code(GDErrorKind::ServiceError(0, String::new())), 6);
+        assert_eq!(code(GDErrorKind::AutoQuery(String::new())), 7);
+    }
+}
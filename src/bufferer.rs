@@ -1,166 +1,200 @@
-use crate::{GDError, GDResult};
+use crate::{GDErrorKind, GDResult};
 
+#[derive(Clone, Copy)]
 pub enum Endianess {
     Little, Big
 }
 
-pub struct Bufferer {
-    data: Vec<u8>,
+/// A reader over borrowed data, nothing is copied until a value is taken out of it (and the strings can be
+/// borrowed too, see [get_str_utf8](Bufferer::get_str_utf8)).
+pub struct Bufferer<'a> {
+    data: &'a [u8],
     endianess: Endianess,
     position: usize
 }
 
-impl Bufferer {
-    pub fn new_with_data(endianess: Endianess, data: &[u8]) -> Self {
+impl<'a> Bufferer<'a> {
+    pub fn new_with_data(endianess: Endianess, data: &'a [u8]) -> Self {
         Bufferer {
-            data: data.to_vec(),
+            data,
             endianess,
             position: 0
         }
     }
 
-    fn check_size(&self, by: usize) -> bool {
-        by > self.remaining_length()
+    /// Gets the next N bytes (`what` being what they are, for the error).
+    fn get_bytes<const N: usize>(&mut self, what: &str) -> GDResult<[u8; N]> {
+        let bytes: [u8; N] = self.remaining_data().get(..N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| GDErrorKind::PacketUnderflow(format!("Unexpectedly short packet for getting an {what}.")))?;
+
+        self.position += N;
+        Ok(bytes)
     }
 
     pub fn get_u8(&mut self) -> GDResult<u8> {
-        if self.check_size(1) {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an u8.".to_string()));
-        }
-
-        let value = self.data[self.position];
-        self.position += 1;
+        let [value] = self.get_bytes("u8")?;
         Ok(value)
     }
 
     pub fn get_u16(&mut self) -> GDResult<u16> {
-        if self.check_size(2) {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an u16.".to_string()));
-        }
-
-        let source_data: [u8; 2] = (&self.data[self.position..self.position + 2]).try_into().unwrap();
+        let source_data: [u8; 2] = self.get_bytes("u16")?;
 
-        let value = match self.endianess {
+        Ok(match self.endianess {
             Endianess::Little => u16::from_le_bytes(source_data),
             Endianess::Big => u16::from_be_bytes(source_data)
-        };
-
-        self.position += 2;
-        Ok(value)
+        })
     }
     
     pub fn get_u32(&mut self) -> GDResult<u32> {
-        if self.check_size(4) {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an u32.".to_string()));
-        }
-
-        let source_data: [u8; 4] = (&self.data[self.position..self.position + 4]).try_into().unwrap();
+        let source_data: [u8; 4] = self.get_bytes("u32")?;
 
-        let value = match self.endianess {
+        Ok(match self.endianess {
             Endianess::Little => u32::from_le_bytes(source_data),
             Endianess::Big => u32::from_be_bytes(source_data)
-        };
-
-        self.position += 4;
-        Ok(value)
+        })
     }
 
     pub fn get_f32(&mut self) -> GDResult<f32> {
-        if self.check_size(4) {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an f32.".to_string()));
-        }
+        let source_data: [u8; 4] = self.get_bytes("f32")?;
 
-        let source_data: [u8; 4] = (&self.data[self.position..self.position + 4]).try_into().unwrap();
-
-        let value = match self.endianess {
+        Ok(match self.endianess {
             Endianess::Little => f32::from_le_bytes(source_data),
             Endianess::Big => f32::from_be_bytes(source_data)
-        };
-
-        self.position += 4;
-        Ok(value)
+        })
     }
 
     pub fn get_u64(&mut self) -> GDResult<u64> {
-        if self.check_size(8) {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an u64.".to_string()));
-        }
+        let source_data: [u8; 8] = self.get_bytes("u64")?;
 
-        let source_data: [u8; 8] = (&self.data[self.position..self.position + 8]).try_into().unwrap();
-        
-        let value = match self.endianess {
+        Ok(match self.endianess {
             Endianess::Little => u64::from_le_bytes(source_data),
             Endianess::Big => u64::from_be_bytes(source_data)
-        };
+        })
+    }
 
-        self.position += 8;
-        Ok(value)
+    #[allow(dead_code)]
+    pub fn get_i8(&mut self) -> GDResult<i8> {
+        let source_data: [u8; 1] = self.get_bytes("i8")?;
+        Ok(i8::from_le_bytes(source_data))
     }
 
-    pub fn get_string_utf8(&mut self) -> GDResult<String> {
-        let sub_buf = &self.data[self.position..];
-        if sub_buf.len() == 0 {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an utf8 string.".to_string()));
+    #[allow(dead_code)]
+    pub fn get_i16(&mut self) -> GDResult<i16> {
+        let source_data: [u8; 2] = self.get_bytes("i16")?;
+
+        Ok(match self.endianess {
+            Endianess::Little => i16::from_le_bytes(source_data),
+            Endianess::Big => i16::from_be_bytes(source_data)
+        })
+    }
+
+    pub fn get_i32(&mut self) -> GDResult<i32> {
+        let source_data: [u8; 4] = self.get_bytes("i32")?;
+
+        Ok(match self.endianess {
+            Endianess::Little => i32::from_le_bytes(source_data),
+            Endianess::Big => i32::from_be_bytes(source_data)
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn get_i64(&mut self) -> GDResult<i64> {
+        let source_data: [u8; 8] = self.get_bytes("i64")?;
+
+        Ok(match self.endianess {
+            Endianess::Little => i64::from_le_bytes(source_data),
+            Endianess::Big => i64::from_be_bytes(source_data)
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn get_f64(&mut self) -> GDResult<f64> {
+        let source_data: [u8; 8] = self.get_bytes("f64")?;
+
+        Ok(match self.endianess {
+            Endianess::Little => f64::from_le_bytes(source_data),
+            Endianess::Big => f64::from_be_bytes(source_data)
+        })
+    }
+
+    /// Gets a null terminated utf8 string, borrowed from the data.
+    pub fn get_str_utf8(&mut self) -> GDResult<&'a str> {
+        let sub_buf = self.remaining_data();
+        if sub_buf.is_empty() {
+            return Err(GDErrorKind::PacketUnderflow("Unexpectedly short packet for getting an utf8 string.".to_string()).into());
         }
 
         let first_null_position = sub_buf.iter().position(|&x| x == 0)
-            .ok_or(GDError::PacketBad("Unexpectedly formatted packet for getting an utf8 string.".to_string()))?;
+            .ok_or(GDErrorKind::PacketBad("Unexpectedly formatted packet for getting an utf8 string.".to_string()))?;
         let value = std::str::from_utf8(&sub_buf[..first_null_position])
-            .map_err(|_| GDError::PacketBad("Badly formatted utf8 string.".to_string()))?.to_string();
+            .map_err(|e| GDErrorKind::PacketBad("Badly formatted utf8 string.".to_string()).context(e))?;
 
         self.position += value.len() + 1;
         Ok(value)
     }
 
-    pub fn get_string_utf8_unended(&mut self) -> GDResult<String> {
-        let sub_buf = &self.data[self.position..];
-        if sub_buf.len() == 0 {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an utf8 unended string.".to_string()));
+    pub fn get_string_utf8(&mut self) -> GDResult<String> {
+        self.get_str_utf8().map(str::to_string)
+    }
+
+    /// Gets the rest of the data as an utf8 string, borrowed from the data.
+    pub fn get_str_utf8_unended(&mut self) -> GDResult<&'a str> {
+        let sub_buf = self.remaining_data();
+        if sub_buf.is_empty() {
+            return Err(GDErrorKind::PacketUnderflow("Unexpectedly short packet for getting an utf8 unended string.".to_string()).into());
         }
 
-        let value = std::str::from_utf8(&sub_buf)
-            .map_err(|_| GDError::PacketBad("Badly formatted utf8 unended string.".to_string()))?.to_string();
+        let value = std::str::from_utf8(sub_buf)
+            .map_err(|e| GDErrorKind::PacketBad("Badly formatted utf8 unended string.".to_string()).context(e))?;
 
         self.position += value.len();
         Ok(value)
     }
 
+    #[allow(dead_code)]
+    pub fn get_string_utf8_unended(&mut self) -> GDResult<String> {
+        self.get_str_utf8_unended().map(str::to_string)
+    }
+
     pub fn get_string_utf16(&mut self) -> GDResult<String> {
-        let sub_buf = &self.data[self.position..];
-        if sub_buf.len() == 0 {
-            return Err(GDError::PacketUnderflow("Unexpectedly short packet for getting an utf16 string.".to_string()));
+        let sub_buf = self.remaining_data();
+        if sub_buf.is_empty() {
+            return Err(GDErrorKind::PacketUnderflow("Unexpectedly short packet for getting an utf16 string.".to_string()).into());
         }
 
-        let paired_buf: Vec<u16> = sub_buf.chunks_exact(2)
-            .into_iter().map(|a| match self.endianess {
+        let paired_buf: Vec<u16> = sub_buf.chunks_exact(2).map(|a| match self.endianess {
             Endianess::Little => u16::from_le_bytes([a[0], a[1]]),
             Endianess::Big => u16::from_be_bytes([a[0], a[1]])
         }).collect();
 
         let value = String::from_utf16(&paired_buf)
-            .map_err(|_| GDError::PacketBad("Badly formatted utf16 string.".to_string()))?.to_string();
+            .map_err(|e| GDErrorKind::PacketBad("Badly formatted utf16 string.".to_string()).context(e))?;
 
-        self.position += value.len() * 2;
+        self.position += paired_buf.len() * 2;
         Ok(value)
     }
     
+    /// Moves the position ahead, at most to the end of the data.
     pub fn move_position_ahead(&mut self, by: usize) {
-        self.position += by;
+        self.position = self.position.saturating_add(by).min(self.data.len());
     }
 
+    /// Moves the position backward, at most to the beginning of the data.
+    #[allow(dead_code)]
     pub fn move_position_backward(&mut self, by: usize) {
-        self.position -= by;
+        self.position = self.position.saturating_sub(by);
     }
     
-    pub fn get_data_in_front_of_position(&self) -> Vec<u8> {
-        self.data[self.position..].to_vec()
+    pub fn get_data_in_front_of_position(&self) -> &'a [u8] {
+        &self.data[self.position..]
     }
 
     pub fn data_length(&self) -> usize {
         self.data.len()
     }
 
-    pub fn remaining_data(&self) -> &[u8] {
+    pub fn remaining_data(&self) -> &'a [u8] {
         &self.data[self.position..]
     }
 
@@ -170,9 +204,8 @@ impl Bufferer {
 
     pub fn as_endianess(&self, endianess: Endianess) -> Self {
         Bufferer {
-            data: self.data.clone(),
             endianess,
-            position: self.position,
+            ..*self
         }
     }
 }
@@ -262,6 +295,69 @@ mod tests {
         assert!(buffer.get_u64().is_err());
     }
 
+    #[test]
+    fn get_i8() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &[200]);
+
+        assert_eq!(buffer.get_i8().unwrap(), -56);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_i8().is_err());
+    }
+
+    #[test]
+    fn get_i16_le() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &[72, 200]);
+
+        assert_eq!(buffer.get_i16().unwrap(), -14264);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_i16().is_err());
+    }
+
+    #[test]
+    fn get_i32_le() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &[254, 255, 255, 255]);
+
+        assert_eq!(buffer.get_i32().unwrap(), -2);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_i32().is_err());
+    }
+
+    #[test]
+    fn get_i32_be() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Big, &[255, 255, 255, 254]);
+
+        assert_eq!(buffer.get_i32().unwrap(), -2);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_i32().is_err());
+    }
+
+    #[test]
+    fn get_i64_le() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &[72, 29, 128, 99, 69, 4, 2, 128]);
+
+        assert_eq!(buffer.get_i64().unwrap(), -9222804390832759480);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_i64().is_err());
+    }
+
+    #[test]
+    fn get_f64_le() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &[0, 0, 0, 0, 0, 0, 240, 191]);
+
+        assert_eq!(buffer.get_f64().unwrap(), -1.0);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_f64().is_err());
+    }
+
+    #[test]
+    fn get_f64_be() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Big, &[64, 9, 33, 251, 84, 68, 45, 24]);
+
+        assert_eq!(buffer.get_f64().unwrap(), std::f64::consts::PI);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_f64().is_err());
+    }
+
     #[test]
     fn get_string_utf8() {
         let mut buffer = Bufferer::new_with_data(Endianess::Little, &[72, 101, 108, 108, 111, 0, 72]);
@@ -280,6 +376,19 @@ mod tests {
         assert!(buffer.get_string_utf8_unended().is_err());
     }
 
+    #[test]
+    fn get_str_utf8_borrowed() {
+        let data = b"Hello\0World";
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, data);
+
+        let hello = buffer.get_str_utf8().unwrap();
+        assert_eq!(hello, "Hello");
+        assert_eq!(hello.as_ptr(), data.as_ptr());
+
+        assert_eq!(buffer.get_str_utf8_unended().unwrap(), "World");
+        assert_eq!(buffer.remaining_length(), 0);
+    }
+
     #[test]
     fn get_string_utf16_le() {
         let mut buffer = Bufferer::new_with_data(Endianess::Little, &[0x48, 0x00, 0x65, 0x00, 0x6c, 0x00, 0x6c, 0x00, 0x6f, 0x00]);
@@ -297,4 +406,25 @@ mod tests {
         assert_eq!(buffer.remaining_length(), 0);
         assert!(buffer.get_string_utf16().is_err());
     }
+
+    #[test]
+    fn get_string_utf16_multi_byte() {
+        //'§' is 2 bytes in utf8, but a single utf16 unit
+        let mut buffer = Bufferer::new_with_data(Endianess::Big, &[0x00, 0xA7, 0x00, 0x31, 0x00]);
+
+        assert_eq!(buffer.get_string_utf16().unwrap(), "§1");
+        assert_eq!(buffer.remaining_length(), 1);
+    }
+
+    #[test]
+    fn move_position_bounds() {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &[72, 79]);
+
+        buffer.move_position_ahead(5);
+        assert_eq!(buffer.remaining_length(), 0);
+        assert!(buffer.get_u8().is_err());
+
+        buffer.move_position_backward(5);
+        assert_eq!(buffer.get_u16().unwrap(), 20296);
+    }
 }
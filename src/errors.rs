@@ -2,14 +2,18 @@
 //! The library's possible errors.
 
 use core::fmt;
+use std::error::Error;
 use std::fmt::Formatter;
+use std::io;
+use std::sync::Arc;
 
 /// Result of Type and GDError.
 pub type GDResult<T> = Result<T, GDError>;
 
-/// GameDigError.
-#[derive(Debug, Clone)]
-pub enum GDError {
+/// What went wrong, with its details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GDErrorKind {
     /// The received packet was bigger than the buffer size.
     PacketOverflow(String),
     /// The received packet was shorter than the expected one.
@@ -22,8 +26,8 @@ pub enum GDError {
     PacketReceive(String),
     /// Couldn't decompress data.
     Decompress(String),
-    /// Unknown cast while translating a value to an enum.
-    UnknownEnumCast,
+    /// Unknown cast while translating a value to an enum (what value, to what).
+    UnknownEnumCast(String),
     /// The server queried is not from the queried game.
     BadGame(String),
     /// Couldn't bind a socket.
@@ -34,32 +38,202 @@ pub enum GDError {
     SocketConnect(String),
     /// Couldn't parse a json string.
     JsonParse(String),
-    /// Couldn't automatically query.
-    AutoQuery,
+    /// Couldn't automatically query (why every attempt failed).
+    AutoQuery(String),
     /// A protocol-defined expected format was not met.
     ProtocolFormat(String),
     /// Couldn't parse a value.
     TypeParse(String),
+    /// A service replied with an error (its id and message).
+    ServiceError(u32, String),
 }
 
-impl fmt::Display for GDError {
+impl GDErrorKind {
+    /// Makes an error of this kind that was caused by another one (example: an `io::Error`).
+    pub fn context<E: Error + Send + Sync + 'static>(self, source: E) -> GDError {
+        GDError {
+            kind: self,
+            stage: None,
+            source: Some(Arc::new(source))
+        }
+    }
+}
+
+impl fmt::Display for GDErrorKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            GDError::PacketOverflow(details) => write!(f, "Packet overflow: {details}"),
-            GDError::PacketUnderflow(details) => write!(f, "Packet underflow: {details}"),
-            GDError::PacketBad(details) => write!(f, "Packet bad: {details}"),
-            GDError::PacketSend(details) => write!(f, "Couldn't send a packet: {details}"),
-            GDError::PacketReceive(details) => write!(f, "Couldn't receive a packet: {details}"),
-            GDError::Decompress(details) => write!(f, "Couldn't decompress data: {details}"),
-            GDError::UnknownEnumCast => write!(f, "Unknown enum cast encountered."),
-            GDError::BadGame(details) => write!(f, "Queried another game that the supposed one: {details}"),
-            GDError::SocketBind(details) => write!(f, "Socket bind: {details}"),
-            GDError::InvalidInput(details) => write!(f, "Invalid input: {details}"),
-            GDError::SocketConnect(details) => write!(f, "Socket connect: {details}"),
-            GDError::JsonParse(details) => write!(f, "Json parse: {details}"),
-            GDError::AutoQuery => write!(f, "Auto query failed."),
-            GDError::ProtocolFormat(details) => write!(f, "Protocol rule: {details}"),
-            GDError::TypeParse(details) => write!(f, "Type parse: {details}"),
+            GDErrorKind::PacketOverflow(details) => write!(f, "Packet overflow: {details}"),
+            GDErrorKind::PacketUnderflow(details) => write!(f, "Packet underflow: {details}"),
+            GDErrorKind::PacketBad(details) => write!(f, "Packet bad: {details}"),
+            GDErrorKind::PacketSend(details) => write!(f, "Couldn't send a packet: {details}"),
+            GDErrorKind::PacketReceive(details) => write!(f, "Couldn't receive a packet: {details}"),
+            GDErrorKind::Decompress(details) => write!(f, "Couldn't decompress data: {details}"),
+            GDErrorKind::UnknownEnumCast(details) => write!(f, "Unknown enum cast encountered: {details}"),
+            GDErrorKind::BadGame(details) => write!(f, "Queried another game that the supposed one: {details}"),
+            GDErrorKind::SocketBind(details) => write!(f, "Socket bind: {details}"),
+            GDErrorKind::InvalidInput(details) => write!(f, "Invalid input: {details}"),
+            GDErrorKind::SocketConnect(details) => write!(f, "Socket connect: {details}"),
+            GDErrorKind::JsonParse(details) => write!(f, "Json parse: {details}"),
+            GDErrorKind::AutoQuery(details) => write!(f, "Auto query failed: {details}"),
+            GDErrorKind::ProtocolFormat(details) => write!(f, "Protocol rule: {details}"),
+            GDErrorKind::TypeParse(details) => write!(f, "Type parse: {details}"),
+            GDErrorKind::ServiceError(id, message) => write!(f, "Service error {id}: {message}"),
+        }
+    }
+}
+
+/// The stage of a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stage {
+    /// Creating the socket (resolving, binding and connecting).
+    Connect,
+    /// Getting a challenge from the server, which the next requests have to send.
+    Challenge,
+    /// Getting the server's information.
+    Info,
+    /// Getting the server's players.
+    Players,
+    /// Getting the server's rules.
+    Rules
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Connect => write!(f, "connect"),
+            Stage::Challenge => write!(f, "challenge"),
+            Stage::Info => write!(f, "info"),
+            Stage::Players => write!(f, "players"),
+            Stage::Rules => write!(f, "rules")
+        }
+    }
+}
+
+/// GameDigError: what went wrong, at which stage of the query (when known) and the error that caused it
+/// (when there is one, available as the [source](Error::source), it isn't kept when serialized).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GDError {
+    kind: GDErrorKind,
+    stage: Option<Stage>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<Arc<dyn Error + Send + Sync>>
+}
+
+impl GDError {
+    /// What went wrong.
+    pub fn kind(&self) -> &GDErrorKind {
+        &self.kind
+    }
+
+    /// The stage of the query that failed, if it's known.
+    pub fn stage(&self) -> Option<Stage> {
+        self.stage
+    }
+
+    /// Records the stage that failed, an already recorded one is kept (being the more precise one).
+    pub fn at(mut self, stage: Stage) -> Self {
+        self.stage.get_or_insert(stage);
+        self
+    }
+
+    /// Whether the query failed because something (the socket or the query's deadline) timed out.
+    pub fn is_timeout(&self) -> bool {
+        let source = match &self.source {
+            None => return false,
+            Some(source) => source
+        };
+
+        if let Some(error) = source.downcast_ref::<GDError>() {
+            return error.is_timeout();
+        }
+
+        #[cfg(feature = "async")]
+        if source.downcast_ref::<tokio::time::error::Elapsed>().is_some() {
+            return true;
         }
+
+        //a timed out blocking read is a `WouldBlock` on some platforms
+        matches!(self.io_error_kind(), Some(io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock))
+    }
+
+    /// Whether the query could succeed if it's tried again, as the network failed for a moment (something timed
+    /// out or the connection got interrupted), not the server (example: a refused connection isn't retryable).
+    pub fn is_retryable(&self) -> bool {
+        let is_network_error = matches!(self.kind, GDErrorKind::PacketSend(_) | GDErrorKind::PacketReceive(_) | GDErrorKind::SocketConnect(_));
+        let is_transient = matches!(self.io_error_kind(), Some(io::ErrorKind::Interrupted | io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe));
+
+        self.is_timeout() || (is_network_error && is_transient)
+    }
+
+    /// The kind of the I/O error that caused this one, if one did (directly or through other errors).
+    fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        let source = self.source.as_ref()?;
+        match source.downcast_ref::<GDError>() {
+            Some(error) => error.io_error_kind(),
+            None => source.downcast_ref::<io::Error>().map(io::Error::kind)
+        }
+    }
+}
+
+impl From<GDErrorKind> for GDError {
+    fn from(kind: GDErrorKind) -> Self {
+        Self {
+            kind,
+            stage: None,
+            source: None
+        }
+    }
+}
+
+impl fmt::Display for GDError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.stage {
+            None => write!(f, "{}", self.kind),
+            Some(stage) => write!(f, "{} (stage: {stage})", self.kind)
+        }
+    }
+}
+
+impl Error for GDError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|source| source.as_ref() as &(dyn Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_and_timeout() {
+        let error = GDErrorKind::PacketReceive("Timed out.".to_string())
+            .context(io::Error::new(io::ErrorKind::TimedOut, "Timed out."))
+            .at(Stage::Players)
+            .at(Stage::Info);
+
+        assert_eq!(error.stage(), Some(Stage::Players));
+        assert_eq!(error.to_string(), "Couldn't receive a packet: Timed out. (stage: players)");
+        assert!(error.is_timeout());
+        assert!(error.is_retryable());
+        assert!(error.source().unwrap().downcast_ref::<io::Error>().is_some());
+
+        let error = GDError::from(GDErrorKind::PacketBad("Bad.".to_string()));
+        assert!(error.source().is_none());
+        assert!(!error.is_timeout());
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn retryable_network_errors() {
+        let reset = GDErrorKind::PacketSend("Reset.".to_string()).context(io::Error::new(io::ErrorKind::ConnectionReset, "Reset."));
+        assert!(reset.is_retryable());
+        assert!(!reset.is_timeout());
+
+        let refused = GDErrorKind::SocketConnect("Refused.".to_string()).context(io::Error::new(io::ErrorKind::ConnectionRefused, "Refused."));
+        assert!(!refused.is_retryable());
+
+        assert!(!GDError::from(GDErrorKind::PacketReceive("No reason.".to_string())).is_retryable());
     }
 }
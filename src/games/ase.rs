@@ -1,12 +1,26 @@
 use crate::GDResult;
+use crate::games::QueryOptions;
 use crate::protocols::valve;
 use crate::protocols::valve::{game, SteamID};
 
 pub fn query(address: &str, port: Option<u16>) -> GDResult<game::Response> {
-    let valve_response = valve::query(address, match port {
-        None => 27015,
-        Some(port) => port
-    }, SteamID::ASE.as_app(), None, None)?;
+    query_with_options(address, port, &QueryOptions::default())
+}
+
+pub fn query_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<game::Response> {
+    let valve_response = valve::query(address, port.unwrap_or(27015), SteamID::ASE.as_app(), options.gather_settings.clone(), options.timeout_settings.clone())?;
+
+    Ok(game::Response::new_from_valve_response(valve_response))
+}
+
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: Option<u16>) -> GDResult<game::Response> {
+    query_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+#[cfg(feature = "async")]
+pub async fn query_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<game::Response> {
+    let valve_response = valve::query_async(address, port.unwrap_or(27015), SteamID::ASE.as_app(), options.gather_settings.clone(), options.timeout_settings.clone()).await?;
 
     Ok(game::Response::new_from_valve_response(valve_response))
 }
@@ -0,0 +1,22 @@
+use crate::GDResult;
+use crate::games::QueryOptions;
+use crate::protocols::gamespy;
+use crate::protocols::gamespy::Response;
+
+pub fn query(address: &str, port: Option<u16>) -> GDResult<Response> {
+    query_with_options(address, port, &QueryOptions::default())
+}
+
+pub fn query_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    gamespy::three::query(address, port.unwrap_or(64087), options.timeout_settings.clone())
+}
+
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: Option<u16>) -> GDResult<Response> {
+    query_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+#[cfg(feature = "async")]
+pub async fn query_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    gamespy::three::query_async(address, port.unwrap_or(64087), options.timeout_settings.clone()).await
+}
@@ -1,54 +1,324 @@
-use crate::{GDError, GDResult};
+use crate::GDResult;
+use crate::games::QueryOptions;
 use crate::protocols::minecraft;
-use crate::protocols::minecraft::{Response, LegacyGroup, BedrockResponse};
+use crate::protocols::minecraft::{auto_query_error, Response, LegacyGroup, BedrockResponse};
+use crate::resolver::{resolve_ip, Resolver, SystemResolver};
+#[cfg(feature = "async")]
+use crate::GDErrorKind;
 
 /// Query with all the protocol variants one by one (Java -> Bedrock -> Legacy (1.6 -> 1.4 -> Beta 1.8)).
 pub fn query(address: &str, port: Option<u16>) -> GDResult<Response> {
-    if let Ok(response) = query_java(address, port) {
-        return Ok(response);
-    }
+    query_with_options(address, port, &QueryOptions::default())
+}
 
-    if let Ok(response) = query_bedrock(address, port) {
-        return Ok(Response::from_bedrock_response(response));
-    }
+/// Query with all the protocol variants one by one, with the given options (there's nothing to gather).
+pub fn query_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_with_resolver(address, port, options, &SystemResolver)
+}
 
-    if let Ok(response) = query_legacy(address, port) {
-        return Ok(response);
-    }
+/// Query with all the protocol variants one by one, with the given options, resolving the address (and the
+/// Java SRV record, if the port isn't given) with the given resolver.
+pub fn query_with_resolver(address: &str, port: Option<u16>, options: &QueryOptions, resolver: &dyn Resolver) -> GDResult<Response> {
+    let java_error = match query_java_with_resolver(address, port, options, resolver) {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    let bedrock_error = match query_bedrock_with_resolver(address, port, options, resolver) {
+        Ok(response) => return Ok(Response::from_bedrock_response(response)),
+        Err(error) => error
+    };
 
-    Err(GDError::AutoQuery)
+    let legacy_error = match query_legacy_with_resolver(address, port, options, resolver) {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    Err(auto_query_error(&[("Java", java_error), ("Bedrock", bedrock_error), ("Legacy", legacy_error)]))
 }
 
-/// Query a Java Server.
+/// Query a Java Server, if the port isn't given, the address's SRV record (if any) is used.
 pub fn query_java(address: &str, port: Option<u16>) -> GDResult<Response> {
-    minecraft::query_java(address, port_or_java_default(port), None)
+    query_java_with_options(address, port, &QueryOptions::default())
+}
+
+/// Query a Java Server with the given options.
+pub fn query_java_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_java_with_resolver(address, port, options, &SystemResolver)
+}
+
+/// Query a Java Server with the given options, resolving the address (and the SRV record, if the port isn't
+/// given) with the given resolver.
+pub fn query_java_with_resolver(address: &str, port: Option<u16>, options: &QueryOptions, resolver: &dyn Resolver) -> GDResult<Response> {
+    let (ip, port) = resolve_java(resolver, address, port)?;
+    minecraft::query_java(&ip, port, options.timeout_settings.clone())
 }
 
 /// Query a (Java) Legacy Server (1.6 -> 1.4 -> Beta 1.8).
 pub fn query_legacy(address: &str, port: Option<u16>) -> GDResult<Response> {
-    minecraft::query_legacy(address, port_or_java_default(port), None)
+    query_legacy_with_options(address, port, &QueryOptions::default())
+}
+
+/// Query a (Java) Legacy Server with the given options.
+pub fn query_legacy_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_legacy_with_resolver(address, port, options, &SystemResolver)
+}
+
+/// Query a (Java) Legacy Server with the given options, resolving the address with the given resolver.
+pub fn query_legacy_with_resolver(address: &str, port: Option<u16>, options: &QueryOptions, resolver: &dyn Resolver) -> GDResult<Response> {
+    let (ip, port) = resolve_java(resolver, address, port)?;
+    minecraft::query_legacy(&ip, port, options.timeout_settings.clone())
 }
 
 /// Query a specific (Java) Legacy Server.
 pub fn query_legacy_specific(group: LegacyGroup, address: &str, port: Option<u16>) -> GDResult<Response> {
-    minecraft::query_legacy_specific(group, address, port_or_java_default(port), None)
+    query_legacy_specific_with_options(group, address, port, &QueryOptions::default())
+}
+
+/// Query a specific (Java) Legacy Server with the given options.
+pub fn query_legacy_specific_with_options(group: LegacyGroup, address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_legacy_specific_with_resolver(group, address, port, options, &SystemResolver)
+}
+
+/// Query a specific (Java) Legacy Server with the given options, resolving the address with the given resolver.
+pub fn query_legacy_specific_with_resolver(group: LegacyGroup, address: &str, port: Option<u16>, options: &QueryOptions, resolver: &dyn Resolver) -> GDResult<Response> {
+    let (ip, port) = resolve_java(resolver, address, port)?;
+    minecraft::query_legacy_specific(group, &ip, port, options.timeout_settings.clone())
 }
 
 /// Query a Bedrock Server.
 pub fn query_bedrock(address: &str, port: Option<u16>) -> GDResult<BedrockResponse> {
-    minecraft::query_bedrock(address, port_or_bedrock_default(port), None)
+    query_bedrock_with_options(address, port, &QueryOptions::default())
+}
+
+/// Query a Bedrock Server with the given options.
+pub fn query_bedrock_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<BedrockResponse> {
+    query_bedrock_with_resolver(address, port, options, &SystemResolver)
+}
+
+/// Query a Bedrock Server with the given options, resolving the address with the given resolver.
+pub fn query_bedrock_with_resolver(address: &str, port: Option<u16>, options: &QueryOptions, resolver: &dyn Resolver) -> GDResult<BedrockResponse> {
+    let (ip, port) = resolve_bedrock(resolver, address, port)?;
+    minecraft::query_bedrock(&ip, port, options.timeout_settings.clone())
+}
+
+/// Asynchronously query with all the protocol variants one by one (Java -> Bedrock -> Legacy (1.6 -> 1.4 -> Beta 1.8)).
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: Option<u16>) -> GDResult<Response> {
+    query_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+/// Asynchronously query with all the protocol variants one by one, with the given options (there's nothing to gather).
+#[cfg(feature = "async")]
+pub async fn query_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_with_resolver_async(address, port, options, SystemResolver).await
+}
+
+/// Asynchronously query with all the protocol variants one by one, same as [query_with_resolver] (the
+/// resolver being blocking, it's used on tokio's blocking threads).
+#[cfg(feature = "async")]
+pub async fn query_with_resolver_async<R: Resolver + Clone + Send + 'static>(address: &str, port: Option<u16>, options: &QueryOptions, resolver: R) -> GDResult<Response> {
+    let java_error = match query_java_with_resolver_async(address, port, options, resolver.clone()).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    let bedrock_error = match query_bedrock_with_resolver_async(address, port, options, resolver.clone()).await {
+        Ok(response) => return Ok(Response::from_bedrock_response(response)),
+        Err(error) => error
+    };
+
+    let legacy_error = match query_legacy_with_resolver_async(address, port, options, resolver).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    Err(auto_query_error(&[("Java", java_error), ("Bedrock", bedrock_error), ("Legacy", legacy_error)]))
+}
+
+/// Asynchronously query a Java Server.
+#[cfg(feature = "async")]
+pub async fn query_java_async(address: &str, port: Option<u16>) -> GDResult<Response> {
+    query_java_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+/// Asynchronously query a Java Server with the given options.
+#[cfg(feature = "async")]
+pub async fn query_java_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_java_with_resolver_async(address, port, options, SystemResolver).await
+}
+
+/// Asynchronously query a Java Server, same as [query_java_with_resolver].
+#[cfg(feature = "async")]
+pub async fn query_java_with_resolver_async<R: Resolver + Send + 'static>(address: &str, port: Option<u16>, options: &QueryOptions, resolver: R) -> GDResult<Response> {
+    let (ip, port) = resolve_java_async(resolver, address, port).await?;
+    minecraft::query_java_async(&ip, port, options.timeout_settings.clone()).await
+}
+
+/// Asynchronously query a (Java) Legacy Server (1.6 -> 1.4 -> Beta 1.8).
+#[cfg(feature = "async")]
+pub async fn query_legacy_async(address: &str, port: Option<u16>) -> GDResult<Response> {
+    query_legacy_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+/// Asynchronously query a (Java) Legacy Server with the given options.
+#[cfg(feature = "async")]
+pub async fn query_legacy_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_legacy_with_resolver_async(address, port, options, SystemResolver).await
+}
+
+/// Asynchronously query a (Java) Legacy Server, same as [query_legacy_with_resolver].
+#[cfg(feature = "async")]
+pub async fn query_legacy_with_resolver_async<R: Resolver + Send + 'static>(address: &str, port: Option<u16>, options: &QueryOptions, resolver: R) -> GDResult<Response> {
+    let (ip, port) = resolve_java_async(resolver, address, port).await?;
+    minecraft::query_legacy_async(&ip, port, options.timeout_settings.clone()).await
+}
+
+/// Asynchronously query a specific (Java) Legacy Server.
+#[cfg(feature = "async")]
+pub async fn query_legacy_specific_async(group: LegacyGroup, address: &str, port: Option<u16>) -> GDResult<Response> {
+    query_legacy_specific_with_options_async(group, address, port, &QueryOptions::default()).await
+}
+
+/// Asynchronously query a specific (Java) Legacy Server with the given options.
+#[cfg(feature = "async")]
+pub async fn query_legacy_specific_with_options_async(group: LegacyGroup, address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    query_legacy_specific_with_resolver_async(group, address, port, options, SystemResolver).await
+}
+
+/// Asynchronously query a specific (Java) Legacy Server, same as [query_legacy_specific_with_resolver].
+#[cfg(feature = "async")]
+pub async fn query_legacy_specific_with_resolver_async<R: Resolver + Send + 'static>(group: LegacyGroup, address: &str, port: Option<u16>, options: &QueryOptions, resolver: R) -> GDResult<Response> {
+    let (ip, port) = resolve_java_async(resolver, address, port).await?;
+    minecraft::query_legacy_specific_async(group, &ip, port, options.timeout_settings.clone()).await
+}
+
+/// Asynchronously query a Bedrock Server.
+#[cfg(feature = "async")]
+pub async fn query_bedrock_async(address: &str, port: Option<u16>) -> GDResult<BedrockResponse> {
+    query_bedrock_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+/// Asynchronously query a Bedrock Server with the given options.
+#[cfg(feature = "async")]
+pub async fn query_bedrock_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<BedrockResponse> {
+    query_bedrock_with_resolver_async(address, port, options, SystemResolver).await
+}
+
+/// Asynchronously query a Bedrock Server, same as [query_bedrock_with_resolver].
+#[cfg(feature = "async")]
+pub async fn query_bedrock_with_resolver_async<R: Resolver + Send + 'static>(address: &str, port: Option<u16>, options: &QueryOptions, resolver: R) -> GDResult<BedrockResponse> {
+    let (ip, port) = blocking_resolve(address, move |address| resolve_bedrock(&resolver, &address, port)).await?;
+    minecraft::query_bedrock_async(&ip, port, options.timeout_settings.clone()).await
 }
 
 fn port_or_java_default(port: Option<u16>) -> u16 {
-    match port {
-        None => 25565,
-        Some(port) => port
-    }
+    port.unwrap_or(25565)
 }
 
 fn port_or_bedrock_default(port: Option<u16>) -> u16 {
-    match port {
-        None => 19132,
-        Some(port) => port
+    port.unwrap_or(19132)
+}
+
+/// Java servers can be behind a `_minecraft._tcp` SRV record, which (if there is one and the port isn't given)
+/// overrides the host and the port, the host is then resolved to an ip.
+fn resolve_java(resolver: &dyn Resolver, address: &str, port: Option<u16>) -> GDResult<(String, u16)> {
+    let srv_target = match port.is_none() && address.parse::<std::net::IpAddr>().is_err() {
+        false => None,
+        //not having a SRV record (or not being able to look it up) isn't an error
+        true => resolver.resolve_srv(&format!("_minecraft._tcp.{address}")).ok().flatten()
+    };
+
+    let (host, port) = match srv_target {
+        Some(target) => (target.host, target.port),
+        None => (address.to_string(), port_or_java_default(port))
+    };
+
+    Ok((resolve_ip(resolver, &host)?.to_string(), port))
+}
+
+/// Bedrock servers don't have SRV records, the host is just resolved to an ip.
+fn resolve_bedrock(resolver: &dyn Resolver, address: &str, port: Option<u16>) -> GDResult<(String, u16)> {
+    Ok((resolve_ip(resolver, address)?.to_string(), port_or_bedrock_default(port)))
+}
+
+/// Resolves on tokio's blocking threads (as the resolvers are blocking), an ip needs no lookup, so it's
+/// resolved right away.
+#[cfg(feature = "async")]
+async fn blocking_resolve<T: Send + 'static>(address: &str, resolve: impl FnOnce(String) -> GDResult<T> + Send + 'static) -> GDResult<T> {
+    if address.parse::<std::net::IpAddr>().is_ok() {
+        return resolve(address.to_string());
+    }
+
+    let address = address.to_string();
+    tokio::task::spawn_blocking(move || resolve(address)).await
+        .map_err(|e| GDErrorKind::InvalidInput(format!("Couldn't resolve the address: {e}")).context(e))?
+}
+
+#[cfg(feature = "async")]
+async fn resolve_java_async<R: Resolver + Send + 'static>(resolver: R, address: &str, port: Option<u16>) -> GDResult<(String, u16)> {
+    blocking_resolve(address, move |address| resolve_java(&resolver, &address, port)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use crate::GDErrorKind;
+    use crate::resolver::SrvTarget;
+    use super::*;
+
+    #[derive(Clone)]
+    struct FakeResolver;
+
+    impl Resolver for FakeResolver {
+        fn resolve(&self, host: &str) -> GDResult<Vec<IpAddr>> {
+            match host {
+                "play.example.net" => Ok(vec![IpAddr::from([10, 0, 0, 1])]),
+                "node.example.net" => Ok(vec![IpAddr::from([10, 0, 0, 2])]),
+                _ => Err(GDErrorKind::InvalidInput(host.to_string()).into())
+            }
+        }
+
+        fn resolve_srv(&self, name: &str) -> GDResult<Option<SrvTarget>> {
+            Ok(match name {
+                "_minecraft._tcp.play.example.net" => Some(SrvTarget {
+                    host: "node.example.net".to_string(),
+                    port: 25600
+                }),
+                _ => None
+            })
+        }
+    }
+
+    #[test]
+    fn srv_overrides_host_and_port() {
+        assert_eq!(resolve_java(&FakeResolver, "play.example.net", None).unwrap(), ("10.0.0.2".to_string(), 25600));
+    }
+
+    #[test]
+    fn given_port_skips_srv() {
+        assert_eq!(resolve_java(&FakeResolver, "play.example.net", Some(25570)).unwrap(), ("10.0.0.1".to_string(), 25570));
+        assert_eq!(resolve_java(&FakeResolver, "10.0.0.3", None).unwrap(), ("10.0.0.3".to_string(), 25565));
+    }
+
+    #[test]
+    fn bedrock_resolves_without_srv() {
+        assert_eq!(resolve_bedrock(&FakeResolver, "play.example.net", None).unwrap(), ("10.0.0.1".to_string(), 19132));
+        let error = query_bedrock_with_resolver("unknown.example.net", None, &QueryOptions::default(), &FakeResolver).unwrap_err();
+        assert!(matches!(error.kind(), GDErrorKind::InvalidInput(host) if host == "unknown.example.net"));
+    }
+
+    #[test]
+    fn injected_resolver() {
+        let error = query_with_resolver("unknown.example.net", None, &QueryOptions::default(), &FakeResolver).unwrap_err();
+        assert!(matches!(error.kind(), GDErrorKind::AutoQuery(details) if details.contains("unknown.example.net")));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_resolving() {
+        assert_eq!(resolve_java_async(FakeResolver, "play.example.net", None).await.unwrap(), ("10.0.0.2".to_string(), 25600));
+        assert_eq!(resolve_java_async(FakeResolver, "10.0.0.3", Some(25570)).await.unwrap(), ("10.0.0.3".to_string(), 25570));
+        assert!(query_java_with_resolver_async("unknown.example.net", None, &QueryOptions::default(), FakeResolver).await.is_err());
     }
 }
@@ -59,3 +59,37 @@ pub mod doi;
 pub mod hldms;
 /// Risk of Rain 2
 pub mod ror2;
+/// Quake 1 (QuakeWorld)
+pub mod quake1;
+/// Quake 2
+pub mod quake2;
+/// Quake 3: Arena
+pub mod quake3;
+/// Urban Terror
+pub mod urt;
+/// Wolfenstein: Enemy Territory
+pub mod wet;
+/// Call of Duty
+pub mod cod;
+/// Call of Duty 2
+pub mod cod2;
+/// Call of Duty 4: Modern Warfare
+pub mod cod4;
+/// Star Wars Jedi Knight: Jedi Academy
+pub mod jka;
+/// Battlefield 2
+pub mod bf2;
+/// Halo: Combat Evolved
+pub mod haloce;
+/// Unreal Tournament 2004
+pub mod ut2004;
+/// Crysis
+pub mod crysis;
+
+/// The options of the games' queries.
+mod options;
+pub use options::*;
+
+/// The registry of all the supported games.
+mod registry;
+pub use registry::*;
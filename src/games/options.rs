@@ -0,0 +1,12 @@
+use crate::protocols::types::TimeoutSettings;
+use crate::protocols::valve::GatheringSettings;
+
+/// The options of a game's `query_with_options`, the defaults (all None) being the ones `query` uses.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryOptions {
+    /// The timeouts and the retries (see [TimeoutSettings::new_with_retry]).
+    pub timeout_settings: Option<TimeoutSettings>,
+    /// What to gather, used only by the games that use the Valve protocol.
+    pub gather_settings: Option<GatheringSettings>
+}
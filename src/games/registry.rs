@@ -0,0 +1,537 @@
+use crate::{GDErrorKind, GDResult};
+use crate::games::*;
+use crate::protocols::minecraft::LegacyGroup;
+use crate::protocols::types::GenericResponse;
+
+/// The protocol that a game uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Protocol {
+    Valve,
+    Minecraft,
+    QuakeOne,
+    QuakeTwo,
+    QuakeThree,
+    GameSpyOne,
+    GameSpyTwo,
+    GameSpyThree
+}
+
+/// A supported game's definition.
+#[derive(Debug, Clone, Copy)]
+pub struct Game {
+    /// The game's identifier (the same as its module's name, example: `tf2`, or the module's name and the
+    /// variant that is queried, example: `mc_bedrock`).
+    pub id: &'static str,
+    /// The game's full name.
+    pub name: &'static str,
+    /// The protocol that the game uses.
+    pub protocol: Protocol,
+    /// The default query port.
+    pub default_port: u16,
+    /// How far the query port is from the game (connection) port (None if they aren't related).
+    pub query_port_offset: Option<u16>,
+    query: fn(&str, Option<u16>) -> GDResult<GenericResponse>,
+    query_with_options: fn(&str, Option<u16>, &QueryOptions) -> GDResult<GenericResponse>
+}
+
+impl Game {
+    /// Query the game, passing None as the port uses the default one.
+    pub fn query(&self, address: &str, port: Option<u16>) -> GDResult<GenericResponse> {
+        (self.query)(address, port)
+    }
+
+    /// Query the game with the given options, same as the game's `query_with_options`.
+    pub fn query_with_options(&self, address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<GenericResponse> {
+        (self.query_with_options)(address, port, options)
+    }
+}
+
+/// Every supported game.
+pub static GAMES: &[Game] = &[
+    Game {
+        id: "tf2",
+        name: "Team Fortress 2",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| tf2::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| tf2::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "ts",
+        name: "The Ship",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| ts::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| ts::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "csgo",
+        name: "Counter-Strike: Global Offensive",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| csgo::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| csgo::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "css",
+        name: "Counter-Strike: Source",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| css::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| css::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "dods",
+        name: "Day of Defeat: Source",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| dods::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| dods::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "gm",
+        name: "Garry's Mod",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| gm::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| gm::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "l4d",
+        name: "Left 4 Dead",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| l4d::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| l4d::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "l4d2",
+        name: "Left 4 Dead 2",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| l4d2::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| l4d2::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "hl2dm",
+        name: "Half-Life 2 Deathmatch",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| hl2dm::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| hl2dm::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "aliens",
+        name: "Alien Swarm",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| aliens::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| aliens::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "asrd",
+        name: "Alien Swarm: Reactive Drop",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| asrd::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| asrd::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "ins",
+        name: "Insurgency",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| ins::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| ins::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "inss",
+        name: "Insurgency: Sandstorm",
+        protocol: Protocol::Valve,
+        default_port: 27131,
+        query_port_offset: Some(29),
+        query: |address, port| inss::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| inss::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "insmic",
+        name: "Insurgency: Modern Infantry Combat",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| insmic::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| insmic::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "cscz",
+        name: "Counter Strike: Condition Zero",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| cscz::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| cscz::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "dod",
+        name: "Day of Defeat",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| dod::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| dod::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "mc",
+        name: "Minecraft",
+        protocol: Protocol::Minecraft,
+        default_port: 25565,
+        query_port_offset: Some(0),
+        query: |address, port| mc::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| mc::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "mc_java",
+        name: "Minecraft (Java Edition)",
+        protocol: Protocol::Minecraft,
+        default_port: 25565,
+        query_port_offset: Some(0),
+        query: |address, port| mc::query_java(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| mc::query_java_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "mc_bedrock",
+        name: "Minecraft (Bedrock Edition)",
+        protocol: Protocol::Minecraft,
+        default_port: 19132,
+        query_port_offset: Some(0),
+        query: |address, port| mc::query_bedrock(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| mc::query_bedrock_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "mc_legacy",
+        name: "Minecraft (Java Edition, legacy)",
+        protocol: Protocol::Minecraft,
+        default_port: 25565,
+        query_port_offset: Some(0),
+        query: |address, port| mc::query_legacy(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| mc::query_legacy_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "mc_legacy_v1_6",
+        name: "Minecraft (Java Edition, 1.6)",
+        protocol: Protocol::Minecraft,
+        default_port: 25565,
+        query_port_offset: Some(0),
+        query: |address, port| mc::query_legacy_specific(LegacyGroup::V1_6, address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| mc::query_legacy_specific_with_options(LegacyGroup::V1_6, address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "mc_legacy_v1_4",
+        name: "Minecraft (Java Edition, 1.4 to 1.5)",
+        protocol: Protocol::Minecraft,
+        default_port: 25565,
+        query_port_offset: Some(0),
+        query: |address, port| mc::query_legacy_specific(LegacyGroup::V1_4, address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| mc::query_legacy_specific_with_options(LegacyGroup::V1_4, address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "mc_legacy_vb1_8",
+        name: "Minecraft (Java Edition, Beta 1.8 to 1.3)",
+        protocol: Protocol::Minecraft,
+        default_port: 25565,
+        query_port_offset: Some(0),
+        query: |address, port| mc::query_legacy_specific(LegacyGroup::VB1_8, address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| mc::query_legacy_specific_with_options(LegacyGroup::VB1_8, address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "sdtd",
+        name: "7 Days To Die",
+        protocol: Protocol::Valve,
+        default_port: 26900,
+        query_port_offset: Some(0),
+        query: |address, port| sdtd::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| sdtd::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "ase",
+        name: "ARK: Survival Evolved",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: None,
+        query: |address, port| ase::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| ase::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "unturned",
+        name: "Unturned",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| unturned::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| unturned::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "tf",
+        name: "The Forest",
+        protocol: Protocol::Valve,
+        default_port: 27016,
+        query_port_offset: Some(1),
+        query: |address, port| tf::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| tf::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "tfc",
+        name: "Team Fortress Classic",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| tfc::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| tfc::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "sc",
+        name: "Sven Co-op",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| sc::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| sc::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "rust",
+        name: "Rust",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: None,
+        query: |address, port| rust::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| rust::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "cs",
+        name: "Counter-Strike",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| cs::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| cs::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "arma2oa",
+        name: "ARMA 2: Operation Arrowhead",
+        protocol: Protocol::Valve,
+        default_port: 2304,
+        query_port_offset: Some(2),
+        query: |address, port| arma2oa::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| arma2oa::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "doi",
+        name: "Day of Infamy",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| doi::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| doi::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "hldms",
+        name: "Half-Life Deathmatch: Source",
+        protocol: Protocol::Valve,
+        default_port: 27015,
+        query_port_offset: Some(0),
+        query: |address, port| hldms::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| hldms::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "ror2",
+        name: "Risk of Rain 2",
+        protocol: Protocol::Valve,
+        default_port: 27016,
+        query_port_offset: Some(1),
+        query: |address, port| ror2::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| ror2::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "quake1",
+        name: "Quake 1 (QuakeWorld)",
+        protocol: Protocol::QuakeOne,
+        default_port: 27500,
+        query_port_offset: Some(0),
+        query: |address, port| quake1::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| quake1::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "quake2",
+        name: "Quake 2",
+        protocol: Protocol::QuakeTwo,
+        default_port: 27910,
+        query_port_offset: Some(0),
+        query: |address, port| quake2::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| quake2::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "quake3",
+        name: "Quake 3: Arena",
+        protocol: Protocol::QuakeThree,
+        default_port: 27960,
+        query_port_offset: Some(0),
+        query: |address, port| quake3::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| quake3::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "urt",
+        name: "Urban Terror",
+        protocol: Protocol::QuakeThree,
+        default_port: 27960,
+        query_port_offset: Some(0),
+        query: |address, port| urt::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| urt::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "wet",
+        name: "Wolfenstein: Enemy Territory",
+        protocol: Protocol::QuakeThree,
+        default_port: 27960,
+        query_port_offset: Some(0),
+        query: |address, port| wet::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| wet::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "cod",
+        name: "Call of Duty",
+        protocol: Protocol::QuakeThree,
+        default_port: 28960,
+        query_port_offset: Some(0),
+        query: |address, port| cod::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| cod::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "cod2",
+        name: "Call of Duty 2",
+        protocol: Protocol::QuakeThree,
+        default_port: 28960,
+        query_port_offset: Some(0),
+        query: |address, port| cod2::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| cod2::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "cod4",
+        name: "Call of Duty 4: Modern Warfare",
+        protocol: Protocol::QuakeThree,
+        default_port: 28960,
+        query_port_offset: Some(0),
+        query: |address, port| cod4::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| cod4::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "jka",
+        name: "Star Wars Jedi Knight: Jedi Academy",
+        protocol: Protocol::QuakeThree,
+        default_port: 29070,
+        query_port_offset: Some(0),
+        query: |address, port| jka::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| jka::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "bf2",
+        name: "Battlefield 2",
+        protocol: Protocol::GameSpyThree,
+        default_port: 29900,
+        query_port_offset: None,
+        query: |address, port| bf2::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| bf2::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "haloce",
+        name: "Halo: Combat Evolved",
+        protocol: Protocol::GameSpyTwo,
+        default_port: 2302,
+        query_port_offset: Some(0),
+        query: |address, port| haloce::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| haloce::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "ut2004",
+        name: "Unreal Tournament 2004",
+        protocol: Protocol::GameSpyOne,
+        default_port: 7787,
+        query_port_offset: Some(10),
+        query: |address, port| ut2004::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| ut2004::query_with_options(address, port, options).map(GenericResponse::from)
+    },
+    Game {
+        id: "crysis",
+        name: "Crysis",
+        protocol: Protocol::GameSpyThree,
+        default_port: 64087,
+        query_port_offset: Some(0),
+        query: |address, port| crysis::query(address, port).map(GenericResponse::from),
+        query_with_options: |address, port, options| crysis::query_with_options(address, port, options).map(GenericResponse::from)
+    }
+];
+
+/// Other identifiers of the games, for the ones whose usual name can't be a module's name.
+pub static ALIASES: [(&str, &str); 1] = [
+    ("7dtd", "sdtd")
+];
+
+/// Find a game by its identifier or one of its [aliases](ALIASES).
+pub fn find_game(id: &str) -> Option<&'static Game> {
+    let id = ALIASES.iter().find(|(alias, _)| *alias == id).map_or(id, |(_, id)| *id);
+    GAMES.iter().find(|game| game.id == id)
+}
+
+/// Query a game by its identifier (example: `tf2`), passing None as the port uses the game's default one.
+pub fn query_by_id(id: &str, address: &str, port: Option<u16>) -> GDResult<GenericResponse> {
+    find_game(id)
+        .ok_or_else(|| GDErrorKind::InvalidInput(format!("Unknown game: {id}")))?
+        .query(address, port)
+}
+
+/// Query a game by its identifier with the given options, same as [query_by_id].
+pub fn query_by_id_with_options(id: &str, address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<GenericResponse> {
+    find_game(id)
+        .ok_or_else(|| GDErrorKind::InvalidInput(format!("Unknown game: {id}")))?
+        .query_with_options(address, port, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_ids() {
+        for (index, game) in GAMES.iter().enumerate() {
+            assert!(GAMES[index + 1..].iter().all(|other| other.id != game.id), "{} is registered twice", game.id);
+        }
+
+        for (alias, id) in ALIASES.iter() {
+            assert!(GAMES.iter().all(|game| game.id != *alias), "{alias} is both an id and an alias");
+            assert!(GAMES.iter().any(|game| game.id == *id), "{alias} is an alias of the unknown {id}");
+        }
+    }
+
+    #[test]
+    fn find() {
+        assert_eq!(find_game("tf2").unwrap().name, "Team Fortress 2");
+        assert_eq!(find_game("bf2").unwrap().protocol, Protocol::GameSpyThree);
+        assert_eq!(find_game("7dtd").unwrap().id, "sdtd");
+        assert_eq!(find_game("mc_bedrock").unwrap().default_port, 19132);
+        assert!(find_game("not_a_game").is_none());
+        assert!(query_by_id("not_a_game", "127.0.0.1", None).is_err());
+        assert!(query_by_id_with_options("not_a_game", "127.0.0.1", None, &QueryOptions::default()).is_err());
+    }
+}
@@ -1,29 +1,35 @@
-use crate::GDResult;
+use std::time::Duration;
+use crate::{GDError, GDErrorKind, GDResult};
+use crate::games::QueryOptions;
+use crate::protocols::types::{GenericPlayer, GenericResponse, SpecificResponse};
 use crate::protocols::valve;
-use crate::protocols::valve::{Server, ServerRule, ServerPlayer, get_optional_extracted_data, SteamID};
+use crate::protocols::valve::{Server, ServerRule, ServerPlayer, get_optional_extracted_data, split_gathered, SteamID};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TheShipPlayer {
     pub name: String,
-    pub score: u32,
+    pub score: i32,
     pub duration: f32,
-    pub deaths: u32,
-    pub money: u32
+    pub deaths: i32,
+    pub money: i32
 }
 
 impl TheShipPlayer {
-    pub fn new_from_valve_player(player: &ServerPlayer) -> Self {
-        Self {
+    /// Fails if the player doesn't have The Ship's data (the deaths and the money).
+    pub fn new_from_valve_player(player: &ServerPlayer) -> GDResult<Self> {
+        Ok(Self {
             name: player.name.clone(),
             score: player.score,
             duration: player.duration,
-            deaths: player.deaths.unwrap(),
-            money: player.money.unwrap()
-        }
+            deaths: player.deaths.ok_or_else(|| GDErrorKind::PacketBad("Missing The Ship player's deaths.".to_string()))?,
+            money: player.money.ok_or_else(|| GDErrorKind::PacketBad("Missing The Ship player's money.".to_string()))?
+        })
     }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
     pub protocol: u8,
     pub name: String,
@@ -31,6 +37,8 @@ pub struct Response {
     pub game: String,
     pub players: u8,
     pub players_details: Vec<TheShipPlayer>,
+    /// Why the players couldn't be gathered, when gathering leniently.
+    pub players_error: Option<GDError>,
     pub max_players: u8,
     pub bots: u8,
     pub server_type: Server,
@@ -43,24 +51,32 @@ pub struct Response {
     pub tv_name: Option<String>,
     pub keywords: Option<String>,
     pub rules: Vec<ServerRule>,
+    /// Why the rules couldn't be gathered, when gathering leniently.
+    pub rules_error: Option<GDError>,
+    pub ping: Duration,
     pub mode: u8,
     pub witnesses: u8,
     pub duration: u8
 }
 
 impl Response {
-    pub fn new_from_valve_response(response: valve::Response) -> Self {
+    /// Fails if the response doesn't have The Ship's data (it wasn't queried as The Ship).
+    pub fn new_from_valve_response(response: valve::Response) -> GDResult<Self> {
         let (port, steam_id, tv_port, tv_name, keywords) = get_optional_extracted_data(response.info.extra_data);
 
-        let the_unwrapped_ship = response.info.the_ship.unwrap();
+        let the_unwrapped_ship = response.info.the_ship
+            .ok_or_else(|| GDErrorKind::BadGame("The response doesn't have The Ship's data.".to_string()))?;
+        let (players, players_error) = split_gathered(response.players);
+        let (rules, rules_error) = split_gathered(response.rules);
 
-        Self {
+        Ok(Self {
             protocol: response.info.protocol,
             name: response.info.name,
             map: response.info.map,
             game: response.info.game,
             players: response.info.players,
-            players_details: response.players.unwrap().iter().map(TheShipPlayer::new_from_valve_player).collect(),
+            players_details: players.iter().map(TheShipPlayer::new_from_valve_player).collect::<GDResult<_>>()?,
+            players_error,
             max_players: response.info.max_players,
             bots: response.info.bots,
             server_type: response.info.server_type,
@@ -72,19 +88,56 @@ impl Response {
             tv_port,
             tv_name,
             keywords,
-            rules: response.rules.unwrap(),
+            rules,
+            rules_error,
+            ping: response.ping,
             mode: the_unwrapped_ship.mode,
             witnesses: the_unwrapped_ship.witnesses,
             duration: the_unwrapped_ship.duration
+        })
+    }
+}
+
+impl From<Response> for GenericResponse {
+    fn from(response: Response) -> Self {
+        Self {
+            name: Some(response.name.clone()),
+            description: None,
+            game: Some(response.game.clone()),
+            game_version: Some(response.version.clone()),
+            map: Some(response.map.clone()),
+            players_maximum: response.max_players as u32,
+            players_online: response.players as u32,
+            players_bots: Some(response.bots as u32),
+            has_password: Some(response.has_password),
+            players: response.players_details.iter().map(|player| GenericPlayer {
+                name: player.name.clone(),
+                score: Some(player.score)
+            }).collect(),
+            ping: Some(response.ping),
+            inner: SpecificResponse::TheShip(response)
         }
     }
 }
 
 pub fn query(address: &str, port: Option<u16>) -> GDResult<Response> {
-    let valve_response = valve::query(address, match port {
-        None => 27015,
-        Some(port) => port
-    }, SteamID::TS.as_app(), None, None)?;
+    query_with_options(address, port, &QueryOptions::default())
+}
+
+pub fn query_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    let valve_response = valve::query(address, port.unwrap_or(27015), SteamID::TS.as_app(), options.gather_settings.clone(), options.timeout_settings.clone())?;
+
+    Response::new_from_valve_response(valve_response)
+}
+
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: Option<u16>) -> GDResult<Response> {
+    query_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+#[cfg(feature = "async")]
+pub async fn query_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response> {
+    let valve_response = valve::query_async(address, port.unwrap_or(27015), SteamID::TS.as_app(), options.gather_settings.clone(), options.timeout_settings.clone()).await?;
 
-    Ok(Response::new_from_valve_response(valve_response))
+    Response::new_from_valve_response(valve_response)
 }
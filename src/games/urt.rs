@@ -0,0 +1,23 @@
+use crate::GDResult;
+use crate::games::QueryOptions;
+use crate::protocols::quake;
+use crate::protocols::quake::three::Player;
+use crate::protocols::quake::Response;
+
+pub fn query(address: &str, port: Option<u16>) -> GDResult<Response<Player>> {
+    query_with_options(address, port, &QueryOptions::default())
+}
+
+pub fn query_with_options(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response<Player>> {
+    quake::three::query(address, port.unwrap_or(27960), options.timeout_settings.clone())
+}
+
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: Option<u16>) -> GDResult<Response<Player>> {
+    query_with_options_async(address, port, &QueryOptions::default()).await
+}
+
+#[cfg(feature = "async")]
+pub async fn query_with_options_async(address: &str, port: Option<u16>, options: &QueryOptions) -> GDResult<Response<Player>> {
+    quake::three::query_async(address, port.unwrap_or(27960), options.timeout_settings.clone()).await
+}
@@ -17,10 +17,15 @@
 
 pub mod errors;
 pub mod protocols;
+pub mod services;
 pub mod games;
+pub mod batch;
+pub mod resolver;
+pub mod socket;
+pub mod query;
 mod utils;
-mod socket;
 mod bufferer;
 
 pub use errors::*;
 pub use games::*;
+pub use query::Query;
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use crate::{GDErrorKind, GDResult};
+use crate::protocols::gamespy::{Player, Response, Team};
+
+/// A player's or team's fields, keyed by the field name.
+pub(crate) type Fields = HashMap<String, String>;
+
+/// The highest player or team index that is accepted, no server reports anywhere near as many.
+static MAXIMUM_INDEX: usize = 512;
+
+/// Strips the player (`score_`) or team (`score_t`) suffixes from a field name.
+pub(crate) fn field_name(name: &str) -> String {
+    name.strip_suffix("_t")
+        .or_else(|| name.strip_suffix('_'))
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// Sets a field of the item at the index, adding empty items as needed, an index above
+/// [MAXIMUM_INDEX] throws GDErrorKind::[PacketBad](GDErrorKind::PacketBad).
+pub(crate) fn set_field(items: &mut Vec<Fields>, index: usize, name: String, value: String) -> GDResult<()> {
+    let length = index.checked_add(1)
+        .filter(|_| index <= MAXIMUM_INDEX)
+        .ok_or_else(|| GDErrorKind::PacketBad(format!("The player or team index {index} is too high.")))?;
+
+    if items.len() < length {
+        items.resize_with(length, HashMap::new);
+    }
+
+    items[index].insert(name, value);
+    Ok(())
+}
+
+fn take_first(fields: &mut Fields, names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| fields.remove(*name))
+}
+
+fn take_parsed<T: std::str::FromStr>(fields: &mut Fields, names: &[&str]) -> Option<T> {
+    take_first(fields, names).and_then(|value| value.trim().parse().ok())
+}
+
+fn player_from_fields(mut fields: Fields) -> Player {
+    Player {
+        name: take_first(&mut fields, &["player", "playername", "name"]).unwrap_or_default(),
+        score: take_parsed(&mut fields, &["score"]),
+        ping: take_parsed(&mut fields, &["ping"]),
+        team: take_first(&mut fields, &["team"]),
+        kills: take_parsed(&mut fields, &["kills", "frags"]),
+        deaths: take_parsed(&mut fields, &["deaths"]),
+        fields
+    }
+}
+
+fn team_from_fields(mut fields: Fields) -> Team {
+    Team {
+        name: take_first(&mut fields, &["team", "teamname", "name"]).unwrap_or_default(),
+        score: take_parsed(&mut fields, &["score"]),
+        fields
+    }
+}
+
+fn parse_count(rules: &HashMap<String, String>, key: &str) -> GDResult<Option<u32>> {
+    rules.get(key)
+        .map(|value| value.trim().parse()
+            .map_err(|e| GDErrorKind::TypeParse(format!("Couldn't parse {key}.")).context(e)))
+        .transpose()
+}
+
+pub(crate) fn build_response(rules: HashMap<String, String>, players: Vec<Fields>, teams: Vec<Fields>) -> GDResult<Response> {
+    let players: Vec<Player> = players.into_iter()
+        .filter(|fields| !fields.is_empty())
+        .map(player_from_fields)
+        .collect();
+
+    Ok(Response {
+        name: rules.get("hostname").cloned().unwrap_or_default(),
+        map: rules.get("mapname").cloned().unwrap_or_default(),
+        game_type: rules.get("gametype").cloned(),
+        game_version: rules.get("gamever").cloned(),
+        has_password: rules.get("password")
+            .map(|value| value == "1" || value.eq_ignore_ascii_case("true")),
+        players_online: parse_count(&rules, "numplayers")?.unwrap_or(players.len() as u32),
+        players_maximum: parse_count(&rules, "maxplayers")?.unwrap_or(0),
+        players,
+        teams: teams.into_iter()
+            .filter(|fields| !fields.is_empty())
+            .map(team_from_fields)
+            .collect(),
+        rules
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_names() {
+        assert_eq!(field_name("player_"), "player");
+        assert_eq!(field_name("score_t"), "score");
+        assert_eq!(field_name("hostname"), "hostname");
+    }
+
+    #[test]
+    fn response() {
+        let rules = HashMap::from([
+            ("hostname".to_string(), "A Server".to_string()),
+            ("maxplayers".to_string(), "32".to_string()),
+            ("password".to_string(), "0".to_string())
+        ]);
+
+        let mut players = Vec::new();
+        set_field(&mut players, 1, "player".to_string(), "Second".to_string()).unwrap();
+        set_field(&mut players, 1, "score".to_string(), "-5".to_string()).unwrap();
+        set_field(&mut players, 1, "skill".to_string(), "3".to_string()).unwrap();
+        set_field(&mut players, 0, "player".to_string(), "First".to_string()).unwrap();
+
+        let response = build_response(rules, players, vec![]).unwrap();
+        assert_eq!(response.name, "A Server");
+        assert_eq!(response.players_online, 2);
+        assert_eq!(response.players_maximum, 32);
+        assert_eq!(response.has_password, Some(false));
+        assert_eq!(response.players[1].score, Some(-5));
+        assert_eq!(response.players[1].fields.get("skill").unwrap(), "3");
+    }
+
+    #[test]
+    fn huge_index() {
+        let mut players = Vec::new();
+
+        assert!(set_field(&mut players, 4_000_000_000, "player".to_string(), "Far".to_string()).is_err());
+        assert!(set_field(&mut players, usize::MAX, "player".to_string(), "Farthest".to_string()).is_err());
+        assert!(players.is_empty());
+    }
+}
@@ -0,0 +1,12 @@
+/// Shared parsing between the versions.
+mod common;
+/// All types used by the implementation.
+pub mod types;
+/// GameSpy 1, backslash delimited key/values split over multiple packets.
+pub mod one;
+/// GameSpy 2, a single packet with null delimited sections.
+pub mod two;
+/// GameSpy 3, challenge based with split packets.
+pub mod three;
+
+pub use types::*;
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use crate::{GDErrorKind, GDResult, Stage};
+use crate::protocols::gamespy::common::{build_response, Fields, set_field};
+use crate::protocols::gamespy::Response;
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::{DeadlineSocket, Socket, UdpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncUdpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
+
+static PACKET_SIZE: usize = 2048;
+static MAXIMUM_PACKETS: usize = 32;
+static REQUEST: &[u8] = b"\\status\\";
+
+/// A packet's key/values alongside its number (1 based).
+struct Packet {
+    number: usize,
+    is_final: bool,
+    pairs: Vec<(String, String)>
+}
+
+fn parse_packet(data: &[u8]) -> GDResult<Packet> {
+    let content = String::from_utf8_lossy(data);
+    let mut parts = content.split('\\');
+
+    if parts.next() != Some("") {
+        return Err(GDErrorKind::PacketBad("Packet must begin with a backslash.".to_string()).into());
+    }
+
+    let mut packet = Packet {
+        number: 0,
+        is_final: false,
+        pairs: Vec::new()
+    };
+
+    while let Some(key) = parts.next() {
+        match key {
+            //the final key doesn't have a value (it is the last key, so we might not even get an empty part)
+            "final" => packet.is_final = true,
+            "" => continue,
+            _ => {
+                let value = parts.next()
+                    .ok_or_else(|| GDErrorKind::PacketBad(format!("Key {key} has no value.")))?;
+
+                if key == "queryid" {
+                    //the query id is formatted as 'request.packet'
+                    packet.number = value.split('.').nth(1)
+                        .and_then(|number| number.parse().ok())
+                        .unwrap_or(1);
+                } else {
+                    packet.pairs.push((key.to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+
+    Ok(packet)
+}
+
+/// Checks if we have all the packets (the final one and everyone before it).
+fn is_complete(packets: &[Packet]) -> bool {
+    packets.iter()
+        .any(|packet| packet.is_final && packet.number.max(1) <= packets.len())
+}
+
+fn get_response(mut packets: Vec<Packet>) -> GDResult<Response> {
+    packets.sort_by_key(|packet| packet.number);
+
+    let mut rules = HashMap::new();
+    let mut players: Vec<Fields> = Vec::new();
+    let mut teams: Vec<Fields> = Vec::new();
+
+    for (key, value) in packets.into_iter().flat_map(|packet| packet.pairs) {
+        //players fields are suffixed by their index (score_3) and teams fields by t and their index (score_t1)
+        let indexed = key.rsplit_once('_').and_then(|(name, index)| {
+            match index.strip_prefix('t') {
+                Some(team_index) => team_index.parse().ok().map(|index| (name, index, true)),
+                None => index.parse().ok().map(|index| (name, index, false))
+            }
+        });
+
+        match indexed {
+            Some((name, index, true)) => set_field(&mut teams, index, name.to_string(), value)?,
+            Some((name, index, false)) => set_field(&mut players, index, name.to_string(), value)?,
+            None => {
+                rules.insert(key, value);
+            }
+        }
+    }
+
+    build_response(rules, players, teams)
+}
+
+fn check_packets_count(packets: &[Packet]) -> GDResult<()> {
+    match packets.len() > MAXIMUM_PACKETS {
+        true => Err(GDErrorKind::PacketOverflow("Received too many packets.".to_string()).into()),
+        false => Ok(())
+    }
+}
+
+fn query_socket<S: Socket>(socket: &mut S) -> GDResult<Response> {
+    socket.send(REQUEST)?;
+
+    let mut packets = Vec::new();
+    while !is_complete(&packets) {
+        packets.push(parse_packet(&socket.receive(Some(PACKET_SIZE))?)?);
+        check_packets_count(&packets)?;
+    }
+
+    get_response(packets)
+}
+
+/// Query a server that uses the GameSpy 1 protocol.
+pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry(timeout_settings, |timeout_settings| query_with_socket(UdpSocket::new(address, port)?, timeout_settings))
+}
+
+/// Query a server that uses the GameSpy 1 protocol through an already created socket.
+pub fn query_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let mut socket = DeadlineSocket::new(socket, &timeout_settings);
+    socket.apply_timeout(timeout_settings)?;
+
+    query_socket(&mut socket).map_err(|e| e.at(Stage::Info))
+}
+
+/// Asynchronously query a server that uses the GameSpy 1 protocol.
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry_async(timeout_settings, |timeout_settings| async move {
+        query_once_async(address, port, timeout_settings).await.map_err(|e| e.at(Stage::Info))
+    }).await
+}
+
+#[cfg(feature = "async")]
+async fn query_once_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    query_socket_async(AsyncUdpSocket::new(address, port).await?, timeout_settings).await
+}
+
+/// Asynchronously query a server that uses the GameSpy 1 protocol through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    with_deadline(timeout_settings, |timeout_settings| query_socket_async(socket, timeout_settings)).await.map_err(|e| e.at(Stage::Info))
+}
+
+#[cfg(feature = "async")]
+async fn query_socket_async<S: AsyncSocket + Send>(mut socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    socket.apply_timeout(timeout_settings)?;
+
+    socket.send(REQUEST).await?;
+
+    let mut packets = Vec::new();
+    while !is_complete(&packets) {
+        packets.push(parse_packet(&socket.receive(Some(PACKET_SIZE)).await?)?);
+        check_packets_count(&packets)?;
+    }
+
+    get_response(packets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reassembly() {
+        let second = parse_packet(b"\\player_1\\Second\\frags_1\\7\\team_t0\\Red\\queryid\\12.2\\final\\").unwrap();
+        let packets = vec![second];
+        assert!(!is_complete(&packets));
+
+        let first = parse_packet(b"\\hostname\\A Server\\numplayers\\2\\player_0\\First\\frags_0\\-1\\queryid\\12.1").unwrap();
+        let packets = vec![packets.into_iter().next().unwrap(), first];
+        assert!(is_complete(&packets));
+
+        let response = get_response(packets).unwrap();
+        assert_eq!(response.name, "A Server");
+        assert_eq!(response.players_online, 2);
+        assert_eq!(response.players[0].name, "First");
+        assert_eq!(response.players[0].kills, Some(-1));
+        assert_eq!(response.players[1].kills, Some(7));
+        assert_eq!(response.teams[0].name, "Red");
+        assert!(!response.rules.contains_key("queryid"));
+    }
+
+    #[test]
+    fn huge_player_index() {
+        let packets = vec![parse_packet(b"\\hostname\\A Server\\player_4000000000\\x\\final\\").unwrap()];
+        assert!(get_response(packets).is_err());
+    }
+
+    #[test]
+    fn bad_packet() {
+        assert!(parse_packet(b"hostname\\A Server").is_err());
+        assert!(parse_packet(b"\\hostname").is_err());
+    }
+}
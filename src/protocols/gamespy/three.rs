@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use crate::{GDErrorKind, GDResult, Stage};
+use crate::bufferer::{Bufferer, Endianess};
+use crate::protocols::gamespy::common::{build_response, field_name, Fields, set_field};
+use crate::protocols::gamespy::Response;
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::{DeadlineSocket, Socket, UdpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncUdpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
+
+static PACKET_SIZE: usize = 2048;
+static MAXIMUM_PACKETS: usize = 32;
+/// Some servers only keep the lower 4 bits of every byte, so we use a session id that doesn't get altered.
+static SESSION_ID: [u8; 4] = [0x04, 0x05, 0x06, 0x07];
+
+#[derive(Eq, PartialEq, Copy, Clone)]
+#[repr(u8)]
+enum Request {
+    Challenge = 0x09,
+    Status = 0x00
+}
+
+fn request_packet(kind: Request, challenge: Option<i32>) -> Vec<u8> {
+    let mut buf = vec![0xFE, 0xFD, kind as u8];
+    buf.extend(SESSION_ID);
+
+    if let Some(challenge) = challenge {
+        buf.extend(challenge.to_be_bytes());
+    }
+
+    if kind == Request::Status {
+        //ask for the server's info, the players and the teams, in the split packets format
+        buf.extend([0xFF, 0xFF, 0xFF, 0x01]);
+    }
+
+    buf
+}
+
+fn check_header(buffer: &mut Bufferer, kind: Request) -> GDResult<()> {
+    if buffer.get_u8()? != kind as u8 {
+        return Err(GDErrorKind::PacketBad("Invalid response type.".to_string()).into());
+    }
+
+    if buffer.get_u32()?.to_le_bytes() != SESSION_ID {
+        return Err(GDErrorKind::PacketBad("Response session id doesn't match the request.".to_string()).into());
+    }
+
+    Ok(())
+}
+
+fn parse_challenge(data: &[u8]) -> GDResult<i32> {
+    let mut buffer = Bufferer::new_with_data(Endianess::Little, data);
+    check_header(&mut buffer, Request::Challenge)?;
+
+    buffer.get_str_utf8()?.parse()
+        .map_err(|e| GDErrorKind::TypeParse("Couldn't parse the challenge.".to_string()).context(e))
+}
+
+/// A split packet.
+struct Packet {
+    index: u8,
+    is_last: bool,
+    /// The whole received packet (moved in, not copied), its payload begins at `payload_start`.
+    data: Vec<u8>,
+    payload_start: usize
+}
+
+impl Packet {
+    fn payload(&self) -> &[u8] {
+        &self.data[self.payload_start..]
+    }
+}
+
+fn parse_packet(data: Vec<u8>) -> GDResult<Packet> {
+    let mut buffer = Bufferer::new_with_data(Endianess::Little, &data);
+    check_header(&mut buffer, Request::Status)?;
+
+    if buffer.get_str_utf8()? != "splitnum" {
+        return Err(GDErrorKind::PacketBad("Missing the splitnum marker.".to_string()).into());
+    }
+
+    //the highest bit marks the last packet, the others are the packet's index
+    let number = buffer.get_u8()?;
+
+    Ok(Packet {
+        index: number & 0x7F,
+        is_last: number & 0x80 != 0,
+        payload_start: data.len() - buffer.remaining_length(),
+        data
+    })
+}
+
+/// Checks if we have all the packets (the last one and everyone before it).
+fn is_complete(packets: &[Packet]) -> bool {
+    packets.iter()
+        .any(|packet| packet.is_last && (packet.index as usize) < packets.len())
+}
+
+fn check_packets_count(packets: &[Packet]) -> GDResult<()> {
+    match packets.len() > MAXIMUM_PACKETS {
+        true => Err(GDErrorKind::PacketOverflow("Received too many packets.".to_string()).into()),
+        false => Ok(())
+    }
+}
+
+/// Checks if there is a complete (null terminated) string in front.
+fn has_string(buffer: &Bufferer) -> bool {
+    buffer.remaining_data().contains(&0)
+}
+
+/// Parses a players or teams section, every field being followed by its starting row and then the rows values.
+/// A field can continue in the next packet, the starting row telling where it left off.
+fn parse_fields(buffer: &mut Bufferer, items: &mut Vec<Fields>) -> GDResult<()> {
+    while has_string(buffer) {
+        let name = buffer.get_str_utf8()?;
+        if name.is_empty() {
+            break;
+        }
+
+        let name = field_name(name);
+        let offset = buffer.get_u8()? as usize;
+
+        let mut index = offset;
+        while has_string(buffer) {
+            let value = buffer.get_string_utf8()?;
+            if value.is_empty() {
+                break;
+            }
+
+            set_field(items, index, name.clone(), value)?;
+            index += 1;
+        }
+    }
+
+    Ok(())
+}
+
+fn get_response(mut packets: Vec<Packet>) -> GDResult<Response> {
+    packets.sort_by_key(|packet| packet.index);
+
+    let mut rules = HashMap::new();
+    let mut players = Vec::new();
+    let mut teams = Vec::new();
+
+    for packet in &packets {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, packet.payload());
+
+        //a packet can end with a cut off value, which is sent again in the next packet
+        while has_string(&buffer) {
+            match buffer.get_u8()? {
+                0 => while has_string(&buffer) {
+                    let key = buffer.get_string_utf8()?;
+                    if key.is_empty() || !has_string(&buffer) {
+                        break;
+                    }
+
+                    rules.insert(key, buffer.get_string_utf8()?);
+                },
+                1 => parse_fields(&mut buffer, &mut players)?,
+                2 => parse_fields(&mut buffer, &mut teams)?,
+                _ => return Err(GDErrorKind::PacketBad("Unknown section type.".to_string()).into())
+            }
+        }
+    }
+
+    build_response(rules, players, teams)
+}
+
+fn get_challenge<S: Socket>(socket: &mut S) -> GDResult<i32> {
+    socket.send(&request_packet(Request::Challenge, None))?;
+    parse_challenge(&socket.receive(Some(PACKET_SIZE))?)
+}
+
+fn query_socket<S: Socket>(socket: &mut S, challenge: bool) -> GDResult<Response> {
+    let challenge = match challenge {
+        false => None,
+        true => Some(get_challenge(socket).map_err(|e| e.at(Stage::Challenge))?)
+    };
+
+    socket.send(&request_packet(Request::Status, challenge))?;
+
+    let mut packets = Vec::new();
+    while !is_complete(&packets) {
+        packets.push(parse_packet(socket.receive(Some(PACKET_SIZE))?)?);
+        check_packets_count(&packets)?;
+    }
+
+    get_response(packets)
+}
+
+/// Query a server that uses the GameSpy 3 protocol.
+pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry(timeout_settings, |timeout_settings| query_with_socket(UdpSocket::new(address, port)?, timeout_settings))
+}
+
+/// Query a server that uses the GameSpy 3 protocol through an already created socket.
+pub fn query_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let mut socket = DeadlineSocket::new(socket, &timeout_settings);
+    socket.apply_timeout(timeout_settings)?;
+
+    query_socket(&mut socket, true).map_err(|e| e.at(Stage::Info))
+}
+
+/// Query a server that uses the GameSpy 3 protocol, but without the challenge step (some games, like
+/// Battlefield 2, don't answer to it).
+pub fn query_without_challenge(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry(timeout_settings, |timeout_settings| query_without_challenge_with_socket(UdpSocket::new(address, port)?, timeout_settings))
+}
+
+/// Query a server that uses the GameSpy 3 protocol, without the challenge step, through an already created socket.
+pub fn query_without_challenge_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let mut socket = DeadlineSocket::new(socket, &timeout_settings);
+    socket.apply_timeout(timeout_settings)?;
+
+    query_socket(&mut socket, false).map_err(|e| e.at(Stage::Info))
+}
+
+#[cfg(feature = "async")]
+async fn query_socket_async<S: AsyncSocket + Send>(mut socket: S, timeout_settings: Option<TimeoutSettings>, challenge: bool) -> GDResult<Response> {
+    socket.apply_timeout(timeout_settings)?;
+
+    let challenge = match challenge {
+        false => None,
+        true => {
+            let challenge: GDResult<i32> = async {
+                socket.send(&request_packet(Request::Challenge, None)).await?;
+                parse_challenge(&socket.receive(Some(PACKET_SIZE)).await?)
+            }.await;
+
+            Some(challenge.map_err(|e| e.at(Stage::Challenge))?)
+        }
+    };
+
+    socket.send(&request_packet(Request::Status, challenge)).await?;
+
+    let mut packets = Vec::new();
+    while !is_complete(&packets) {
+        packets.push(parse_packet(socket.receive(Some(PACKET_SIZE)).await?)?);
+        check_packets_count(&packets)?;
+    }
+
+    get_response(packets)
+}
+
+/// Asynchronously query a server that uses the GameSpy 3 protocol.
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry_async(timeout_settings, |timeout_settings| async move {
+        query_socket_async(AsyncUdpSocket::new(address, port).await?, timeout_settings, true).await.map_err(|e| e.at(Stage::Info))
+    }).await
+}
+
+/// Asynchronously query a server that uses the GameSpy 3 protocol through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    with_deadline(timeout_settings, |timeout_settings| query_socket_async(socket, timeout_settings, true)).await.map_err(|e| e.at(Stage::Info))
+}
+
+/// Asynchronously query a server that uses the GameSpy 3 protocol, without the challenge step.
+#[cfg(feature = "async")]
+pub async fn query_without_challenge_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry_async(timeout_settings, |timeout_settings| async move {
+        query_socket_async(AsyncUdpSocket::new(address, port).await?, timeout_settings, false).await.map_err(|e| e.at(Stage::Info))
+    }).await
+}
+
+/// Asynchronously query a server that uses the GameSpy 3 protocol, without the challenge step, through an
+/// already created socket.
+#[cfg(feature = "async")]
+pub async fn query_without_challenge_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    with_deadline(timeout_settings, |timeout_settings| query_socket_async(socket, timeout_settings, false)).await.map_err(|e| e.at(Stage::Info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(number: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x00];
+        data.extend(SESSION_ID);
+        data.extend(b"splitnum\x00");
+        data.push(number);
+        data.extend(payload);
+        data
+    }
+
+    #[test]
+    fn challenge() {
+        let mut data = vec![0x09];
+        data.extend(SESSION_ID);
+        data.extend(b"-12345\x00");
+
+        assert_eq!(parse_challenge(&data).unwrap(), -12345);
+        assert_eq!(&request_packet(Request::Status, Some(-12345))[7..11], &(-12345i32).to_be_bytes());
+    }
+
+    #[test]
+    fn reassembly() {
+        let first = parse_packet(packet(0x00, b"\x00hostname\x00A Server\x00maxplayers\x0016\x00\x00\x01player_\x00\x00First\x00Sec")).unwrap();
+        let second = parse_packet(packet(0x81, b"\x01player_\x00\x01Second\x00\x00score_\x00\x003\x00-4\x00\x00\x00\x02team_t\x00\x00Blue\x00\x00")).unwrap();
+
+        let packets = vec![second];
+        assert!(!is_complete(&packets));
+
+        let packets = vec![packets.into_iter().next().unwrap(), first];
+        assert!(is_complete(&packets));
+
+        let response = get_response(packets).unwrap();
+        assert_eq!(response.name, "A Server");
+        assert_eq!(response.players_maximum, 16);
+        assert_eq!(response.players_online, 2);
+        assert_eq!(response.players[0].name, "First");
+        assert_eq!(response.players[1].name, "Second");
+        assert_eq!(response.players[1].score, Some(-4));
+        assert_eq!(response.teams[0].name, "Blue");
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use crate::{GDErrorKind, GDResult, Stage};
+use crate::bufferer::{Bufferer, Endianess};
+use crate::protocols::gamespy::common::{build_response, field_name, Fields, set_field};
+use crate::protocols::gamespy::Response;
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::{DeadlineSocket, Socket, UdpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncUdpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
+
+static PACKET_SIZE: usize = 8192;
+static REQUEST_ID: [u8; 4] = [0x47, 0x44, 0x49, 0x47];
+/// Asks for the server's info, the players and the teams.
+static REQUEST: [u8; 10] = [0xFE, 0xFD, 0x00, 0x47, 0x44, 0x49, 0x47, 0xFF, 0xFF, 0xFF];
+
+/// Parses the server's key/values, ending with an empty key.
+fn parse_rules(buffer: &mut Bufferer) -> GDResult<HashMap<String, String>> {
+    let mut rules = HashMap::new();
+
+    while buffer.remaining_length() > 0 {
+        let key = buffer.get_string_utf8()?;
+        if key.is_empty() {
+            break;
+        }
+
+        rules.insert(key, buffer.get_string_utf8()?);
+    }
+
+    Ok(rules)
+}
+
+/// Parses a players or teams section: the rows count, the fields names and then every row's values.
+fn parse_section(buffer: &mut Bufferer) -> GDResult<Vec<Fields>> {
+    let mut items = Vec::new();
+    if buffer.remaining_length() == 0 {
+        return Ok(items);
+    }
+
+    //the section begins with a zero byte and then the rows count
+    buffer.move_position_ahead(1);
+    let count = buffer.get_u8()? as usize;
+
+    let mut names = Vec::new();
+    loop {
+        let name = buffer.get_str_utf8()?;
+        if name.is_empty() {
+            break;
+        }
+
+        names.push(field_name(name));
+    }
+
+    for index in 0..count {
+        for name in &names {
+            set_field(&mut items, index, name.clone(), buffer.get_string_utf8()?)?;
+        }
+    }
+
+    Ok(items)
+}
+
+fn get_response(data: &[u8]) -> GDResult<Response> {
+    let mut buffer = Bufferer::new_with_data(Endianess::Little, data);
+
+    if buffer.get_u8()? != 0 {
+        return Err(GDErrorKind::PacketBad("Invalid response type.".to_string()).into());
+    }
+
+    if buffer.get_u32()?.to_le_bytes() != REQUEST_ID {
+        return Err(GDErrorKind::PacketBad("Response id doesn't match the request.".to_string()).into());
+    }
+
+    let rules = parse_rules(&mut buffer)?;
+    let players = parse_section(&mut buffer)?;
+    let teams = parse_section(&mut buffer)?;
+
+    build_response(rules, players, teams)
+}
+
+/// Query a server that uses the GameSpy 2 protocol.
+pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry(timeout_settings, |timeout_settings| query_with_socket(UdpSocket::new(address, port)?, timeout_settings))
+}
+
+/// Query a server that uses the GameSpy 2 protocol through an already created socket.
+pub fn query_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let mut socket = DeadlineSocket::new(socket, &timeout_settings);
+    socket.apply_timeout(timeout_settings)?;
+
+    socket.send(&REQUEST)
+        .and_then(|_| socket.receive(Some(PACKET_SIZE)))
+        .and_then(|data| get_response(&data))
+        .map_err(|e| e.at(Stage::Info))
+}
+
+/// Asynchronously query a server that uses the GameSpy 2 protocol.
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry_async(timeout_settings, |timeout_settings| async move {
+        query_once_async(address, port, timeout_settings).await.map_err(|e| e.at(Stage::Info))
+    }).await
+}
+
+#[cfg(feature = "async")]
+async fn query_once_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    query_socket_async(AsyncUdpSocket::new(address, port).await?, timeout_settings).await
+}
+
+/// Asynchronously query a server that uses the GameSpy 2 protocol through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    with_deadline(timeout_settings, |timeout_settings| query_socket_async(socket, timeout_settings)).await.map_err(|e| e.at(Stage::Info))
+}
+
+#[cfg(feature = "async")]
+async fn query_socket_async<S: AsyncSocket + Send>(mut socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    socket.apply_timeout(timeout_settings)?;
+
+    socket.send(&REQUEST).await?;
+    get_response(&socket.receive(Some(PACKET_SIZE)).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response() {
+        let mut data = vec![0x00, 0x47, 0x44, 0x49, 0x47];
+        data.extend(b"hostname\x00A Server\x00numplayers\x002\x00\x00");
+        data.extend(b"\x00\x02player_\x00score_\x00\x00First\x0010\x00Second\x00-3\x00");
+        data.extend(b"\x00\x01team_t\x00score_t\x00\x00Blue\x007\x00");
+
+        let response = get_response(&data).unwrap();
+        assert_eq!(response.name, "A Server");
+        assert_eq!(response.players_online, 2);
+        assert_eq!(response.players[0].name, "First");
+        assert_eq!(response.players[0].score, Some(10));
+        assert_eq!(response.players[1].score, Some(-3));
+        assert_eq!(response.teams[0].name, "Blue");
+        assert_eq!(response.teams[0].score, Some(7));
+    }
+
+    #[test]
+    fn bad_id() {
+        assert!(get_response(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use crate::protocols::types::{GenericPlayer, GenericResponse, SpecificResponse};
+
+/// A player, as reported by the server.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Player {
+    /// The player's name.
+    pub name: String,
+    /// The player's score (can be missing).
+    pub score: Option<i32>,
+    /// The player's ping (can be missing).
+    pub ping: Option<u16>,
+    /// The player's team, some games report its name, some its index (can be missing).
+    pub team: Option<String>,
+    /// The player's kills (or frags) (can be missing).
+    pub kills: Option<i32>,
+    /// The player's deaths (can be missing).
+    pub deaths: Option<i32>,
+    /// Every other field the server reported about the player.
+    pub fields: HashMap<String, String>
+}
+
+/// A team, as reported by the server.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Team {
+    /// The team's name.
+    pub name: String,
+    /// The team's score (can be missing).
+    pub score: Option<i32>,
+    /// Every other field the server reported about the team.
+    pub fields: HashMap<String, String>
+}
+
+/// A query response.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    /// Name of the server.
+    pub name: String,
+    /// Map name.
+    pub map: String,
+    /// The game type (can be missing).
+    pub game_type: Option<String>,
+    /// Version of the game installed on the server (can be missing).
+    pub game_version: Option<String>,
+    /// Indicates whether the server requires a password (can be missing).
+    pub has_password: Option<bool>,
+    /// Number of players on the server.
+    pub players_online: u32,
+    /// Maximum number of players the server reports it can hold.
+    pub players_maximum: u32,
+    /// Players on the server.
+    pub players: Vec<Player>,
+    /// Teams on the server.
+    pub teams: Vec<Team>,
+    /// All the server's key/values.
+    pub rules: HashMap<String, String>
+}
+
+impl From<Response> for GenericResponse {
+    fn from(response: Response) -> Self {
+        Self {
+            name: Some(response.name.clone()),
+            description: None,
+            game: response.rules.get("gamename").cloned(),
+            game_version: response.game_version.clone(),
+            map: Some(response.map.clone()),
+            players_maximum: response.players_maximum,
+            players_online: response.players_online,
+            players_bots: None,
+            has_password: response.has_password,
+            players: response.players.iter().map(|player| GenericPlayer {
+                name: player.name.clone(),
+                score: player.score
+            }).collect(),
+            ping: None,
+            inner: SpecificResponse::GameSpy(response)
+        }
+    }
+}
@@ -6,4 +6,3 @@ pub mod types;
 
 pub use protocol::*;
 pub use types::*;
-pub use protocol::*;
@@ -4,20 +4,25 @@ This file has code that has been documented by the NodeJS GameDig library (MIT)
 https://github.com/gamedig/node-gamedig/blob/master/protocols/minecraftbedrock.js
 */
 
-use crate::{GDError, GDResult};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::{GDErrorKind, GDResult, Stage};
 use crate::bufferer::{Bufferer, Endianess};
 use crate::protocols::minecraft::{BedrockResponse, GameMode, Server};
 use crate::protocols::types::TimeoutSettings;
-use crate::socket::{Socket, UdpSocket};
+use crate::socket::{DeadlineSocket, Socket, UdpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncUdpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
 use crate::utils::error_by_expected_size;
 
-pub struct Bedrock {
-    socket: UdpSocket
+pub struct Bedrock<S: Socket> {
+    socket: S
 }
 
-impl Bedrock {
-    fn new(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
-        let socket = UdpSocket::new(address, port)?;
+impl<S: Socket> Bedrock<S> {
+    fn new(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
         socket.apply_timeout(timeout_settings)?;
 
         Ok(Self {
@@ -25,76 +30,174 @@ impl Bedrock {
         })
     }
 
-    fn send_status_request(&mut self) -> GDResult<()> {
-        self.socket.send(&[
-            // Message ID, ID_UNCONNECTED_PING
-            0x01,
-            // Nonce / timestamp
-            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88,
-            // Magic
-            0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
-            // Client GUID
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])?;
+    fn send_status_request(&mut self, nonce: u64) -> GDResult<()> {
+        self.socket.send(&status_request(nonce))?;
 
         Ok(())
     }
 
     fn get_info(&mut self) -> GDResult<BedrockResponse> {
-        self.send_status_request()?;
-
-        let mut buffer = Bufferer::new_with_data(Endianess::Little, &self.socket.receive(None)?);
-
-        if buffer.get_u8()? != 0x1c {
-            return Err(GDError::PacketBad("Invalid message id.".to_string()));
-        }
-
-        // Checking for our nonce directly from a u64 (as the nonce is 8 bytes).
-        if buffer.get_u64()? != 9833440827789222417 {
-            return Err(GDError::PacketBad("Invalid nonce.".to_string()));
-        }
-
-        // These 8 bytes are identical to the serverId string we receive in decimal below
-        buffer.move_position_ahead(8);
-
-        // Verifying the magic value (as we need 16 bytes, cast to two u64 values)
-        if buffer.get_u64()? != 18374403896610127616 {
-            return Err(GDError::PacketBad("Invalid magic (part 1).".to_string()));
-        }
-
-        if buffer.get_u64()? != 8671175388723805693 {
-            return Err(GDError::PacketBad("Invalid magic (part 2).".to_string()));
-        }
-
-        let remaining_length = buffer.as_endianess(Endianess::Big).get_u16()? as usize;
-        buffer.move_position_ahead(2);
-        error_by_expected_size(remaining_length, buffer.remaining_length())?;
-
-        let binding = buffer.get_string_utf8_unended()?;
-        let status: Vec<&str> = binding.split(";").collect();
-
-        // We must have at least 6 values
-        if status.len() < 6 {
-            return Err(GDError::PacketBad("Not enough status parts.".to_string()));
-        }
-
-        Ok(BedrockResponse {
-            edition: status[0].to_string(),
-            name: status[1].to_string(),
-            version_name: status[3].to_string(),
-            version_protocol:  status[2].to_string(),
-            max_players: status[5].parse().map_err(|_| GDError::TypeParse("couldn't parse.".to_string()))?,
-            online_players: status[4].parse().map_err(|_| GDError::TypeParse("couldn't parse.".to_string()))?,
-            id: status.get(6).and_then(|v| Some(v.to_string())),
-            map: status.get(7).and_then(|v| Some(v.to_string())),
-            game_mode: match status.get(8) {
-                None => None,
-                Some(v) => Some(GameMode::from_bedrock(v)?)
-            },
-            server_type: Server::Bedrock
-        })
+        let nonce = new_nonce();
+        let start = Instant::now();
+        self.send_status_request(nonce)?;
+
+        let data = self.socket.receive(None)?;
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &data);
+        let mut response = get_response(&mut buffer, nonce)?;
+        response.ping = start.elapsed();
+
+        Ok(response)
     }
 
+    pub fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+        Bedrock::new(DeadlineSocket::new(socket, &timeout_settings), timeout_settings)?.get_info().map_err(|e| e.at(Stage::Info))
+    }
+}
+
+impl Bedrock<UdpSocket> {
     pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
-        Bedrock::new(address, port, timeout_settings)?.get_info()
+        retry(timeout_settings, |timeout_settings| Bedrock::query_with_socket(UdpSocket::new(address, port)?, timeout_settings))
+    }
+}
+
+/// The nonce is the current timestamp (in milliseconds), the server sends it back in its pong.
+fn new_nonce() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis() as u64).unwrap_or(0)
+}
+
+fn status_request(nonce: u64) -> Vec<u8> {
+    [
+        // Message ID, ID_UNCONNECTED_PING
+        &[0x01],
+        // Nonce / timestamp
+        &nonce.to_be_bytes()[..],
+        // Magic
+        &[0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78],
+        // Client GUID
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]].concat()
+}
+
+pub(crate) fn get_response(buffer: &mut Bufferer, nonce: u64) -> GDResult<BedrockResponse> {
+    if buffer.get_u8()? != 0x1c {
+        return Err(GDErrorKind::PacketBad("Invalid message id.".to_string()).into());
+    }
+
+    // The pong must have our nonce (sent as a big endian u64).
+    if buffer.as_endianess(Endianess::Big).get_u64()? != nonce {
+        return Err(GDErrorKind::PacketBad("Invalid nonce.".to_string()).into());
+    }
+    buffer.move_position_ahead(8);
+
+    // These 8 bytes are identical to the serverId string we receive in decimal below
+    buffer.move_position_ahead(8);
+
+    // Verifying the magic value (as we need 16 bytes, cast to two u64 values)
+    if buffer.get_u64()? != 18374403896610127616 {
+        return Err(GDErrorKind::PacketBad("Invalid magic (part 1).".to_string()).into());
+    }
+
+    if buffer.get_u64()? != 8671175388723805693 {
+        return Err(GDErrorKind::PacketBad("Invalid magic (part 2).".to_string()).into());
+    }
+
+    let remaining_length = buffer.as_endianess(Endianess::Big).get_u16()? as usize;
+    buffer.move_position_ahead(2);
+    error_by_expected_size(remaining_length, buffer.remaining_length())?;
+
+    let status: Vec<&str> = buffer.get_str_utf8_unended()?.split(';').collect();
+
+    // We must have at least 6 values
+    if status.len() < 6 {
+        return Err(GDErrorKind::PacketBad("Not enough status parts.".to_string()).into());
+    }
+
+    Ok(BedrockResponse {
+        edition: status[0].to_string(),
+        name: status[1].to_string(),
+        version_name: status[3].to_string(),
+        version_protocol:  status[2].to_string(),
+        max_players: status[5].parse().map_err(|e| GDErrorKind::TypeParse("couldn't parse.".to_string()).context(e))?,
+        online_players: status[4].parse().map_err(|e| GDErrorKind::TypeParse("couldn't parse.".to_string()).context(e))?,
+        id: status.get(6).map(|v| v.to_string()),
+        map: status.get(7).map(|v| v.to_string()),
+        game_mode: match status.get(8) {
+            None => None,
+            Some(v) => Some(GameMode::from_bedrock(v)?)
+        },
+        server_type: Server::Bedrock,
+        ping: Duration::default()
+    })
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncBedrock<S: AsyncSocket + Send> {
+    socket: S
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncSocket + Send> AsyncBedrock<S> {
+    fn new(mut socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
+        socket.apply_timeout(timeout_settings)?;
+
+        Ok(Self {
+            socket
+        })
+    }
+
+    async fn get_info(&mut self) -> GDResult<BedrockResponse> {
+        let nonce = new_nonce();
+        let start = Instant::now();
+        self.socket.send(&status_request(nonce)).await?;
+
+        let data = self.socket.receive(None).await?;
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &data);
+        let mut response = get_response(&mut buffer, nonce)?;
+        response.ping = start.elapsed();
+
+        Ok(response)
+    }
+
+    pub async fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+        with_deadline(timeout_settings, |timeout_settings| async move {
+            AsyncBedrock::new(socket, timeout_settings)?.get_info().await
+        }).await.map_err(|e| e.at(Stage::Info))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncBedrock<AsyncUdpSocket> {
+    pub async fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+        retry_async(timeout_settings, |timeout_settings| async move {
+            AsyncBedrock::query_with_socket(AsyncUdpSocket::new(address, port).await?, timeout_settings).await
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pong(nonce: u64) -> Vec<u8> {
+        let status = "MCPE;A server;560;1.19.40;2;10;123;Bedrock level;Survival";
+
+        [
+            &[0x1c][..],
+            &nonce.to_be_bytes(),
+            &[0; 8], //server id
+            &status_request(0)[9..25], //the magic
+            &(status.len() as u16).to_be_bytes(),
+            status.as_bytes()].concat()
+    }
+
+    #[test]
+    fn nonce_round_trip() {
+        let nonce = new_nonce();
+        assert_eq!(&status_request(nonce)[1..9], &nonce.to_be_bytes());
+
+        let response = get_response(&mut Bufferer::new_with_data(Endianess::Little, &pong(nonce)), nonce).unwrap();
+        assert_eq!(response.name, "A server");
+        assert_eq!(response.online_players, 2);
+
+        assert!(get_response(&mut Bufferer::new_with_data(Endianess::Little, &pong(nonce + 1)), nonce).is_err());
     }
 }
@@ -1,17 +1,22 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use serde_json::Value;
-use crate::{GDError, GDResult};
+use crate::{GDErrorKind, GDResult, Stage};
 use crate::bufferer::{Bufferer, Endianess};
 use crate::protocols::minecraft::{as_varint, get_string, get_varint, Player, Response, Server};
 use crate::protocols::types::TimeoutSettings;
-use crate::socket::{Socket, TcpSocket};
+use crate::socket::{DeadlineSocket, Socket, TcpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncTcpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
 
-pub struct Java {
-    socket: TcpSocket
+pub struct Java<S: Socket> {
+    socket: S
 }
 
-impl Java {
-    fn new(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
-        let socket = TcpSocket::new(address, port)?;
+impl<S: Socket> Java<S> {
+    fn new(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
         socket.apply_timeout(timeout_settings)?;
 
         Ok(Self {
@@ -20,107 +25,332 @@ impl Java {
     }
 
     fn send(&mut self, data: Vec<u8>) -> GDResult<()> {
-        self.socket.send(&[as_varint(data.len() as i32), data].concat())
+        self.socket.send(&as_packet(data))
     }
 
-    fn receive(&mut self) -> GDResult<Bufferer> {
-        let mut buffer = Bufferer::new_with_data(Endianess::Little, &self.socket.receive(None)?);
+    /// Receives a whole packet (without its length), as the connection stays open for the ping.
+    fn receive(&mut self) -> GDResult<Vec<u8>> {
+        let mut length_bytes = Vec::with_capacity(5);
+        while !is_varint_complete(&length_bytes)? {
+            length_bytes.extend(self.socket.receive_exact(1)?);
+        }
+
+        let length = packet_length(&length_bytes)?;
+        self.socket.receive_exact(length)
+    }
 
-        let _packet_length = get_varint(&mut buffer)? as usize;
-        //this declared 'packet length' from within the packet might be wrong (?), not checking with it...
+    fn get_ping(&mut self) -> GDResult<Duration> {
+        let payload = ping_payload();
+        let start = Instant::now();
 
-        Ok(buffer)
+        self.send(ping_request(payload))?;
+        check_pong(&mut Bufferer::new_with_data(Endianess::Little, &self.receive()?), payload)?;
+
+        Ok(start.elapsed())
     }
 
-    fn send_handshake(&mut self) -> GDResult<()> {
-        self.send([
-            //Packet ID (0)
-            0x00,
-            //Protocol Version (-1 to determine version)
-            0xFF, 0xFF, 0xFF, 0xFF, 0x0F,
-            //Server address (can be anything)
-            0x07, 0x47, 0x61, 0x6D, 0x65, 0x44, 0x69, 0x67,
-            //Server port (can be anything)
-            0x00, 0x00,
-            //Next state (1 for status)
-            0x01].to_vec())?;
+    fn get_info(&mut self) -> GDResult<Response> {
+        let start = Instant::now();
+        self.send(handshake_request())?;
+        self.send(status_request())?;
+
+        let mut response = get_response(&mut Bufferer::new_with_data(Endianess::Little, &self.receive()?))?;
+        let status_ping = start.elapsed();
 
-        Ok(())
+        response.ping = ping_or_status(self.get_ping(), status_ping)?;
+
+        Ok(response)
     }
 
-    fn send_status_request(&mut self) -> GDResult<()> {
-        self.send([
-            //Packet ID (0)
-            0x00].to_vec())?;
+    pub fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        Java::new(DeadlineSocket::new(socket, &timeout_settings), timeout_settings)?.get_info().map_err(|e| e.at(Stage::Info))
+    }
+}
 
-        Ok(())
+impl Java<TcpSocket> {
+    pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        retry(timeout_settings, |timeout_settings| Java::query_with_socket(TcpSocket::new(address, port)?, timeout_settings))
     }
+}
 
-    fn send_ping_request(&mut self) -> GDResult<()> {
-        self.send([
-            //Packet ID (1)
-            0x01].to_vec())?;
+/// Prefixes the data with its length.
+fn as_packet(data: Vec<u8>) -> Vec<u8> {
+    [as_varint(data.len() as i32), data].concat()
+}
 
-        Ok(())
+/// Tells if the bytes make up a whole VarInt (its last byte doesn't have the continue bit set).
+fn is_varint_complete(bytes: &[u8]) -> GDResult<bool> {
+    if bytes.len() > 5 {
+        return Err(GDErrorKind::PacketBad("Couldn't parse to VarInt: Overflow.".to_string()).into());
     }
 
-    fn get_info(&mut self) -> GDResult<Response> {
-        self.send_handshake()?;
-        self.send_status_request()?;
-        self.send_ping_request()?;
+    Ok(matches!(bytes.last(), Some(byte) if byte & 0x80 == 0))
+}
 
-        let mut buffer = self.receive()?;
+/// The biggest packet a server can send (its length being at most a 3 bytes VarInt).
+static MAX_PACKET_LENGTH: i32 = 2097151;
 
-        if get_varint(&mut buffer)? != 0 { //first var int is the packet id
-            return Err(GDError::PacketBad("Bad receive packet id.".to_string()));
-        }
+fn packet_length(length_bytes: &[u8]) -> GDResult<usize> {
+    let length = get_varint(&mut Bufferer::new_with_data(Endianess::Little, length_bytes))?;
+    if !(1..=MAX_PACKET_LENGTH).contains(&length) {
+        return Err(GDErrorKind::PacketBad("Bad packet length.".to_string()).into());
+    }
+
+    Ok(length as usize)
+}
+
+fn handshake_request() -> Vec<u8> {
+    [
+        //Packet ID (0)
+        0x00,
+        //Protocol Version (-1 to determine version)
+        0xFF, 0xFF, 0xFF, 0xFF, 0x0F,
+        //Server address (can be anything)
+        0x07, 0x47, 0x61, 0x6D, 0x65, 0x44, 0x69, 0x67,
+        //Server port (can be anything)
+        0x00, 0x00,
+        //Next state (1 for status)
+        0x01].to_vec()
+}
+
+fn status_request() -> Vec<u8> {
+    [
+        //Packet ID (0)
+        0x00].to_vec()
+}
+
+/// The ping's payload, the current timestamp (in milliseconds).
+fn ping_payload() -> [u8; 8] {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis() as u64).unwrap_or(0);
+    timestamp.to_be_bytes()
+}
+
+fn ping_request(payload: [u8; 8]) -> Vec<u8> {
+    [
+        //Packet ID (1)
+        &[0x01],
+        //Payload (echoed back by the server)
+        &payload[..]].concat()
+}
+
+/// Some servers (and proxies) don't answer the ping (it times out or the connection is closed), the status
+/// is still good, so its round-trip time is used, a bad pong is still an error.
+fn ping_or_status(ping: GDResult<Duration>, status_ping: Duration) -> GDResult<Duration> {
+    match ping {
+        Err(error) if error.is_timeout() || matches!(error.kind(), GDErrorKind::PacketReceive(_)) => Ok(status_ping),
+        ping => ping
+    }
+}
+
+/// Checks that the pong has the same payload as the ping.
+fn check_pong(buffer: &mut Bufferer, payload: [u8; 8]) -> GDResult<()> {
+    if get_varint(buffer)? != 1 {
+        return Err(GDErrorKind::PacketBad("Bad pong packet id.".to_string()).into());
+    }
+
+    if buffer.remaining_data() != payload {
+        return Err(GDErrorKind::PacketBad("The pong's payload doesn't match the ping's.".to_string()).into());
+    }
+
+    Ok(())
+}
+
+pub(crate) fn get_response(buffer: &mut Bufferer) -> GDResult<Response> {
+    if get_varint(buffer)? != 0 { //first var int is the packet id
+        return Err(GDErrorKind::PacketBad("Bad receive packet id.".to_string()).into());
+    }
+
+    let json_response = get_string(buffer)?;
+    let value_response: Value = serde_json::from_str(&json_response)
+        .map_err(|e| GDErrorKind::JsonParse(e.to_string()).context(e))?;
+
+    let version_name = value_response["version"]["name"].as_str()
+        .ok_or(GDErrorKind::PacketBad("Couldn't get expected string.".to_string()))?.to_string();
+    let version_protocol = value_response["version"]["protocol"].as_i64()
+        .and_then(|value| i32::try_from(value).ok())
+        .ok_or(GDErrorKind::PacketBad("Couldn't get expected number.".to_string()))?;
+
+    let max_players = value_response["players"]["max"].as_u64()
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or(GDErrorKind::PacketBad("Couldn't get expected number.".to_string()))?;
+    let online_players = value_response["players"]["online"].as_u64()
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or(GDErrorKind::PacketBad("Couldn't get expected number.".to_string()))?;
+    let sample_players: Option<Vec<Player>> = match value_response["players"]["sample"].is_null() {
+        true => None,
+        false => Some({
+            let players_values = value_response["players"]["sample"].as_array()
+                .ok_or(GDErrorKind::PacketBad("Couldn't get expected array.".to_string()))?;
+
+            let mut players = Vec::with_capacity(players_values.len());
+            for player in players_values {
+                players.push(Player {
+                    name: player["name"].as_str().ok_or(GDErrorKind::PacketBad("Couldn't get expected string.".to_string()))?.to_string(),
+                    id: player["id"].as_str().ok_or(GDErrorKind::PacketBad("Couldn't get expected string.".to_string()))?.to_string()
+                })
+            }
+
+            players
+        })
+    };
+
+    Ok(Response {
+        version_name,
+        version_protocol,
+        max_players,
+        online_players,
+        sample_players,
+        description: value_response["description"].to_string(),
+        favicon: value_response["favicon"].as_str().map(str::to_string),
+        previews_chat: value_response["previewsChat"].as_bool(),
+        enforces_secure_chat: value_response["enforcesSecureChat"].as_bool(),
+        server_type: Server::Java,
+        ping: Duration::default()
+    })
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncJava<S: AsyncSocket + Send> {
+    socket: S
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncSocket + Send> AsyncJava<S> {
+    fn new(mut socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
+        socket.apply_timeout(timeout_settings)?;
 
-        let json_response = get_string(&mut buffer)?;
-        let value_response: Value = serde_json::from_str(&json_response)
-            .map_err(|e| GDError::JsonParse(e.to_string()))?;
-
-        let version_name = value_response["version"]["name"].as_str()
-            .ok_or(GDError::PacketBad("Couldn't get expected string.".to_string()))?.to_string();
-        let version_protocol = value_response["version"]["protocol"].as_i64()
-            .ok_or(GDError::PacketBad("Couldn't get expected number.".to_string()))? as i32;
-
-        let max_players = value_response["players"]["max"].as_u64()
-            .ok_or(GDError::PacketBad("Couldn't get expected number.".to_string()))? as u32;
-        let online_players = value_response["players"]["online"].as_u64()
-            .ok_or(GDError::PacketBad("Couldn't get expected number.".to_string()))? as u32;
-        let sample_players: Option<Vec<Player>> = match value_response["players"]["sample"].is_null() {
-            true => None,
-            false => Some({
-                let players_values = value_response["players"]["sample"].as_array()
-                    .ok_or(GDError::PacketBad("Couldn't get expected array.".to_string()))?;
-
-                let mut players = Vec::with_capacity(players_values.len());
-                for player in players_values {
-                    players.push(Player {
-                        name: player["name"].as_str().ok_or(GDError::PacketBad("Couldn't get expected string.".to_string()))?.to_string(),
-                        id: player["id"].as_str().ok_or(GDError::PacketBad("Couldn't get expected string.".to_string()))?.to_string()
-                    })
-                }
-
-                players
-            })
-        };
-
-        Ok(Response {
-            version_name,
-            version_protocol,
-            max_players,
-            online_players,
-            sample_players,
-            description: value_response["description"].to_string(),
-            favicon: value_response["favicon"].as_str().map(str::to_string),
-            previews_chat: value_response["previewsChat"].as_bool(),
-            enforces_secure_chat: value_response["enforcesSecureChat"].as_bool(),
-            server_type: Server::Java
+        Ok(Self {
+            socket
         })
     }
 
-    pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
-        Java::new(address, port, timeout_settings)?.get_info()
+    async fn send(&mut self, data: Vec<u8>) -> GDResult<()> {
+        self.socket.send(&as_packet(data)).await
+    }
+
+    async fn receive(&mut self) -> GDResult<Vec<u8>> {
+        let mut length_bytes = Vec::with_capacity(5);
+        while !is_varint_complete(&length_bytes)? {
+            length_bytes.extend(self.socket.receive_exact(1).await?);
+        }
+
+        let length = packet_length(&length_bytes)?;
+        self.socket.receive_exact(length).await
+    }
+
+    async fn get_ping(&mut self) -> GDResult<Duration> {
+        let payload = ping_payload();
+        let start = Instant::now();
+
+        self.send(ping_request(payload)).await?;
+        check_pong(&mut Bufferer::new_with_data(Endianess::Little, &self.receive().await?), payload)?;
+
+        Ok(start.elapsed())
+    }
+
+    async fn get_info(&mut self) -> GDResult<Response> {
+        let start = Instant::now();
+        self.send(handshake_request()).await?;
+        self.send(status_request()).await?;
+
+        let mut response = get_response(&mut Bufferer::new_with_data(Endianess::Little, &self.receive().await?))?;
+        let status_ping = start.elapsed();
+
+        response.ping = ping_or_status(self.get_ping().await, status_ping)?;
+
+        Ok(response)
+    }
+
+    pub async fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        with_deadline(timeout_settings, |timeout_settings| async move {
+            AsyncJava::new(socket, timeout_settings)?.get_info().await
+        }).await.map_err(|e| e.at(Stage::Info))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncJava<AsyncTcpSocket> {
+    pub async fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        retry_async(timeout_settings, |timeout_settings| async move {
+            AsyncJava::query_with_socket(AsyncTcpSocket::new(address, port).await?, timeout_settings).await
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use crate::socket::MockSocket;
+    use super::*;
+
+    fn read_packet(stream: &mut TcpStream) -> Vec<u8> {
+        let mut length_bytes = vec![];
+        while !is_varint_complete(&length_bytes).unwrap() {
+            let mut byte = [0];
+            stream.read_exact(&mut byte).unwrap();
+            length_bytes.push(byte[0]);
+        }
+
+        let mut packet = vec![0; packet_length(&length_bytes).unwrap()];
+        stream.read_exact(&mut packet).unwrap();
+        packet
+    }
+
+    #[test]
+    fn status_and_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            assert_eq!(read_packet(&mut stream)[0], 0x00); //handshake
+            assert_eq!(read_packet(&mut stream), vec![0x00]); //status request
+
+            let status = r#"{"version":{"name":"1.19.2","protocol":760},"players":{"max":20,"online":1},"description":"A server"}"#;
+            let status_packet = [&[0x00, status.len() as u8][..], status.as_bytes()].concat();
+            stream.write_all(&as_packet(status_packet)).unwrap();
+
+            let ping = read_packet(&mut stream);
+            assert_eq!(ping.len(), 9);
+            stream.write_all(&as_packet(ping)).unwrap(); //the pong echoes the ping
+        });
+
+        let response = Java::query("127.0.0.1", port, None).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.version_name, "1.19.2");
+        assert_eq!(response.online_players, 1);
+        assert!(response.ping < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn status_without_pong() {
+        let status = r#"{"version":{"name":"1.19.2","protocol":760},"players":{"max":20,"online":1},"description":"A server"}"#;
+        let status_packet = [&[0x00, status.len() as u8][..], status.as_bytes()].concat();
+        let mut socket = MockSocket::new(vec![as_packet(status_packet)]);
+
+        let response = Java::query_with_socket(&mut socket, None).unwrap();
+        assert_eq!(response.version_name, "1.19.2");
+        assert_eq!(socket.sent().len(), 3); //handshake, status request and ping
+    }
+
+    #[test]
+    fn status_with_bad_pong() {
+        let status = r#"{"version":{"name":"1.19.2","protocol":760},"players":{"max":20,"online":1},"description":"A server"}"#;
+        let status_packet = [&[0x00, status.len() as u8][..], status.as_bytes()].concat();
+        let mut socket = MockSocket::new(vec![as_packet(status_packet), as_packet(ping_request([0; 8]))]);
+
+        let error = Java::query_with_socket(&mut socket, None).unwrap_err();
+        assert!(matches!(error.kind(), GDErrorKind::PacketBad(_)));
+    }
+
+    #[test]
+    fn pong_payload_mismatch() {
+        let payload = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        assert!(check_pong(&mut Bufferer::new_with_data(Endianess::Little, &ping_request(payload)), payload).is_ok());
+        assert!(check_pong(&mut Bufferer::new_with_data(Endianess::Little, &ping_request([0; 8])), payload).is_err());
     }
 }
@@ -1,18 +1,23 @@
 
-use crate::{GDError, GDResult};
+use std::time::{Duration, Instant};
+use crate::{GDErrorKind, GDResult, Stage};
 use crate::bufferer::{Bufferer, Endianess};
 use crate::protocols::minecraft::{LegacyGroup, Response, Server};
 use crate::protocols::types::TimeoutSettings;
-use crate::socket::{Socket, TcpSocket};
+use crate::socket::{DeadlineSocket, Socket, TcpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncTcpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
 use crate::utils::error_by_expected_size;
 
-pub struct LegacyBV1_8 {
-    socket: TcpSocket
+pub struct LegacyBV1_8<S: Socket> {
+    socket: S
 }
 
-impl LegacyBV1_8 {
-    fn new(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
-        let socket = TcpSocket::new(address, port)?;
+impl<S: Socket> LegacyBV1_8<S> {
+    fn new(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
         socket.apply_timeout(timeout_settings)?;
 
         Ok(Self {
@@ -21,47 +26,109 @@ impl LegacyBV1_8 {
     }
 
     fn send_initial_request(&mut self) -> GDResult<()> {
-        self.socket.send(&[0xFE])
+        self.socket.send(&initial_request())
     }
 
     fn get_info(&mut self) -> GDResult<Response> {
+        let start = Instant::now();
         self.send_initial_request()?;
 
-        let mut buffer = Bufferer::new_with_data(Endianess::Big, &self.socket.receive(None)?);
-
-        if buffer.get_u8()? != 0xFF {
-            return Err(GDError::ProtocolFormat("Expected 0xFF at the begin of the packet.".to_string()));
-        }
-
-        let length = buffer.get_u16()? * 2;
-        error_by_expected_size((length + 3) as usize, buffer.data_length())?;
-
-        let packet_string = buffer.get_string_utf16()?;
-
-        let split: Vec<&str> = packet_string.split("§").collect();
-        error_by_expected_size(3, split.len())?;
-
-        let description = split[0].to_string();
-        let online_players = split[1].parse()
-            .map_err(|_| GDError::PacketBad("Failed to parse to expected int.".to_string()))?;
-        let max_players = split[2].parse()
-            .map_err(|_| GDError::PacketBad("Failed to parse to expected int.".to_string()))?;
-
-        Ok(Response {
-            version_name: "Beta 1.8+".to_string(),
-            version_protocol: -1,
-            max_players,
-            online_players,
-            sample_players: None,
-            description,
-            favicon: None,
-            previews_chat: None,
-            enforces_secure_chat: None,
-            server_type: Server::Legacy(LegacyGroup::VB1_8)
-        })
+        let data = self.socket.receive(None)?;
+        let mut buffer = Bufferer::new_with_data(Endianess::Big, &data);
+        let mut response = get_response(&mut buffer)?;
+        response.ping = start.elapsed();
+
+        Ok(response)
     }
 
+    pub fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        LegacyBV1_8::new(DeadlineSocket::new(socket, &timeout_settings), timeout_settings)?.get_info().map_err(|e| e.at(Stage::Info))
+    }
+}
+
+impl LegacyBV1_8<TcpSocket> {
     pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
-        LegacyBV1_8::new(address, port, timeout_settings)?.get_info()
+        retry(timeout_settings, |timeout_settings| LegacyBV1_8::query_with_socket(TcpSocket::new(address, port)?, timeout_settings))
+    }
+}
+
+fn initial_request() -> [u8; 1] {
+    [0xFE]
+}
+
+fn get_response(buffer: &mut Bufferer) -> GDResult<Response> {
+    if buffer.get_u8()? != 0xFF {
+        return Err(GDErrorKind::ProtocolFormat("Expected 0xFF at the begin of the packet.".to_string()).into());
+    }
+
+    let length = buffer.get_u16()? as usize * 2;
+    error_by_expected_size(length + 3, buffer.data_length())?;
+
+    let packet_string = buffer.get_string_utf16()?;
+
+    let split: Vec<&str> = packet_string.split("§").collect();
+    error_by_expected_size(3, split.len())?;
+
+    let description = split[0].to_string();
+    let online_players = split[1].parse()
+        .map_err(|e| GDErrorKind::PacketBad("Failed to parse to expected int.".to_string()).context(e))?;
+    let max_players = split[2].parse()
+        .map_err(|e| GDErrorKind::PacketBad("Failed to parse to expected int.".to_string()).context(e))?;
+
+    Ok(Response {
+        version_name: "Beta 1.8+".to_string(),
+        version_protocol: -1,
+        max_players,
+        online_players,
+        sample_players: None,
+        description,
+        favicon: None,
+        previews_chat: None,
+        enforces_secure_chat: None,
+        server_type: Server::Legacy(LegacyGroup::VB1_8),
+        ping: Duration::default()
+    })
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncLegacyBV1_8<S: AsyncSocket + Send> {
+    socket: S
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncSocket + Send> AsyncLegacyBV1_8<S> {
+    fn new(mut socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
+        socket.apply_timeout(timeout_settings)?;
+
+        Ok(Self {
+            socket
+        })
+    }
+
+    async fn get_info(&mut self) -> GDResult<Response> {
+        let start = Instant::now();
+        self.socket.send(&initial_request()).await?;
+
+        let data = self.socket.receive(None).await?;
+        let mut buffer = Bufferer::new_with_data(Endianess::Big, &data);
+        let mut response = get_response(&mut buffer)?;
+        response.ping = start.elapsed();
+
+        Ok(response)
+    }
+
+    pub async fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        with_deadline(timeout_settings, |timeout_settings| async move {
+            AsyncLegacyBV1_8::new(socket, timeout_settings)?.get_info().await
+        }).await.map_err(|e| e.at(Stage::Info))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncLegacyBV1_8<AsyncTcpSocket> {
+    pub async fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        retry_async(timeout_settings, |timeout_settings| async move {
+            AsyncLegacyBV1_8::query_with_socket(AsyncTcpSocket::new(address, port).await?, timeout_settings).await
+        }).await
     }
 }
@@ -1,17 +1,22 @@
-use crate::{GDError, GDResult};
+use std::time::{Duration, Instant};
+use crate::{GDErrorKind, GDResult, Stage};
 use crate::bufferer::{Bufferer, Endianess};
 use crate::protocols::minecraft::{LegacyGroup, Response, Server};
 use crate::protocols::types::TimeoutSettings;
-use crate::socket::{Socket, TcpSocket};
+use crate::socket::{DeadlineSocket, Socket, TcpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncTcpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
 use crate::utils::error_by_expected_size;
 
-pub struct LegacyV1_6 {
-    socket: TcpSocket
+pub struct LegacyV1_6<S: Socket> {
+    socket: S
 }
 
-impl LegacyV1_6 {
-    fn new(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
-        let socket = TcpSocket::new(address, port)?;
+impl<S: Socket> LegacyV1_6<S> {
+    fn new(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
         socket.apply_timeout(timeout_settings)?;
 
         Ok(Self {
@@ -20,80 +25,140 @@ impl LegacyV1_6 {
     }
 
     fn send_initial_request(&mut self) -> GDResult<()> {
-        self.socket.send(&[
-            // Packet ID (FE)
-            0xfe,
-            // Ping payload (01)
-            0x01,
-            // Packet identifier for plugin message
-            0xfa,
-            // Length of 'GameDig' string (7) as unsigned short
-            0x00, 0x07,
-            // 'GameDig' string as UTF-16BE
-            0x00, 0x47, 0x00, 0x61, 0x00, 0x6D, 0x00, 0x65, 0x00, 0x44, 0x00, 0x69, 0x00, 0x67])?;
-
-        Ok(())
+        self.socket.send(&initial_request())
     }
 
-    pub fn is_protocol(buffer: &mut Bufferer) -> GDResult<bool> {
-        let state = buffer.remaining_data().starts_with(&[0x00, 0xA7, 0x00, 0x31, 0x00, 0x00]);
+    fn get_info(&mut self) -> GDResult<Response> {
+        let start = Instant::now();
+        self.send_initial_request()?;
 
-        if state {
-            buffer.move_position_ahead(6);
-        }
+        let data = self.socket.receive(None)?;
+        let mut buffer = Bufferer::new_with_data(Endianess::Big, &data);
+        let mut response = get_packet_response(&mut buffer)?;
+        response.ping = start.elapsed();
 
-        Ok(state)
+        Ok(response)
     }
 
-    pub fn get_response(buffer: &mut Bufferer) -> GDResult<Response> {
-        let packet_string = buffer.get_string_utf16()?;
-
-        let split: Vec<&str> = packet_string.split("\x00").collect();
-        error_by_expected_size(5, split.len())?;
-
-        let version_protocol = split[0].parse()
-            .map_err(|_| GDError::PacketBad("Failed to parse to expected int.".to_string()))?;
-        let version_name = split[1].to_string();
-        let description = split[2].to_string();
-        let online_players = split[3].parse()
-            .map_err(|_| GDError::PacketBad("Failed to parse to expected int.".to_string()))?;
-        let max_players = split[4].parse()
-            .map_err(|_| GDError::PacketBad("Failed to parse to expected int.".to_string()))?;
-
-        Ok(Response {
-            version_name,
-            version_protocol,
-            max_players,
-            online_players,
-            sample_players: None,
-            description,
-            favicon: None,
-            previews_chat: None,
-            enforces_secure_chat: None,
-            server_type: Server::Legacy(LegacyGroup::V1_6)
-        })
+    pub fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        LegacyV1_6::new(DeadlineSocket::new(socket, &timeout_settings), timeout_settings)?.get_info().map_err(|e| e.at(Stage::Info))
     }
+}
 
-    fn get_info(&mut self) -> GDResult<Response> {
-        self.send_initial_request()?;
+impl LegacyV1_6<TcpSocket> {
+    pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        retry(timeout_settings, |timeout_settings| LegacyV1_6::query_with_socket(TcpSocket::new(address, port)?, timeout_settings))
+    }
+}
 
-        let mut buffer = Bufferer::new_with_data(Endianess::Big, &self.socket.receive(None)?);
+fn initial_request() -> [u8; 19] {
+    [
+        // Packet ID (FE)
+        0xfe,
+        // Ping payload (01)
+        0x01,
+        // Packet identifier for plugin message
+        0xfa,
+        // Length of 'GameDig' string (7) as unsigned short
+        0x00, 0x07,
+        // 'GameDig' string as UTF-16BE
+        0x00, 0x47, 0x00, 0x61, 0x00, 0x6D, 0x00, 0x65, 0x00, 0x44, 0x00, 0x69, 0x00, 0x67]
+}
 
-        if buffer.get_u8()? != 0xFF {
-            return Err(GDError::ProtocolFormat("Expected a certain byte (0xFF) at the begin of the packet.".to_string()));
-        }
+pub(crate) fn is_protocol(buffer: &mut Bufferer) -> GDResult<bool> {
+    let state = buffer.remaining_data().starts_with(&[0x00, 0xA7, 0x00, 0x31, 0x00, 0x00]);
 
-        let length = buffer.get_u16()? * 2;
-        error_by_expected_size((length + 3) as usize, buffer.data_length())?;
+    if state {
+        buffer.move_position_ahead(6);
+    }
 
-        if !LegacyV1_6::is_protocol(&mut buffer)? {
-            return Err(GDError::ProtocolFormat("Expected certain bytes at the beginning of the packet.".to_string()));
-        }
+    Ok(state)
+}
+
+pub(crate) fn get_response(buffer: &mut Bufferer) -> GDResult<Response> {
+    let packet_string = buffer.get_string_utf16()?;
+
+    let split: Vec<&str> = packet_string.split("\x00").collect();
+    error_by_expected_size(5, split.len())?;
+
+    let version_protocol = split[0].parse()
+        .map_err(|e| GDErrorKind::PacketBad("Failed to parse to expected int.".to_string()).context(e))?;
+    let version_name = split[1].to_string();
+    let description = split[2].to_string();
+    let online_players = split[3].parse()
+        .map_err(|e| GDErrorKind::PacketBad("Failed to parse to expected int.".to_string()).context(e))?;
+    let max_players = split[4].parse()
+        .map_err(|e| GDErrorKind::PacketBad("Failed to parse to expected int.".to_string()).context(e))?;
+
+    Ok(Response {
+        version_name,
+        version_protocol,
+        max_players,
+        online_players,
+        sample_players: None,
+        description,
+        favicon: None,
+        previews_chat: None,
+        enforces_secure_chat: None,
+        server_type: Server::Legacy(LegacyGroup::V1_6),
+        ping: Duration::default()
+    })
+}
 
-        LegacyV1_6::get_response(&mut buffer)
+fn get_packet_response(buffer: &mut Bufferer) -> GDResult<Response> {
+    if buffer.get_u8()? != 0xFF {
+        return Err(GDErrorKind::ProtocolFormat("Expected a certain byte (0xFF) at the begin of the packet.".to_string()).into());
     }
 
-    pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
-        LegacyV1_6::new(address, port, timeout_settings)?.get_info()
+    let length = buffer.get_u16()? as usize * 2;
+    error_by_expected_size(length + 3, buffer.data_length())?;
+
+    if !is_protocol(buffer)? {
+        return Err(GDErrorKind::ProtocolFormat("Expected certain bytes at the beginning of the packet.".to_string()).into());
+    }
+
+    get_response(buffer)
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncLegacyV1_6<S: AsyncSocket + Send> {
+    socket: S
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncSocket + Send> AsyncLegacyV1_6<S> {
+    fn new(mut socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
+        socket.apply_timeout(timeout_settings)?;
+
+        Ok(Self {
+            socket
+        })
+    }
+
+    async fn get_info(&mut self) -> GDResult<Response> {
+        let start = Instant::now();
+        self.socket.send(&initial_request()).await?;
+
+        let data = self.socket.receive(None).await?;
+        let mut buffer = Bufferer::new_with_data(Endianess::Big, &data);
+        let mut response = get_packet_response(&mut buffer)?;
+        response.ping = start.elapsed();
+
+        Ok(response)
+    }
+
+    pub async fn query_with_socket(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        with_deadline(timeout_settings, |timeout_settings| async move {
+            AsyncLegacyV1_6::new(socket, timeout_settings)?.get_info().await
+        }).await.map_err(|e| e.at(Stage::Info))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncLegacyV1_6<AsyncTcpSocket> {
+    pub async fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+        retry_async(timeout_settings, |timeout_settings| async move {
+            AsyncLegacyV1_6::query_with_socket(AsyncTcpSocket::new(address, port).await?, timeout_settings).await
+        }).await
     }
 }
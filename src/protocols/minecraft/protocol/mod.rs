@@ -1,4 +1,5 @@
-use crate::{GDError, GDResult};
+use std::net::SocketAddr;
+use crate::{GDError, GDErrorKind, GDResult};
 use crate::protocols::minecraft::{BedrockResponse, LegacyGroup, Response};
 use crate::protocols::minecraft::protocol::bedrock::Bedrock;
 use crate::protocols::minecraft::protocol::java::Java;
@@ -6,6 +7,20 @@ use crate::protocols::minecraft::protocol::legacy_v1_4::LegacyV1_4;
 use crate::protocols::minecraft::protocol::legacy_v1_6::LegacyV1_6;
 use crate::protocols::minecraft::protocol::legacy_bv1_8::LegacyBV1_8;
 use crate::protocols::types::TimeoutSettings;
+use crate::socket::{Socket, UdpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::AsyncSocket;
+#[cfg(feature = "async")]
+use crate::protocols::minecraft::protocol::bedrock::AsyncBedrock;
+#[cfg(feature = "async")]
+use crate::protocols::minecraft::protocol::java::AsyncJava;
+#[cfg(feature = "async")]
+use crate::protocols::minecraft::protocol::legacy_v1_4::AsyncLegacyV1_4;
+#[cfg(feature = "async")]
+use crate::protocols::minecraft::protocol::legacy_v1_6::AsyncLegacyV1_6;
+#[cfg(feature = "async")]
+use crate::protocols::minecraft::protocol::legacy_bv1_8::AsyncLegacyBV1_8;
 
 mod java;
 mod legacy_v1_4;
@@ -13,21 +28,30 @@ mod legacy_v1_6;
 mod legacy_bv1_8;
 mod bedrock;
 
+/// The error of an auto query, with why every variant (example: `("Java", error)`) failed.
+pub(crate) fn auto_query_error(errors: &[(&str, GDError)]) -> GDError {
+    let details: Vec<String> = errors.iter().map(|(variant, error)| format!("{variant}: {error}")).collect();
+    GDErrorKind::AutoQuery(details.join("; ")).into()
+}
+
 /// Queries a Minecraft server with all the protocol variants one by one (Java -> Bedrock -> Legacy (1.6 -> 1.4 -> Beta 1.8)).
 pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
-    if let Ok(response) = query_java(address, port, timeout_settings.clone()) {
-        return Ok(response);
-    }
+    let java_error = match query_java(address, port, timeout_settings.clone()) {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
 
-    if let Ok(response) = query_bedrock(address, port, timeout_settings.clone()) {
-        return Ok(Response::from_bedrock_response(response));
-    }
+    let bedrock_error = match query_bedrock(address, port, timeout_settings.clone()) {
+        Ok(response) => return Ok(Response::from_bedrock_response(response)),
+        Err(error) => error
+    };
 
-    if let Ok(response) = query_legacy(address, port, timeout_settings) {
-        return Ok(response);
-    }
+    let legacy_error = match query_legacy(address, port, timeout_settings) {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
 
-    Err(GDError::AutoQuery)
+    Err(auto_query_error(&[("Java", java_error), ("Bedrock", bedrock_error), ("Legacy", legacy_error)]))
 }
 
 /// Query a Java Server.
@@ -35,21 +59,34 @@ pub fn query_java(address: &str, port: u16, timeout_settings: Option<TimeoutSett
     Java::query(address, port, timeout_settings)
 }
 
+/// Query a Java Server by its socket address (IPv4 or IPv6).
+pub fn query_java_addr(address: &SocketAddr, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    query_java(&address.ip().to_string(), address.port(), timeout_settings)
+}
+
+/// Query a Java Server through an already created socket (a connected stream).
+pub fn query_java_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    Java::query_with_socket(socket, timeout_settings)
+}
+
 /// Query a (Java) Legacy Server (1.6 -> 1.4 -> Beta 1.8).
 pub fn query_legacy(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
-    if let Ok(response) = query_legacy_specific(LegacyGroup::V1_6, address, port, timeout_settings.clone()) {
-        return Ok(response);
-    }
+    let v1_6_error = match query_legacy_specific(LegacyGroup::V1_6, address, port, timeout_settings.clone()) {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
 
-    if let Ok(response) = query_legacy_specific(LegacyGroup::V1_4, address, port, timeout_settings.clone()) {
-        return Ok(response);
-    }
+    let v1_4_error = match query_legacy_specific(LegacyGroup::V1_4, address, port, timeout_settings.clone()) {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
 
-    if let Ok(response) = query_legacy_specific(LegacyGroup::VB1_8, address, port, timeout_settings) {
-        return Ok(response);
-    }
+    let vb1_8_error = match query_legacy_specific(LegacyGroup::VB1_8, address, port, timeout_settings) {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
 
-    Err(GDError::AutoQuery)
+    Err(auto_query_error(&[("1.6", v1_6_error), ("1.4", v1_4_error), ("Beta 1.8", vb1_8_error)]))
 }
 
 /// Query a specific (Java) Legacy Server.
@@ -61,7 +98,124 @@ pub fn query_legacy_specific(group: LegacyGroup, address: &str, port: u16, timeo
     }
 }
 
+/// Query a specific (Java) Legacy Server through an already created socket (a connected stream).
+pub fn query_legacy_specific_with_socket<S: Socket>(group: LegacyGroup, socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    match group {
+        LegacyGroup::V1_6 => LegacyV1_6::query_with_socket(socket, timeout_settings),
+        LegacyGroup::V1_4 => LegacyV1_4::query_with_socket(socket, timeout_settings),
+        LegacyGroup::VB1_8 => LegacyBV1_8::query_with_socket(socket, timeout_settings)
+    }
+}
+
 /// Query a Bedrock Server.
 pub fn query_bedrock(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
     Bedrock::query(address, port, timeout_settings)
 }
+
+/// Query a Bedrock Server by its socket address (IPv4 or IPv6).
+pub fn query_bedrock_addr(address: &SocketAddr, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+    retry(timeout_settings, |timeout_settings| query_bedrock_with_socket(UdpSocket::new_with_address(*address)?, timeout_settings))
+}
+
+/// Query a Bedrock Server through an already created socket.
+pub fn query_bedrock_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+    Bedrock::query_with_socket(socket, timeout_settings)
+}
+
+/// Asynchronously queries a Minecraft server, same as [query].
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let java_error = match query_java_async(address, port, timeout_settings.clone()).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    let bedrock_error = match query_bedrock_async(address, port, timeout_settings.clone()).await {
+        Ok(response) => return Ok(Response::from_bedrock_response(response)),
+        Err(error) => error
+    };
+
+    let legacy_error = match query_legacy_async(address, port, timeout_settings).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    Err(auto_query_error(&[("Java", java_error), ("Bedrock", bedrock_error), ("Legacy", legacy_error)]))
+}
+
+/// Asynchronously query a Java Server.
+#[cfg(feature = "async")]
+pub async fn query_java_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    AsyncJava::query(address, port, timeout_settings).await
+}
+
+/// Asynchronously query a Java Server by its socket address (IPv4 or IPv6).
+#[cfg(feature = "async")]
+pub async fn query_java_addr_async(address: &SocketAddr, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    query_java_async(&address.ip().to_string(), address.port(), timeout_settings).await
+}
+
+/// Asynchronously query a Java Server through an already created socket (a connected stream).
+#[cfg(feature = "async")]
+pub async fn query_java_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    AsyncJava::query_with_socket(socket, timeout_settings).await
+}
+
+/// Asynchronously query a (Java) Legacy Server (1.6 -> 1.4 -> Beta 1.8).
+#[cfg(feature = "async")]
+pub async fn query_legacy_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let v1_6_error = match query_legacy_specific_async(LegacyGroup::V1_6, address, port, timeout_settings.clone()).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    let v1_4_error = match query_legacy_specific_async(LegacyGroup::V1_4, address, port, timeout_settings.clone()).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    let vb1_8_error = match query_legacy_specific_async(LegacyGroup::VB1_8, address, port, timeout_settings).await {
+        Ok(response) => return Ok(response),
+        Err(error) => error
+    };
+
+    Err(auto_query_error(&[("1.6", v1_6_error), ("1.4", v1_4_error), ("Beta 1.8", vb1_8_error)]))
+}
+
+/// Asynchronously query a specific (Java) Legacy Server.
+#[cfg(feature = "async")]
+pub async fn query_legacy_specific_async(group: LegacyGroup, address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    match group {
+        LegacyGroup::V1_6 => AsyncLegacyV1_6::query(address, port, timeout_settings).await,
+        LegacyGroup::V1_4 => AsyncLegacyV1_4::query(address, port, timeout_settings).await,
+        LegacyGroup::VB1_8 => AsyncLegacyBV1_8::query(address, port, timeout_settings).await
+    }
+}
+
+/// Asynchronously query a specific (Java) Legacy Server through an already created socket (a connected stream).
+#[cfg(feature = "async")]
+pub async fn query_legacy_specific_with_socket_async<S: AsyncSocket + Send>(group: LegacyGroup, socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    match group {
+        LegacyGroup::V1_6 => AsyncLegacyV1_6::query_with_socket(socket, timeout_settings).await,
+        LegacyGroup::V1_4 => AsyncLegacyV1_4::query_with_socket(socket, timeout_settings).await,
+        LegacyGroup::VB1_8 => AsyncLegacyBV1_8::query_with_socket(socket, timeout_settings).await
+    }
+}
+
+/// Asynchronously query a Bedrock Server.
+#[cfg(feature = "async")]
+pub async fn query_bedrock_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+    AsyncBedrock::query(address, port, timeout_settings).await
+}
+
+/// Asynchronously query a Bedrock Server by its socket address (IPv4 or IPv6).
+#[cfg(feature = "async")]
+pub async fn query_bedrock_addr_async(address: &SocketAddr, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+    query_bedrock_async(&address.ip().to_string(), address.port(), timeout_settings).await
+}
+
+/// Asynchronously query a Bedrock Server through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_bedrock_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<BedrockResponse> {
+    AsyncBedrock::query_with_socket(socket, timeout_settings).await
+}
@@ -5,11 +5,14 @@ by Jaiden Bernard (2021-2022 - MIT) from
 https://github.com/thisjaiden/golden_apple/blob/master/src/lib.rs
 */
 
-use crate::{GDError, GDResult};
+use std::time::Duration;
+use crate::{GDErrorKind, GDResult};
 use crate::bufferer::Bufferer;
+use crate::protocols::types::{GenericPlayer, GenericResponse, SpecificResponse};
 
 /// The type of Minecraft Server you want to query.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Server {
     /// Java Edition.
     Java,
@@ -20,7 +23,8 @@ pub enum Server {
 }
 
 /// Legacy Java (Versions) Groups.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LegacyGroup {
     /// 1.6
     V1_6,
@@ -32,6 +36,7 @@ pub enum LegacyGroup {
 
 /// Information about a player.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Player {
     pub name: String,
     pub id: String
@@ -39,6 +44,7 @@ pub struct Player {
 
 /// A query response.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
     /// Version name, example: "1.19.2".
     pub version_name: String,
@@ -59,11 +65,14 @@ pub struct Response {
     /// Tells if secure chat is enforced (can be missing).
     pub enforces_secure_chat: Option<bool>,
     /// Tell's the server type.
-    pub server_type: Server
+    pub server_type: Server,
+    /// The round-trip time (measured with a ping).
+    pub ping: Duration
 }
 
 /// A Bedrock Edition query response.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BedrockResponse {
     /// Server edition.
     pub edition: String,
@@ -84,7 +93,9 @@ pub struct BedrockResponse {
     /// Game mode.
     pub game_mode: Option<GameMode>,
     /// Tell's the server type.
-    pub server_type: Server
+    pub server_type: Server,
+    /// The round-trip time (measured with a ping).
+    pub ping: Duration
 }
 
 impl Response {
@@ -99,13 +110,56 @@ impl Response {
             favicon: None,
             previews_chat: None,
             enforces_secure_chat: None,
-            server_type: Server::Bedrock
+            server_type: Server::Bedrock,
+            ping: response.ping
+        }
+    }
+}
+
+impl From<Response> for GenericResponse {
+    fn from(response: Response) -> Self {
+        Self {
+            name: None,
+            description: Some(response.description.clone()),
+            game: Some("Minecraft".to_string()),
+            game_version: Some(response.version_name.clone()),
+            map: None,
+            players_maximum: response.max_players,
+            players_online: response.online_players,
+            players_bots: None,
+            has_password: None,
+            players: response.sample_players.iter().flatten().map(|player| GenericPlayer {
+                name: player.name.clone(),
+                score: None
+            }).collect(),
+            ping: Some(response.ping),
+            inner: SpecificResponse::Minecraft(response)
+        }
+    }
+}
+
+impl From<BedrockResponse> for GenericResponse {
+    fn from(response: BedrockResponse) -> Self {
+        Self {
+            name: Some(response.name.clone()),
+            description: None,
+            game: Some("Minecraft".to_string()),
+            game_version: Some(response.version_name.clone()),
+            map: response.map.clone(),
+            players_maximum: response.max_players,
+            players_online: response.online_players,
+            players_bots: None,
+            has_password: None,
+            players: vec![],
+            ping: Some(response.ping),
+            inner: SpecificResponse::MinecraftBedrock(response)
         }
     }
 }
 
 /// A server's game mode (used only by Bedrock servers).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameMode {
     Survival, Creative, Hardcore, Spectator, Adventure
 }
@@ -118,7 +172,7 @@ impl GameMode {
             "Hardcore" => Ok(GameMode::Hardcore),
             "Spectator" => Ok(GameMode::Spectator),
             "Adventure" => Ok(GameMode::Adventure),
-            _ => Err(GDError::UnknownEnumCast)
+            other => Err(GDErrorKind::UnknownEnumCast(format!("Unknown game mode: {other}.")).into())
         }
     }
 }
@@ -136,7 +190,7 @@ pub(crate) fn get_varint(buffer: &mut Bufferer) -> GDResult<i32> {
 
         // The 5th byte is only allowed to have the 4 smallest bits set
         if i == 4 && (current_byte & 0xf0 != 0) {
-            return Err(GDError::PacketBad("Couldn't parse to VarInt: Overflow.".to_string()))
+            return Err(GDErrorKind::PacketBad("Couldn't parse to VarInt: Overflow.".to_string()).into())
         }
 
         if (current_byte & msb) == 0 {
@@ -172,15 +226,19 @@ pub(crate) fn as_varint(value: i32) -> Vec<u8> {
 }
 
 pub(crate) fn get_string(buffer: &mut Bufferer) -> GDResult<String> {
-    let length = get_varint(buffer)? as usize;
-    let mut text = vec![0; length];
+    let length = get_varint(buffer)?;
+    if length < 0 || length as usize > buffer.remaining_length() {
+        return Err(GDErrorKind::PacketBad("Bad Minecraft String length.".to_string()).into());
+    }
+
+    let mut text = vec![0; length as usize];
 
-    for i in 0..length {
-        text[i] = buffer.get_u8()?;
+    for byte in text.iter_mut() {
+        *byte = buffer.get_u8()?;
     }
 
-    Ok(String::from_utf8(text)
-        .map_err(|_| GDError::PacketBad("Couldn't parse to a Minecraft String.".to_string()))?)
+    String::from_utf8(text)
+        .map_err(|e| GDErrorKind::PacketBad("Couldn't parse to a Minecraft String.".to_string()).context(e))
 }
 
 #[allow(dead_code)]
@@ -10,3 +10,7 @@ pub mod types;
 pub mod valve;
 /// Reference: [Server List Ping](https://wiki.vg/Server_List_Ping)
 pub mod minecraft;
+/// Reference: [Quake Protocol](https://github.com/gamedig/node-gamedig/blob/master/protocols/quake2.js)
+pub mod quake;
+/// Reference: [GameSpy Protocols](https://github.com/gamedig/node-gamedig/tree/master/protocols)
+pub mod gamespy;
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::slice::Iter;
+use crate::{GDErrorKind, GDResult, Stage};
+use crate::protocols::quake::Response;
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::{DeadlineSocket, Socket, UdpSocket};
+use crate::utils::retry;
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncUdpSocket};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
+
+static PACKET_SIZE: usize = 8192;
+static PACKET_HEADER: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+/// What makes a Quake version different from the others.
+pub(crate) trait QuakeClient {
+    type Player;
+
+    /// The request's header (after the 0xFF bytes).
+    fn get_send_header() -> &'static str;
+    /// The response's header (after the 0xFF bytes).
+    fn get_response_header() -> &'static str;
+    /// Parse a player from its line's parts.
+    fn parse_player(parts: Iter<String>) -> GDResult<Self::Player>;
+}
+
+fn request_packet<Client: QuakeClient>() -> Vec<u8> {
+    let mut buf = PACKET_HEADER.to_vec();
+
+    buf.extend(Client::get_send_header().as_bytes());
+    buf.push(0x00);
+
+    buf
+}
+
+/// Get the response's content, without its header.
+fn get_content<Client: QuakeClient>(data: &[u8]) -> GDResult<String> {
+    let header = [&PACKET_HEADER, Client::get_response_header().as_bytes()].concat();
+    if !data.starts_with(&header) {
+        return Err(GDErrorKind::PacketBad("Invalid response header.".to_string()).into());
+    }
+
+    let mut content = &data[header.len()..];
+    if content.starts_with(b"\n") {
+        content = &content[1..];
+    }
+
+    Ok(String::from_utf8_lossy(content).to_string())
+}
+
+/// Parse the backslash-delimited rules (example: `\sv_hostname\A Server\mapname\q3dm17`).
+fn parse_rules(line: &str) -> GDResult<HashMap<String, String>> {
+    let mut parts = line.split('\\');
+
+    if !line.is_empty() && parts.next() != Some("") {
+        return Err(GDErrorKind::PacketBad("Rules must begin with a backslash.".to_string()).into());
+    }
+
+    let mut rules = HashMap::new();
+    while let Some(key) = parts.next() {
+        let value = parts.next()
+            .ok_or_else(|| GDErrorKind::PacketBad(format!("Rule {key} has no value.")))?;
+        rules.insert(key.to_string(), value.to_string());
+    }
+
+    Ok(rules)
+}
+
+/// Split a player line by spaces, the quoted parts being kept together (without the quotes).
+fn split_player_line(line: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            ' ' if !quoted => {
+                if !current.is_empty() || was_quoted {
+                    parts.push(std::mem::take(&mut current));
+                }
+                was_quoted = false;
+            }
+            c => current.push(c)
+        }
+    }
+
+    if !current.is_empty() || was_quoted {
+        parts.push(current);
+    }
+
+    parts
+}
+
+fn get_rule(rules: &HashMap<String, String>, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| rules.get(*key)).cloned()
+}
+
+fn get_response<Client: QuakeClient>(data: &[u8]) -> GDResult<Response<Client::Player>> {
+    let content = get_content::<Client>(data)?;
+    let mut lines = content.split('\n');
+
+    let rules = parse_rules(lines.next().unwrap_or_default())?;
+
+    let mut players = Vec::new();
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        let parts = split_player_line(line.trim());
+        players.push(Client::parse_player(parts.iter())?);
+    }
+
+    let players_maximum = match get_rule(&rules, &["sv_maxclients", "maxclients"]) {
+        None => 0,
+        Some(value) => value.parse()
+            .map_err(|e| GDErrorKind::TypeParse("Couldn't parse the maximum players.".to_string()).context(e))?
+    };
+
+    let players_online = u8::try_from(players.len())
+        .map_err(|e| GDErrorKind::PacketBad(format!("Too many players ({}).", players.len())).context(e))?;
+
+    Ok(Response {
+        name: get_rule(&rules, &["sv_hostname", "hostname"]).unwrap_or_default(),
+        map: get_rule(&rules, &["mapname", "map"]).unwrap_or_default(),
+        players_online,
+        players,
+        players_maximum,
+        version: get_rule(&rules, &["version", "shortversion", "*version", "gamename"]),
+        game_type: get_rule(&rules, &["g_gametype", "gametype"]),
+        has_password: get_rule(&rules, &["g_needpass", "needpass", "pswrd"]).map(|value| value == "1"),
+        rules
+    })
+}
+
+pub(crate) fn client_query<Client: QuakeClient>(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Client::Player>> {
+    retry(timeout_settings, |timeout_settings| client_query_with_socket::<Client, _>(UdpSocket::new(address, port)?, timeout_settings))
+}
+
+pub(crate) fn client_query_with_socket<Client: QuakeClient, S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Client::Player>> {
+    let mut socket = DeadlineSocket::new(socket, &timeout_settings);
+    socket.apply_timeout(timeout_settings)?;
+
+    socket.send(&request_packet::<Client>())
+        .and_then(|_| socket.receive(Some(PACKET_SIZE)))
+        .and_then(|data| get_response::<Client>(&data))
+        .map_err(|e| e.at(Stage::Info))
+}
+
+#[cfg(feature = "async")]
+pub(crate) async fn client_query_async<Client: QuakeClient>(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Client::Player>> {
+    retry_async(timeout_settings, |timeout_settings| async move {
+        client_query_once_async::<Client>(address, port, timeout_settings).await.map_err(|e| e.at(Stage::Info))
+    }).await
+}
+
+#[cfg(feature = "async")]
+async fn client_query_once_async<Client: QuakeClient>(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Client::Player>> {
+    client_query_with_socket_async::<Client, _>(AsyncUdpSocket::new(address, port).await?, timeout_settings).await
+}
+
+#[cfg(feature = "async")]
+pub(crate) async fn client_query_with_socket_async<Client: QuakeClient, S: AsyncSocket + Send>(mut socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Client::Player>> {
+    let response: GDResult<Response<Client::Player>> = with_deadline(timeout_settings, |timeout_settings| async move {
+        socket.apply_timeout(timeout_settings)?;
+        socket.send(&request_packet::<Client>()).await?;
+        get_response::<Client>(&socket.receive(Some(PACKET_SIZE)).await?)
+    }).await;
+
+    response.map_err(|e| e.at(Stage::Info))
+}
+
+/// Parse the next part of a player line.
+pub(crate) fn parse_player_part<T: std::str::FromStr>(parts: &mut Iter<String>, name: &str) -> GDResult<T>
+    where T::Err: std::error::Error + Send + Sync + 'static {
+    parts.next()
+        .ok_or_else(|| GDErrorKind::PacketBad(format!("Player line is missing the {name}.")))?
+        .parse()
+        .map_err(|e| GDErrorKind::TypeParse(format!("Couldn't parse the player's {name}.")).context(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let rules = parse_rules("\\sv_hostname\\A Server\\mapname\\q3dm17").unwrap();
+
+        assert_eq!(rules.get("sv_hostname").unwrap(), "A Server");
+        assert_eq!(rules.get("mapname").unwrap(), "q3dm17");
+        assert!(parse_rules("\\key").is_err());
+        assert!(parse_rules("key\\value").is_err());
+    }
+
+    #[test]
+    fn player_line() {
+        assert_eq!(split_player_line("5 48 \"The ^1Player\""), vec!["5", "48", "The ^1Player"]);
+        assert_eq!(split_player_line("1 0 \"\" \"skin\" 4 5"), vec!["1", "0", "", "skin", "4", "5"]);
+    }
+
+    #[test]
+    fn response() {
+        let data = b"\xFF\xFF\xFF\xFFstatusResponse\n\\sv_hostname\\A Server\\mapname\\q3dm17\\sv_maxclients\\16\\g_needpass\\0\n5 48 \"Player\"\n-2 0 \"Bot\"\n";
+        let response = get_response::<crate::protocols::quake::three::QuakeThree>(data).unwrap();
+
+        assert_eq!(response.name, "A Server");
+        assert_eq!(response.map, "q3dm17");
+        assert_eq!(response.players_online, 2);
+        assert_eq!(response.players_maximum, 16);
+        assert_eq!(response.has_password, Some(false));
+        assert_eq!(response.players[1].score, -2);
+        assert_eq!(response.players[1].name, "Bot");
+
+        assert!(get_response::<crate::protocols::quake::two::QuakeTwo>(data).is_err());
+    }
+
+    #[test]
+    fn too_many_players() {
+        let mut data = b"\xFF\xFF\xFF\xFFstatusResponse\n\\sv_hostname\\A Server\n".to_vec();
+        for _ in 0..256 {
+            data.extend(b"0 0 \"Player\"\n");
+        }
+
+        let error = get_response::<crate::protocols::quake::three::QuakeThree>(&data).unwrap_err();
+        assert!(matches!(error.kind(), GDErrorKind::PacketBad(_)));
+    }
+}
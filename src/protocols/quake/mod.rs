@@ -0,0 +1,12 @@
+/// The implementation shared by all the versions.
+mod client;
+/// All types used by the implementation.
+pub mod types;
+/// Quake 1 (QuakeWorld).
+pub mod one;
+/// Quake 2.
+pub mod two;
+/// Quake 3 (and the id Tech 3 games).
+pub mod three;
+
+pub use types::*;
@@ -0,0 +1,80 @@
+use std::slice::Iter;
+use crate::GDResult;
+use crate::protocols::quake::client::{client_query, client_query_with_socket, parse_player_part, QuakeClient};
+#[cfg(feature = "async")]
+use crate::protocols::quake::client::{client_query_async, client_query_with_socket_async};
+use crate::protocols::quake::Response;
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::Socket;
+#[cfg(feature = "async")]
+use crate::socket::AsyncSocket;
+
+/// A Quake 1 player.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Player {
+    /// The player's id.
+    pub id: u32,
+    /// The player's score (frags).
+    pub score: i32,
+    /// For how long the player has been connected (in minutes).
+    pub time: u32,
+    /// The player's ping.
+    pub ping: u16,
+    /// The player's name.
+    pub name: String,
+    /// The player's skin.
+    pub skin: String,
+    /// The player's top color.
+    pub color_primary: u8,
+    /// The player's bottom color.
+    pub color_secondary: u8
+}
+
+pub(crate) struct QuakeOne;
+impl QuakeClient for QuakeOne {
+    type Player = Player;
+
+    fn get_send_header() -> &'static str {
+        "status"
+    }
+
+    fn get_response_header() -> &'static str {
+        "n"
+    }
+
+    fn parse_player(mut parts: Iter<String>) -> GDResult<Self::Player> {
+        Ok(Player {
+            id: parse_player_part(&mut parts, "id")?,
+            score: parse_player_part(&mut parts, "score")?,
+            time: parse_player_part(&mut parts, "time")?,
+            ping: parse_player_part(&mut parts, "ping")?,
+            name: parse_player_part(&mut parts, "name")?,
+            skin: parse_player_part(&mut parts, "skin")?,
+            color_primary: parse_player_part(&mut parts, "top color")?,
+            color_secondary: parse_player_part(&mut parts, "bottom color")?
+        })
+    }
+}
+
+/// Query a Quake 1 (QuakeWorld) server.
+pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query::<QuakeOne>(address, port, timeout_settings)
+}
+
+/// Query a Quake 1 (QuakeWorld) server through an already created socket.
+pub fn query_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_with_socket::<QuakeOne, S>(socket, timeout_settings)
+}
+
+/// Asynchronously query a Quake 1 (QuakeWorld) server.
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_async::<QuakeOne>(address, port, timeout_settings).await
+}
+
+/// Asynchronously query a Quake 1 (QuakeWorld) server through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_with_socket_async::<QuakeOne, S>(socket, timeout_settings).await
+}
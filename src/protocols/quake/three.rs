@@ -0,0 +1,52 @@
+use std::slice::Iter;
+use crate::GDResult;
+use crate::protocols::quake::client::{client_query, client_query_with_socket, QuakeClient};
+#[cfg(feature = "async")]
+use crate::protocols::quake::client::{client_query_async, client_query_with_socket_async};
+use crate::protocols::quake::Response;
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::Socket;
+#[cfg(feature = "async")]
+use crate::socket::AsyncSocket;
+
+/// A Quake 3 player, which is the same as a Quake 2 one.
+pub use crate::protocols::quake::two::Player;
+
+pub(crate) struct QuakeThree;
+impl QuakeClient for QuakeThree {
+    type Player = Player;
+
+    fn get_send_header() -> &'static str {
+        "getstatus"
+    }
+
+    fn get_response_header() -> &'static str {
+        "statusResponse"
+    }
+
+    fn parse_player(parts: Iter<String>) -> GDResult<Self::Player> {
+        crate::protocols::quake::two::parse_player(parts)
+    }
+}
+
+/// Query a Quake 3 (or any id Tech 3 game) server.
+pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query::<QuakeThree>(address, port, timeout_settings)
+}
+
+/// Query a Quake 3 server through an already created socket.
+pub fn query_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_with_socket::<QuakeThree, S>(socket, timeout_settings)
+}
+
+/// Asynchronously query a Quake 3 (or any id Tech 3 game) server.
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_async::<QuakeThree>(address, port, timeout_settings).await
+}
+
+/// Asynchronously query a Quake 3 server through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_with_socket_async::<QuakeThree, S>(socket, timeout_settings).await
+}
@@ -0,0 +1,69 @@
+use std::slice::Iter;
+use crate::GDResult;
+use crate::protocols::quake::client::{client_query, client_query_with_socket, parse_player_part, QuakeClient};
+#[cfg(feature = "async")]
+use crate::protocols::quake::client::{client_query_async, client_query_with_socket_async};
+use crate::protocols::quake::Response;
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::Socket;
+#[cfg(feature = "async")]
+use crate::socket::AsyncSocket;
+
+/// A Quake 2 (and Quake 3) player.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Player {
+    /// The player's score (frags).
+    pub score: i32,
+    /// The player's ping.
+    pub ping: u16,
+    /// The player's name.
+    pub name: String
+}
+
+pub(crate) fn parse_player(mut parts: Iter<String>) -> GDResult<Player> {
+    Ok(Player {
+        score: parse_player_part(&mut parts, "score")?,
+        ping: parse_player_part(&mut parts, "ping")?,
+        name: parse_player_part(&mut parts, "name")?
+    })
+}
+
+pub(crate) struct QuakeTwo;
+impl QuakeClient for QuakeTwo {
+    type Player = Player;
+
+    fn get_send_header() -> &'static str {
+        "status"
+    }
+
+    fn get_response_header() -> &'static str {
+        "print"
+    }
+
+    fn parse_player(parts: Iter<String>) -> GDResult<Self::Player> {
+        parse_player(parts)
+    }
+}
+
+/// Query a Quake 2 server.
+pub fn query(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query::<QuakeTwo>(address, port, timeout_settings)
+}
+
+/// Query a Quake 2 server through an already created socket.
+pub fn query_with_socket<S: Socket>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_with_socket::<QuakeTwo, S>(socket, timeout_settings)
+}
+
+/// Asynchronously query a Quake 2 server.
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_async::<QuakeTwo>(address, port, timeout_settings).await
+}
+
+/// Asynchronously query a Quake 2 server through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_with_socket_async<S: AsyncSocket + Send>(socket: S, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response<Player>> {
+    client_query_with_socket_async::<QuakeTwo, S>(socket, timeout_settings).await
+}
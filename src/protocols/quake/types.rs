@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use crate::protocols::quake::{one, two};
+use crate::protocols::types::{GenericPlayer, GenericResponse, SpecificResponse};
+
+/// A query response.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response<P> {
+    /// Name of the server.
+    pub name: String,
+    /// Map name.
+    pub map: String,
+    /// Players on the server.
+    pub players: Vec<P>,
+    /// Number of players on the server.
+    pub players_online: u8,
+    /// Maximum number of players the server reports it can hold.
+    pub players_maximum: u8,
+    /// Version of the game installed on the server (can be missing).
+    pub version: Option<String>,
+    /// The game type (can be missing).
+    pub game_type: Option<String>,
+    /// Indicates whether the server requires a password (can be missing).
+    pub has_password: Option<bool>,
+    /// All the rules (key/values) that the server reported.
+    pub rules: HashMap<String, String>
+}
+
+impl From<Response<one::Player>> for GenericResponse {
+    fn from(response: Response<one::Player>) -> Self {
+        into_generic(response, |player| (player.name.clone(), player.score), SpecificResponse::QuakeOne)
+    }
+}
+
+impl From<Response<two::Player>> for GenericResponse {
+    fn from(response: Response<two::Player>) -> Self {
+        into_generic(response, |player| (player.name.clone(), player.score), SpecificResponse::QuakeTwo)
+    }
+}
+
+/// Every version shares the response, only the players (their name and score) differ.
+fn into_generic<P>(response: Response<P>, player: fn(&P) -> (String, i32), inner: fn(Response<P>) -> SpecificResponse) -> GenericResponse {
+    GenericResponse {
+        name: Some(response.name.clone()),
+        description: None,
+        game: None,
+        game_version: response.version.clone(),
+        map: Some(response.map.clone()),
+        players_maximum: response.players_maximum as u32,
+        players_online: response.players_online as u32,
+        players_bots: None,
+        has_password: response.has_password,
+        players: response.players.iter().map(|p| {
+            let (name, score) = player(p);
+            GenericPlayer {
+                name,
+                score: Some(score)
+            }
+        }).collect(),
+        ping: None,
+        inner: inner(response)
+    }
+}
@@ -1,31 +1,43 @@
-use std::time::Duration;
-use crate::{GDError, GDResult};
+use std::time::{Duration, Instant};
+use crate::{GDErrorKind, GDResult};
 
-/// Timeout settings for socket operations
-#[derive(Clone)]
+/// Timeout settings for socket operations, with the query's [RetrySettings].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimeoutSettings {
     read: Option<Duration>,
-    write: Option<Duration>
+    write: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    retry: RetrySettings
 }
 
 impl TimeoutSettings {
-    /// Construct new settings, passing None will block indefinitely. Passing zero Duration throws GDError::[InvalidInput](GDError::InvalidInput).
+    /// Construct new settings, passing None will block indefinitely. Passing zero Duration throws GDErrorKind::[InvalidInput](GDErrorKind::InvalidInput).
     pub fn new(read: Option<Duration>, write: Option<Duration>) -> GDResult<Self> {
         if let Some(read_duration) = read {
             if read_duration == Duration::new(0, 0) {
-                return Err(GDError::InvalidInput("Can't pass duration 0 to timeout settings.".to_string()))
+                return Err(GDErrorKind::InvalidInput("Can't pass duration 0 to timeout settings.".to_string()).into())
             }
         }
 
         if let Some(write_duration) = write {
             if write_duration == Duration::new(0, 0) {
-                return Err(GDError::InvalidInput("Can't pass duration 0 to timeout settings.".to_string()))
+                return Err(GDErrorKind::InvalidInput("Can't pass duration 0 to timeout settings.".to_string()).into())
             }
         }
 
         Ok(Self {
             read,
-            write
+            write,
+            retry: RetrySettings::default()
+        })
+    }
+
+    /// Construct new settings that also retry the queries, see [new](TimeoutSettings::new).
+    pub fn new_with_retry(read: Option<Duration>, write: Option<Duration>, retry: RetrySettings) -> GDResult<Self> {
+        Ok(Self {
+            retry,
+            ..TimeoutSettings::new(read, write)?
         })
     }
 
@@ -38,14 +50,182 @@ impl TimeoutSettings {
     pub fn get_write(&self) -> Option<Duration> {
         self.write
     }
+
+    /// Get the retry settings.
+    pub fn get_retry(&self) -> &RetrySettings {
+        &self.retry
+    }
+
+    /// The settings of one attempt of a query: no retries and what's left until the deadline.
+    pub(crate) fn for_attempt(&self, deadline: Option<Instant>) -> Self {
+        Self {
+            retry: RetrySettings {
+                retries: 0,
+                deadline: deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())),
+                ..self.retry.clone()
+            },
+            ..self.clone()
+        }
+    }
+
+    /// The settings limited by a deadline: the timeouts are at most the time that's left, None if it passed.
+    pub(crate) fn until(&self, deadline: Instant) -> Option<Self> {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left == Duration::new(0, 0) {
+            return None;
+        }
+
+        Some(Self {
+            read: Some(self.read.map_or(left, |read| read.min(left))),
+            write: Some(self.write.map_or(left, |write| write.min(left))),
+            ..self.clone()
+        })
+    }
 }
 
 impl Default for TimeoutSettings {
-    /// Default values are 4 seconds for both read and write.
+    /// Default values are 4 seconds for both read and write, without retries.
     fn default() -> Self {
         Self {
             read: Some(Duration::from_secs(4)),
-            write: Some(Duration::from_secs(4))
+            write: Some(Duration::from_secs(4)),
+            retry: RetrySettings::default()
         }
     }
 }
+
+/// How a query is retried when it fails because of the network (example: a dropped UDP packet) and how
+/// long it can take in total.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetrySettings {
+    retries: u8,
+    backoff: Duration,
+    deadline: Option<Duration>
+}
+
+impl RetrySettings {
+    /// Construct new settings: how many times to retry, the wait before the first retry (doubled for every
+    /// next one) and the deadline of the whole query (every request and retry included), None being no
+    /// deadline. Passing a zero deadline throws GDErrorKind::[InvalidInput](GDErrorKind::InvalidInput).
+    pub fn new(retries: u8, backoff: Duration, deadline: Option<Duration>) -> GDResult<Self> {
+        if deadline == Some(Duration::new(0, 0)) {
+            return Err(GDErrorKind::InvalidInput("Can't pass duration 0 as the deadline.".to_string()).into());
+        }
+
+        Ok(Self {
+            retries,
+            backoff,
+            deadline
+        })
+    }
+
+    /// Get the number of retries.
+    pub fn get_retries(&self) -> u8 {
+        self.retries
+    }
+
+    /// Get the wait before the first retry.
+    pub fn get_backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    /// Get the deadline of the whole query.
+    pub fn get_deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    /// The wait before a retry (the first one being 0).
+    pub(crate) fn backoff_before(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+impl Default for RetrySettings {
+    /// Default values are no retries, a 500 milliseconds backoff and no deadline.
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff: Duration::from_millis(500),
+            deadline: None
+        }
+    }
+}
+
+/// A player, with only the data that every protocol can provide.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenericPlayer {
+    /// The player's name.
+    pub name: String,
+    /// The player's score (can be missing).
+    pub score: Option<i32>
+}
+
+/// The original response that a [GenericResponse] was made from.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpecificResponse {
+    Valve(crate::protocols::valve::game::Response),
+    TheShip(crate::games::ts::Response),
+    Minecraft(crate::protocols::minecraft::Response),
+    MinecraftBedrock(crate::protocols::minecraft::BedrockResponse),
+    QuakeOne(crate::protocols::quake::Response<crate::protocols::quake::one::Player>),
+    /// Quake 2 and 3 (as they have the same player type).
+    QuakeTwo(crate::protocols::quake::Response<crate::protocols::quake::two::Player>),
+    GameSpy(crate::protocols::gamespy::Response),
+    TeamSpeak3(crate::services::teamspeak3::Response)
+}
+
+/// A response that every protocol, game and service response can be converted into (using `From`), so
+/// that any server can be treated the same way.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenericResponse {
+    /// Name of the server (can be missing).
+    pub name: Option<String>,
+    /// Description or MOTD of the server (can be missing).
+    pub description: Option<String>,
+    /// The game's name (can be missing).
+    pub game: Option<String>,
+    /// Version of the game installed on the server (can be missing).
+    pub game_version: Option<String>,
+    /// Map name (can be missing).
+    pub map: Option<String>,
+    /// Maximum number of players the server reports it can hold.
+    pub players_maximum: u32,
+    /// Number of players on the server.
+    pub players_online: u32,
+    /// Number of bots on the server (can be missing).
+    pub players_bots: Option<u32>,
+    /// Indicates whether the server requires a password (can be missing).
+    pub has_password: Option<bool>,
+    /// The players the server reported (some servers report only some of them or none).
+    pub players: Vec<GenericPlayer>,
+    /// The round-trip time (measured only by the Valve and Minecraft protocols).
+    pub ping: Option<Duration>,
+    /// The original response, with all the protocol specific data.
+    pub inner: SpecificResponse
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_field_names() {
+        let player = GenericPlayer {
+            name: "Someone".to_string(),
+            score: Some(-2)
+        };
+
+        let json = serde_json::to_string(&player).unwrap();
+        assert_eq!(json, r#"{"name":"Someone","score":-2}"#);
+
+        let player: GenericPlayer = serde_json::from_str(&json).unwrap();
+        assert_eq!(player.score, Some(-2));
+
+        let settings: TimeoutSettings = serde_json::from_str(&serde_json::to_string(&TimeoutSettings::default()).unwrap()).unwrap();
+        assert_eq!(settings.get_read(), Some(Duration::from_secs(4)));
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use crate::{GDError, GDErrorKind, GDResult};
+use crate::bufferer::{Bufferer, Endianess};
+use crate::protocols::types::TimeoutSettings;
+use crate::socket::{Socket, UdpSocket};
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncUdpSocket};
+
+/// The default master server address.
+pub const DEFAULT_ADDRESS: &str = "hl2master.steampowered.com";
+/// The default master server port.
+pub const DEFAULT_PORT: u16 = 27011;
+
+static PACKET_SIZE: usize = 1500;
+static RESPONSE_HEADER: [u8; 6] = [0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A];
+
+/// The region that the servers are located in, see the [protocol](https://developer.valvesoftware.com/wiki/Master_Server_Query_Protocol#Region_codes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum Region {
+    UsEastCoast = 0x00,
+    UsWestCoast = 0x01,
+    SouthAmerica = 0x02,
+    Europe = 0x03,
+    Asia = 0x04,
+    Australia = 0x05,
+    MiddleEast = 0x06,
+    Africa = 0x07,
+    /// All the regions.
+    RestOfTheWorld = 0xFF
+}
+
+/// A search filter, see the [protocol](https://developer.valvesoftware.com/wiki/Master_Server_Query_Protocol#Filter).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Filter {
+    /// Servers running dedicated.
+    Dedicated,
+    /// Servers using anti-cheat technology (VAC, but potentially others as well).
+    Secure,
+    /// Servers running the specified modification (ex. cstrike).
+    GameDir(String),
+    /// Servers running the specified map (ex. cs_italy).
+    Map(String),
+    /// Servers running on a Linux platform.
+    Linux,
+    /// Servers that are not password protected.
+    NoPassword,
+    /// Servers that are not empty.
+    NotEmpty,
+    /// Servers that are not full.
+    NotFull,
+    /// Servers that are spectator proxies.
+    Proxy,
+    /// Servers that are running the game with the specified app id.
+    AppId(u32),
+    /// Servers that are NOT running the game with the specified app id.
+    NotAppId(u32),
+    /// Servers that are empty.
+    NoPlayers,
+    /// Servers that are whitelisted.
+    Whitelisted,
+    /// Servers with all of the given tag(s) in sv_tags.
+    GameType(Vec<String>),
+    /// Servers with all of the given tag(s) in their 'hidden' tags (L4D2).
+    GameData(Vec<String>),
+    /// Servers with any of the given tag(s) in their 'hidden' tags (L4D2).
+    GameDataOr(Vec<String>),
+    /// Servers with their hostname matching the given one (can use '*' as a wildcard).
+    NameMatch(String),
+    /// Servers running the given version (can use '*' as a wildcard).
+    VersionMatch(String),
+    /// Return only one server for each unique IP address matched.
+    CollapseAddressHash,
+    /// Return only servers on the specified IP address (port supported and optional).
+    GameAddress(String),
+    /// A special filter, specifies that servers matching any of the given filters should not be returned.
+    Nor(Vec<Filter>),
+    /// A special filter, specifies that servers matching all of the given filters should not be returned.
+    Nand(Vec<Filter>),
+    /// An already formatted filter string, example: `\appid\440\empty\1`.
+    Raw(String)
+}
+
+impl Filter {
+    fn to_filter_string(&self) -> String {
+        match self {
+            Filter::Dedicated => "\\dedicated\\1".to_string(),
+            Filter::Secure => "\\secure\\1".to_string(),
+            Filter::GameDir(dir) => format!("\\gamedir\\{dir}"),
+            Filter::Map(map) => format!("\\map\\{map}"),
+            Filter::Linux => "\\linux\\1".to_string(),
+            Filter::NoPassword => "\\password\\0".to_string(),
+            Filter::NotEmpty => "\\empty\\1".to_string(),
+            Filter::NotFull => "\\full\\1".to_string(),
+            Filter::Proxy => "\\proxy\\1".to_string(),
+            Filter::AppId(id) => format!("\\appid\\{id}"),
+            Filter::NotAppId(id) => format!("\\napp\\{id}"),
+            Filter::NoPlayers => "\\noplayers\\1".to_string(),
+            Filter::Whitelisted => "\\white\\1".to_string(),
+            Filter::GameType(tags) => format!("\\gametype\\{}", tags.join(",")),
+            Filter::GameData(tags) => format!("\\gamedata\\{}", tags.join(",")),
+            Filter::GameDataOr(tags) => format!("\\gamedataor\\{}", tags.join(",")),
+            Filter::NameMatch(name) => format!("\\name_match\\{name}"),
+            Filter::VersionMatch(version) => format!("\\version_match\\{version}"),
+            Filter::CollapseAddressHash => "\\collapse_addr_hash\\1".to_string(),
+            Filter::GameAddress(address) => format!("\\gameaddr\\{address}"),
+            Filter::Nor(filters) => format!("\\nor\\{}{}", filters.len(), filters.iter().map(Filter::to_filter_string).collect::<String>()),
+            Filter::Nand(filters) => format!("\\nand\\{}{}", filters.len(), filters.iter().map(Filter::to_filter_string).collect::<String>()),
+            Filter::Raw(raw) => raw.clone()
+        }
+    }
+}
+
+/// A collection of search filters, all of them must match for a server to be returned.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchFilters {
+    filters: Vec<Filter>
+}
+
+impl SearchFilters {
+    /// No filters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a filter.
+    pub fn insert(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Get the filters as the protocol filter string.
+    pub fn to_filter_string(&self) -> String {
+        self.filters.iter().map(Filter::to_filter_string).collect()
+    }
+}
+
+fn unspecified_address() -> SocketAddr {
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))
+}
+
+/// Build the request for the page that comes after the seed address.
+fn request_packet(region: Region, search_filters: &SearchFilters, seed: &SocketAddr) -> Vec<u8> {
+    let mut buf = vec![0x31, region as u8];
+
+    buf.extend(seed.to_string().into_bytes());
+    buf.push(0);
+    buf.extend(search_filters.to_filter_string().into_bytes());
+    buf.push(0);
+
+    buf
+}
+
+/// Parse a page of addresses, the unspecified address (0.0.0.0:0) marks the end of the list.
+fn parse_page(data: &[u8]) -> GDResult<Vec<SocketAddr>> {
+    let mut buffer = Bufferer::new_with_data(Endianess::Big, data);
+
+    if !buffer.remaining_data().starts_with(&RESPONSE_HEADER) {
+        return Err(GDErrorKind::PacketBad("Invalid master server response header.".to_string()).into());
+    }
+    buffer.move_position_ahead(RESPONSE_HEADER.len());
+
+    let count = buffer.remaining_length() / 6;
+    if count * 6 != buffer.remaining_length() {
+        return Err(GDErrorKind::PacketBad("Master server response has an incomplete address.".to_string()).into());
+    }
+
+    let mut addresses = Vec::with_capacity(count);
+    for _ in 0..count {
+        let ip = Ipv4Addr::new(buffer.get_u8()?, buffer.get_u8()?, buffer.get_u8()?, buffer.get_u8()?);
+        addresses.push(SocketAddr::V4(SocketAddrV4::new(ip, buffer.get_u16()?)));
+    }
+
+    Ok(addresses)
+}
+
+/// The error of a page that ends with its own seed, requesting the next one would return it again.
+fn stuck_seed(seed: &SocketAddr) -> GDError {
+    GDErrorKind::PacketBad(format!("The master server page after {seed} ends with the same address, the paging doesn't advance.")).into()
+}
+
+/// A Valve master server client.
+pub struct MasterServer {
+    socket: UdpSocket,
+    /// The buffer that every page is received into.
+    buffer: Vec<u8>
+}
+
+impl MasterServer {
+    /// Connect to a master server, providing None to the timeout settings results in using the default values.
+    pub fn new(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
+        let socket = UdpSocket::new(address, port)?;
+        socket.apply_timeout(timeout_settings)?;
+
+        Ok(Self {
+            socket,
+            buffer: Vec::with_capacity(PACKET_SIZE)
+        })
+    }
+
+    /// Get a single page of addresses, starting after the seed address (use 0.0.0.0:0 for the first page).
+    pub fn query_specific(&mut self, region: Region, search_filters: &SearchFilters, seed: &SocketAddr) -> GDResult<Vec<SocketAddr>> {
+        self.socket.send(&request_packet(region, search_filters, seed))?;
+        self.socket.receive_into(&mut self.buffer, Some(PACKET_SIZE))?;
+        parse_page(&self.buffer)
+    }
+
+    /// Iterate over all the servers, the pages are requested as they are needed.
+    pub fn servers(&mut self, region: Region, search_filters: SearchFilters) -> Servers<'_> {
+        Servers {
+            master: self,
+            region,
+            search_filters,
+            addresses: VecDeque::new(),
+            seed: unspecified_address(),
+            finished: false
+        }
+    }
+}
+
+/// An iterator over the servers of a master server, stops after the first error (a page that ends with
+/// its seed being one, as the paging wouldn't advance).
+pub struct Servers<'a> {
+    master: &'a mut MasterServer,
+    region: Region,
+    search_filters: SearchFilters,
+    addresses: VecDeque<SocketAddr>,
+    seed: SocketAddr,
+    finished: bool
+}
+
+impl Iterator for Servers<'_> {
+    type Item = GDResult<SocketAddr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.addresses.is_empty() && !self.finished {
+            match self.master.query_specific(self.region, &self.search_filters, &self.seed) {
+                Err(error) => {
+                    self.finished = true;
+                    return Some(Err(error));
+                }
+                Ok(page) => {
+                    match page.last() {
+                        None => self.finished = true,
+                        Some(last) if *last != unspecified_address() && *last == self.seed => {
+                            self.finished = true;
+                            return Some(Err(stuck_seed(&self.seed)));
+                        }
+                        Some(last) => self.seed = *last
+                    }
+
+                    self.addresses.extend(page);
+                }
+            }
+        }
+
+        match self.addresses.pop_front() {
+            Some(address) if address == unspecified_address() => {
+                self.finished = true;
+                self.addresses.clear();
+                None
+            }
+            address => address.map(Ok)
+        }
+    }
+}
+
+/// Get all the servers from the default master server in a region with the given filters.
+pub fn query(region: Region, search_filters: Option<SearchFilters>) -> GDResult<Vec<SocketAddr>> {
+    let mut master = MasterServer::new(DEFAULT_ADDRESS, DEFAULT_PORT, None)?;
+    let servers = master.servers(region, search_filters.unwrap_or_default());
+
+    servers.collect()
+}
+
+/// An async Valve master server client.
+#[cfg(feature = "async")]
+pub struct AsyncMasterServer {
+    socket: AsyncUdpSocket,
+    /// The buffer that every page is received into.
+    buffer: Vec<u8>
+}
+
+#[cfg(feature = "async")]
+impl AsyncMasterServer {
+    /// Connect to a master server, providing None to the timeout settings results in using the default values.
+    pub async fn new(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
+        let mut socket = AsyncUdpSocket::new(address, port).await?;
+        socket.apply_timeout(timeout_settings)?;
+
+        Ok(Self {
+            socket,
+            buffer: Vec::with_capacity(PACKET_SIZE)
+        })
+    }
+
+    /// Get a single page of addresses, starting after the seed address (use 0.0.0.0:0 for the first page).
+    pub async fn query_specific(&mut self, region: Region, search_filters: &SearchFilters, seed: &SocketAddr) -> GDResult<Vec<SocketAddr>> {
+        self.socket.send(&request_packet(region, search_filters, seed)).await?;
+        self.socket.receive_into(&mut self.buffer, Some(PACKET_SIZE)).await?;
+        parse_page(&self.buffer)
+    }
+
+    /// Get all the servers, requesting every page, fails if a page ends with its seed (the paging wouldn't advance).
+    pub async fn servers(&mut self, region: Region, search_filters: &SearchFilters) -> GDResult<Vec<SocketAddr>> {
+        let mut servers = Vec::new();
+        let mut seed = unspecified_address();
+
+        loop {
+            let page = self.query_specific(region, search_filters, &seed).await?;
+
+            match page.last() {
+                None => return Ok(servers),
+                Some(last) if *last == unspecified_address() => {
+                    servers.extend(&page[..page.len() - 1]);
+                    return Ok(servers);
+                }
+                Some(last) if *last == seed => return Err(stuck_seed(&seed)),
+                Some(last) => seed = *last
+            }
+
+            servers.extend(page);
+        }
+    }
+}
+
+/// Asynchronously get all the servers from the default master server, same as [query].
+#[cfg(feature = "async")]
+pub async fn query_async(region: Region, search_filters: Option<SearchFilters>) -> GDResult<Vec<SocketAddr>> {
+    let mut master = AsyncMasterServer::new(DEFAULT_ADDRESS, DEFAULT_PORT, None).await?;
+    master.servers(region, &search_filters.unwrap_or_default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net;
+    use std::thread;
+    use super::*;
+
+    fn page(addresses: &[([u8; 4], u16)]) -> Vec<u8> {
+        let mut data = RESPONSE_HEADER.to_vec();
+        for (ip, port) in addresses {
+            data.extend(ip);
+            data.extend(port.to_be_bytes());
+        }
+
+        data
+    }
+
+    #[test]
+    fn filter_string() {
+        let filters = SearchFilters::new()
+            .insert(Filter::AppId(440))
+            .insert(Filter::NotEmpty)
+            .insert(Filter::GameType(vec!["alltalk".to_string(), "nocrits".to_string()]))
+            .insert(Filter::Nor(vec![Filter::Map("ctf_2fort".to_string()), Filter::Proxy]));
+
+        assert_eq!(filters.to_filter_string(), "\\appid\\440\\empty\\1\\gametype\\alltalk,nocrits\\nor\\2\\map\\ctf_2fort\\proxy\\1");
+    }
+
+    #[test]
+    fn request() {
+        let request = request_packet(Region::Europe, &SearchFilters::new().insert(Filter::Raw("\\appid\\440".to_string())), &unspecified_address());
+        assert_eq!(request, b"\x31\x030.0.0.0:0\0\\appid\\440\0".to_vec());
+    }
+
+    #[test]
+    fn bad_page() {
+        assert!(parse_page(&[0xFF, 0xFF, 0xFF, 0xFF, 0x66]).is_err());
+        assert!(parse_page(&[0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A, 127, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn paging_against_local_master() {
+        let server = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let stand_in = thread::spawn(move || {
+            let mut seeds = Vec::new();
+            let pages = [
+                page(&[([10, 0, 0, 1], 27015), ([10, 0, 0, 2], 27016)]),
+                page(&[([10, 0, 0, 3], 27017), ([0, 0, 0, 0], 0)])
+            ];
+
+            for response in pages.iter() {
+                let mut buf = [0; 256];
+                let (size, client) = server.recv_from(&mut buf).unwrap();
+                let seed_end = buf[2..size].iter().position(|&b| b == 0).unwrap() + 2;
+                seeds.push(String::from_utf8(buf[2..seed_end].to_vec()).unwrap());
+                server.send_to(response, client).unwrap();
+            }
+
+            seeds
+        });
+
+        let mut master = MasterServer::new("127.0.0.1", port, None).unwrap();
+        let servers: Vec<SocketAddr> = master.servers(Region::RestOfTheWorld, SearchFilters::new())
+            .collect::<GDResult<Vec<SocketAddr>>>().unwrap();
+
+        assert_eq!(servers, vec![
+            "10.0.0.1:27015".parse().unwrap(),
+            "10.0.0.2:27016".parse().unwrap(),
+            "10.0.0.3:27017".parse().unwrap()
+        ]);
+        assert_eq!(stand_in.join().unwrap(), vec!["0.0.0.0:0", "10.0.0.2:27016"]);
+    }
+
+    #[test]
+    fn stuck_paging() {
+        let server = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let stand_in = thread::spawn(move || {
+            let response = page(&[([10, 0, 0, 1], 27015), ([10, 0, 0, 2], 27016)]);
+
+            //the same page, whatever the seed is
+            for _ in 0..2 {
+                let mut buf = [0; 256];
+                let (_, client) = server.recv_from(&mut buf).unwrap();
+                server.send_to(&response, client).unwrap();
+            }
+        });
+
+        let mut master = MasterServer::new("127.0.0.1", port, None).unwrap();
+        let mut servers = master.servers(Region::RestOfTheWorld, SearchFilters::new());
+
+        assert_eq!(servers.next().unwrap().unwrap(), "10.0.0.1:27015".parse().unwrap());
+        assert_eq!(servers.next().unwrap().unwrap(), "10.0.0.2:27016".parse().unwrap());
+        assert!(servers.next().unwrap().is_err());
+        assert!(servers.next().is_none());
+
+        stand_in.join().unwrap();
+    }
+}
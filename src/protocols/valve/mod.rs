@@ -3,6 +3,8 @@
 pub mod protocol;
 /// All types used by the implementation.
 pub mod types;
+/// The master server client, reference: [Master Server Query Protocol](https://developer.valvesoftware.com/wiki/Master_Server_Query_Protocol)
+pub mod master;
 
 pub use protocol::*;
 pub use types::*;
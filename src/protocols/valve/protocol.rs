@@ -1,436 +1,1000 @@
-use bzip2_rs::decoder::Decoder;
-use crate::{GDError, GDResult};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use bzip2_rs::DecoderReader;
+use crate::{GDError, GDErrorKind, GDResult, Stage};
 use crate::bufferer::{Bufferer, Endianess};
 use crate::protocols::types::TimeoutSettings;
 use crate::protocols::valve::{App, ModData, SteamID};
 use crate::protocols::valve::types::{Environment, ExtraData, GatheringSettings, Request, Response, Server, ServerInfo, ServerPlayer, ServerRule, TheShip};
-use crate::socket::{Socket, UdpSocket};
-use crate::utils::u8_lower_upper;
-
-#[derive(Debug, Clone)]
-struct Packet {
-    pub header: u32,
-    pub kind: u8,
-    pub payload: Vec<u8>
+use crate::socket::{DeadlineSocket, Socket, UdpSocket};
+#[cfg(feature = "async")]
+use crate::socket::{AsyncSocket, AsyncUdpSocket};
+use crate::utils::{retry, u8_lower_upper};
+#[cfg(feature = "async")]
+use crate::utils::{retry_async, with_deadline};
+
+/// A received packet, its payload being borrowed from the data it was received into.
+#[derive(Debug)]
+struct Packet<'a> {
+    kind: u8,
+    payload: &'a [u8]
 }
 
-impl Packet {
-    fn new(buffer: &mut Bufferer) -> GDResult<Self> {
+impl<'a> Packet<'a> {
+    fn new(data: &'a [u8]) -> GDResult<Self> {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, data);
+        buffer.get_u32()?; //the header
+
         Ok(Self {
-            header: buffer.get_u32()?,
             kind: buffer.get_u8()?,
             payload: buffer.get_data_in_front_of_position()
         })
     }
+}
 
-    fn challenge(kind: Request, challenge: Vec<u8>) -> Self {
-        let mut initial = Packet::initial(kind);
-
-        Self {
-            header: initial.header,
-            kind: initial.kind,
-            payload: match kind {
-                Request::INFO => {
-                    initial.payload.extend(challenge);
-                    initial.payload
-                },
-                _ => challenge
-            }
-        }
-    }
+/// Makes a request's packet, with the challenge (or without, asking for one, as the info request doesn't
+/// need one unless the server asks for it).
+fn request_packet(kind: Request, challenge: Option<&[u8]>) -> Vec<u8> {
+    let mut packet = vec![0xFF, 0xFF, 0xFF, 0xFF, kind as u8];
 
-    fn initial(kind: Request) -> Self {
-        Self {
-            header: 4294967295, //FF FF FF FF
-            kind: kind as u8,
-            payload: match kind {
-                Request::INFO => String::from("Source Engine Query\0").into_bytes(),
-                _ => vec![0xFF, 0xFF, 0xFF, 0xFF]
-            }
-        }
+    match kind {
+        Request::Info => {
+            packet.extend(b"Source Engine Query\0");
+            packet.extend(challenge.unwrap_or_default());
+        },
+        _ => packet.extend(challenge.unwrap_or(&[0xFF, 0xFF, 0xFF, 0xFF]))
     }
 
-    fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::from(self.header.to_be_bytes());
-
-        buf.push(self.kind);
-        buf.extend(&self.payload);
-
-        buf
-    }
+    packet
 }
 
 #[derive(Debug)]
-#[allow(dead_code)] //remove this later on
 struct SplitPacket {
-    pub header: u32,
-    pub id: u32,
-    pub total: u8,
-    pub number: u8,
-    pub size: u16,
-    pub compressed: bool,
-    pub decompressed_size: Option<u32>,
-    pub uncompressed_crc32: Option<u32>,
-    payload: Vec<u8>
+    id: u32,
+    total: u8,
+    number: u8,
+    compressed: bool,
+    decompressed_size: Option<u32>,
+    uncompressed_crc32: Option<u32>,
+    /// The whole received packet (moved in, not copied), its payload begins at `payload_start`.
+    data: Vec<u8>,
+    payload_start: usize
 }
 
 impl SplitPacket {
-    fn new(app: &App, protocol: u8, buffer: &mut Bufferer) -> GDResult<Self> {
-        let header = buffer.get_u32()?;
+    fn new(app: &App, protocol: u8, data: Vec<u8>) -> GDResult<Self> {
+        let mut buffer = Bufferer::new_with_data(Endianess::Little, &data);
+        buffer.get_u32()?; //the header
         let id = buffer.get_u32()?;
-        let (total, number, size, compressed, decompressed_size, uncompressed_crc32) = match app {
+        let (total, number, compressed, decompressed_size, uncompressed_crc32) = match app {
             App::GoldSrc(_) => {
                 let (lower, upper) = u8_lower_upper(buffer.get_u8()?);
-                (lower, upper, 0, false, None, None)
+                (lower, upper, false, None, None)
             }
             App::Source(_) => {
                 let total = buffer.get_u8()?;
                 let number = buffer.get_u8()?;
-                let size = match protocol == 7 && (*app == SteamID::CSS.as_app()) { //certain apps with protocol = 7 doesnt have this field
-                    false => buffer.get_u16()?,
-                    true => 1248
-                };
+                if !(protocol == 7 && (*app == SteamID::CSS.as_app())) { //certain apps with protocol = 7 doesnt have the size field
+                    buffer.get_u16()?;
+                }
                 let compressed = ((id >> 31) & 1) == 1;
-                let (decompressed_size, uncompressed_crc32) = match compressed {
+                let (decompressed_size, uncompressed_crc32) = match compressed && number == 0 { //only the first packet has these
                     false => (None, None),
                     true => (Some(buffer.get_u32()?), Some(buffer.get_u32()?))
                 };
-                (total, number, size, compressed, decompressed_size, uncompressed_crc32)
+                (total, number, compressed, decompressed_size, uncompressed_crc32)
             }
         };
 
         Ok(Self {
-            header,
             id,
             total,
             number,
-            size,
             compressed,
             decompressed_size,
             uncompressed_crc32,
-            payload: buffer.get_data_in_front_of_position()
+            payload_start: data.len() - buffer.remaining_length(),
+            data
         })
     }
 
+    fn payload(&self) -> &[u8] {
+        &self.data[self.payload_start..]
+    }
+}
+
+/// The packets of a split response, they can arrive in any order and more than once.
+struct SplitResponse {
+    total: u8,
+    packets: BTreeMap<u8, SplitPacket>
+}
+
+impl SplitResponse {
+    fn new(total: u8) -> Self {
+        Self {
+            total,
+            packets: BTreeMap::new()
+        }
+    }
+
+    /// Adds a packet, a duplicate (an already received number), one with another total or one with a number
+    /// past the total is ignored.
+    fn add(&mut self, packet: SplitPacket) {
+        if packet.total == self.total && packet.number < packet.total {
+            self.packets.entry(packet.number).or_insert(packet);
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.packets.len() == self.total as usize
+    }
+
+    /// The numbers of the packets that weren't received yet.
+    fn missing(&self) -> Vec<u8> {
+        (0..self.total).filter(|number| !self.packets.contains_key(number)).collect()
+    }
+
+    /// The packets' payloads in order (decompressed, if they are compressed).
     fn get_payload(&self) -> GDResult<Vec<u8>> {
-        if self.compressed {
-            let mut decoder = Decoder::new();
-            decoder.write(&self.payload).map_err(|e| GDError::Decompress(e.to_string()))?;
+        let mut payload = Vec::with_capacity(self.packets.values().map(|packet| packet.payload().len()).sum());
+        for packet in self.packets.values() {
+            payload.extend_from_slice(packet.payload());
+        }
+
+        match self.packets.get(&0) {
+            Some(SplitPacket { compressed: true, decompressed_size: Some(size), uncompressed_crc32: Some(crc32), .. }) =>
+                decompress(&payload, *size as usize, *crc32),
+            _ => Ok(payload)
+        }
+    }
+}
 
-            let decompressed_size = self.decompressed_size.unwrap() as usize;
+/// The split responses being received, by their id, as packets of other responses (example: the rest of
+/// an earlier request's response) can arrive in between.
+#[derive(Default)]
+struct SplitResponses {
+    responses: BTreeMap<u32, SplitResponse>
+}
 
-            let mut decompressed_payload = Vec::with_capacity(decompressed_size);
-            decoder.read(&mut decompressed_payload).map_err(|e| GDError::Decompress(e.to_string()))?;
+impl SplitResponses {
+    /// Adds a packet to its response, returns the response's payload if it got complete.
+    fn add(&mut self, packet: SplitPacket) -> GDResult<Option<Vec<u8>>> {
+        let response = self.responses.entry(packet.id).or_insert_with(|| SplitResponse::new(packet.total));
+        response.add(packet);
 
-            if decompressed_payload.len() != decompressed_size {
-                Err(GDError::Decompress("The decompressed payload size doesn't match the expected one.".to_string()))
-            }
-            else if crc32fast::hash(&decompressed_payload) != self.uncompressed_crc32.unwrap() {
-                Err(GDError::Decompress("The decompressed crc32 hash does not match the expected one.".to_string()))
-            }
-            else {
-                Ok(decompressed_payload)
-            }
-        } else {
-            Ok(self.payload.clone())
+        match response.is_complete() {
+            false => Ok(None),
+            true => response.get_payload().map(Some)
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// The error to return when the packets stopped coming (`error` being why) before any response got
+    /// complete, it tells what's missing from the most complete one.
+    fn incomplete(&self, error: GDError) -> GDError {
+        match self.responses.values().max_by_key(|response| response.packets.len()) {
+            None => error,
+            Some(response) => GDErrorKind::PacketReceive(format!("Incomplete split response, missing packets {:?} of {} ({error}).",
+                                                            response.missing(), response.total)).context(error)
         }
     }
 }
 
-struct ValveProtocol {
-    socket: UdpSocket
+/// Decompresses a (bzip2) payload, checking its size and crc32.
+fn decompress(data: &[u8], decompressed_size: usize, crc32: u32) -> GDResult<Vec<u8>> {
+    //a response can't be bigger than the most packets a split one can have
+    if decompressed_size > u8::MAX as usize * PACKET_SIZE {
+        return Err(GDErrorKind::Decompress(format!("Too big decompressed payload size ({decompressed_size}).")).into());
+    }
+
+    let mut decompressed_payload = Vec::with_capacity(decompressed_size);
+    DecoderReader::new(data)
+        .take(decompressed_size as u64 + 1) //reading one more byte to know if there's more than expected
+        .read_to_end(&mut decompressed_payload)
+        .map_err(|e| GDErrorKind::Decompress(e.to_string()).context(e))?;
+
+    if decompressed_payload.len() != decompressed_size {
+        Err(GDErrorKind::Decompress("The decompressed payload size doesn't match the expected one.".to_string()).into())
+    }
+    else if crc32fast::hash(&decompressed_payload) != crc32 {
+        Err(GDErrorKind::Decompress("The decompressed crc32 hash does not match the expected one.".to_string()).into())
+    }
+    else {
+        Ok(decompressed_payload)
+    }
 }
 
 static PACKET_SIZE: usize = 6144;
 
-impl ValveProtocol {
-    fn new(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
-        let socket = UdpSocket::new(address, port)?;
-        socket.apply_timeout(timeout_settings)?;
+/// Checks whether the received data is the beginning of a split packet.
+fn is_split_packet(data: &[u8]) -> GDResult<bool> {
+    Ok(Bufferer::new_with_data(Endianess::Little, data).get_u8()? == 0xFE)
+}
 
-        Ok(Self {
-            socket
-        })
-    }
+/// What to do after a packet has been received (or failed to be).
+enum Next {
+    /// The response is complete, it's in the buffer.
+    Done,
+    /// Receive the next packet of a split response.
+    Receive,
+    /// Send the request again (as the split response's packets stopped coming), then receive.
+    Retransmit
+}
 
-    fn receive(&mut self, app: &App, protocol: u8, buffer_size: usize) -> GDResult<Packet> {
-        let data = self.socket.receive(Some(buffer_size))?;
-        let mut buffer = Bufferer::new_with_data(Endianess::Little, &data); 
+/// The state of the reception of a response, shared by the sync and the async clients, which only move the
+/// packets between their socket and their buffer.
+struct Reception<'a> {
+    app: &'a App,
+    protocol: u8,
+    split_retransmissions: u8,
+    retransmissions: u8,
+    split_responses: SplitResponses
+}
 
-        let header = buffer.get_u8()?;
-        buffer.move_position_backward(1);
-        if header == 0xFE { //the packet is split
-            let mut main_packet = SplitPacket::new(&app, protocol, &mut buffer)?;
+impl<'a> Reception<'a> {
+    fn new(app: &'a App, protocol: u8, split_retransmissions: u8) -> Self {
+        Self {
+            app,
+            protocol,
+            split_retransmissions,
+            retransmissions: 0,
+            split_responses: SplitResponses::default()
+        }
+    }
 
-            for _ in 1..main_packet.total {
-                let new_data = self.socket.receive(Some(buffer_size))?;
-                buffer = Bufferer::new_with_data(Endianess::Little, &new_data);
-                let chunk_packet = SplitPacket::new(&app, protocol, &mut buffer)?;
-                main_packet.payload.extend(chunk_packet.payload);
+    /// Handles a receive into the buffer, reassembling the response if it's split (the split packets are moved
+    /// out of the buffer, then their payloads are put together in it), when a split response's packets stop
+    /// coming, the request is to be sent again (at most `split_retransmissions` times).
+    fn handle(&mut self, received: GDResult<()>, buffer: &mut Vec<u8>) -> GDResult<Next> {
+        match received {
+            Ok(()) => (),
+            Err(e) if self.split_responses.is_empty() => return Err(e),
+            Err(_) if self.retransmissions < self.split_retransmissions => {
+                self.retransmissions += 1;
+                return Ok(Next::Retransmit);
             }
+            Err(e) => return Err(self.split_responses.incomplete(e))
+        }
 
-            let mut new_packet_buffer = Bufferer::new_with_data(Endianess::Little, &main_packet.get_payload()?);
-            Ok(Packet::new(&mut new_packet_buffer)?)
+        if !is_split_packet(buffer)? {
+            return Ok(Next::Done);
         }
-        else {
-            Packet::new(&mut buffer)
+
+        match self.split_responses.add(SplitPacket::new(self.app, self.protocol, std::mem::take(buffer))?)? {
+            None => Ok(Next::Receive),
+            Some(payload) => {
+                *buffer = payload;
+                Ok(Next::Done)
+            }
         }
     }
+}
+
+/// The request to answer the challenge with, if the response (in the buffer) is one.
+fn challenge_request(kind: Request, buffer: &[u8]) -> GDResult<Option<Vec<u8>>> {
+    let packet = Packet::new(buffer)?;
+
+    Ok(match packet.kind {
+        0x41 => Some(request_packet(kind, Some(packet.payload))), //'A'
+        _ => None
+    })
+}
 
-    /// Ask for a specific request only.
-    fn get_request_data(&mut self, app: &App, protocol: u8, kind: Request) -> GDResult<Bufferer> {
-        let request_initial_packet = Packet::initial(kind).to_bytes();
+/// Parses the (payload of the) response that is in the buffer, errors are tagged with the request's stage.
+fn parse_response<T>(kind: Request, buffer: &[u8], parse: impl FnOnce(&mut Bufferer) -> GDResult<T>) -> GDResult<T> {
+    Packet::new(buffer)
+        .and_then(|packet| parse(&mut Bufferer::new_with_data(Endianess::Little, packet.payload)))
+        .map_err(|e| e.at(request_stage(kind)))
+}
 
-        self.socket.send(&request_initial_packet)?;
-        let packet = self.receive(app, protocol, PACKET_SIZE)?;
+/// The stage that a request's failure happens at.
+fn request_stage(kind: Request) -> Stage {
+    match kind {
+        Request::Info => Stage::Info,
+        Request::Players => Stage::Players,
+        Request::Rules => Stage::Rules
+    }
+}
 
-        if packet.kind != 0x41 { //'A'
-            let data = packet.payload.clone();
-            return Ok(Bufferer::new_with_data(Endianess::Little, &data));
+/// The stage of a request's first exchange, which gets the challenge (the info request only gets one if the
+/// server asks for it, so its first exchange is the info one).
+fn initial_stage(kind: Request) -> Stage {
+    match kind {
+        Request::Info => Stage::Info,
+        _ => Stage::Challenge
+    }
+}
+
+struct ValveProtocol<S: Socket> {
+    socket: S,
+    split_retransmissions: u8,
+    /// The buffer that every packet is received into.
+    buffer: Vec<u8>
+}
+
+impl<S: Socket> ValveProtocol<S> {
+    fn new(socket: S, timeout_settings: Option<TimeoutSettings>, split_retransmissions: u8) -> GDResult<Self> {
+        socket.apply_timeout(timeout_settings)?;
+
+        Ok(Self {
+            socket,
+            split_retransmissions,
+            buffer: Vec::with_capacity(PACKET_SIZE)
+        })
+    }
+
+    /// Receives a (possibly split) response into the buffer, see [Reception::handle].
+    fn receive(&mut self, app: &App, protocol: u8, request: &[u8]) -> GDResult<()> {
+        let mut reception = Reception::new(app, protocol, self.split_retransmissions);
+
+        loop {
+            let received = self.socket.receive_into(&mut self.buffer, Some(PACKET_SIZE));
+            match reception.handle(received, &mut self.buffer)? {
+                Next::Done => return Ok(()),
+                Next::Receive => (),
+                Next::Retransmit => self.socket.send(request)?
+            }
         }
+    }
 
-        let challenge = packet.payload;
-        let challenge_packet = Packet::challenge(kind, challenge).to_bytes();
-
-        self.socket.send(&challenge_packet)?;
-
-        let data = self.receive(app, protocol, PACKET_SIZE)?.payload;
-        Ok(Bufferer::new_with_data(Endianess::Little, &data))
-    }
-
-    fn get_goldsrc_server_info(buffer: &mut Bufferer) -> GDResult<ServerInfo> {
-        buffer.get_u8()?; //get the header (useless info)
-        buffer.get_string_utf8()?; //get the server address (useless info)
-        let name = buffer.get_string_utf8()?;
-        let map = buffer.get_string_utf8()?;
-        let folder = buffer.get_string_utf8()?;
-        let game = buffer.get_string_utf8()?;
-        let players = buffer.get_u8()?;
-        let max_players = buffer.get_u8()?;
-        let protocol = buffer.get_u8()?;
-        let server_type = match buffer.get_u8()? {
-            68 => Server::Dedicated, //'D'
-            76 => Server::NonDedicated, //'L'
-            80 => Server::TV, //'P'
-            _ => Err(GDError::UnknownEnumCast)?
-        };
-        let environment_type = match buffer.get_u8()? {
-            76 => Environment::Linux, //'L'
-            87 => Environment::Windows, //'W'
-            _ => Err(GDError::UnknownEnumCast)?
-        };
-        let has_password = buffer.get_u8()? == 1;
-        let is_mod = buffer.get_u8()? == 1;
-        let mod_data = match is_mod {
-            false => None,
-            true => Some(ModData {
-                link: buffer.get_string_utf8()?,
-                download_link: buffer.get_string_utf8()?,
-                version: buffer.get_u32()?,
-                size: buffer.get_u32()?,
-                multiplayer_only: buffer.get_u8()? == 1,
-                has_own_dll: buffer.get_u8()? == 1
-            })
+    /// Ask for a specific request only, the response is left in the buffer, also returns the round-trip time
+    /// of the request that got answered.
+    fn get_request_data(&mut self, app: &App, protocol: u8, kind: Request) -> GDResult<Duration> {
+        let request = request_packet(kind, None);
+
+        let start = Instant::now();
+        self.socket.send(&request).map_err(|e| e.at(initial_stage(kind)))?;
+        self.receive(app, protocol, &request).map_err(|e| e.at(initial_stage(kind)))?;
+
+        let request = match challenge_request(kind, &self.buffer).map_err(|e| e.at(initial_stage(kind)))? {
+            None => return Ok(start.elapsed()),
+            Some(request) => request
         };
-        let vac_secured = buffer.get_u8()? == 1;
-        let bots = buffer.get_u8()?;
 
-        Ok(ServerInfo {
-            protocol,
-            name,
-            map,
-            folder,
-            game,
-            appid: 0, //not present in the obsolete response
-            players,
-            max_players,
-            bots,
-            server_type,
-            environment_type,
-            has_password,
-            vac_secured,
-            the_ship: None,
-            version: "".to_string(), //a version field only for the mod
-            extra_data: None,
-            is_mod,
-            mod_data
+        let start = Instant::now();
+        self.socket.send(&request)?;
+        self.receive(app, protocol, &request)?;
+        Ok(start.elapsed())
+    }
+
+    /// Gets and parses a request's response, also returns its round-trip time.
+    fn get<T>(&mut self, app: &App, protocol: u8, kind: Request, parse: impl FnOnce(&mut Bufferer) -> GDResult<T>) -> GDResult<(T, Duration)> {
+        let ping = self.get_request_data(app, protocol, kind).map_err(|e| e.at(request_stage(kind)))?;
+        Ok((parse_response(kind, &self.buffer, parse)?, ping))
+    }
+}
+
+#[cfg(feature = "async")]
+struct AsyncValveProtocol<S: AsyncSocket + Send> {
+    socket: S,
+    split_retransmissions: u8,
+    /// The buffer that every packet is received into.
+    buffer: Vec<u8>
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncSocket + Send> AsyncValveProtocol<S> {
+    fn new(mut socket: S, timeout_settings: Option<TimeoutSettings>, split_retransmissions: u8) -> GDResult<Self> {
+        socket.apply_timeout(timeout_settings)?;
+
+        Ok(Self {
+            socket,
+            split_retransmissions,
+            buffer: Vec::with_capacity(PACKET_SIZE)
         })
     }
 
-    /// Get the server information's.
-    fn get_server_info(&mut self, app: &App) -> GDResult<ServerInfo> {
-        let mut buffer = self.get_request_data(&app, 0, Request::INFO)?;
-        
-        if let App::GoldSrc(force) = app {
-            if *force {
-                return ValveProtocol::get_goldsrc_server_info(&mut buffer);
+    /// Receives a (possibly split) response into the buffer, see [Reception::handle].
+    async fn receive(&mut self, app: &App, protocol: u8, request: &[u8]) -> GDResult<()> {
+        let mut reception = Reception::new(app, protocol, self.split_retransmissions);
+
+        loop {
+            let received = self.socket.receive_into(&mut self.buffer, Some(PACKET_SIZE)).await;
+            match reception.handle(received, &mut self.buffer)? {
+                Next::Done => return Ok(()),
+                Next::Receive => (),
+                Next::Retransmit => self.socket.send(request).await?
             }
         }
+    }
 
-        let protocol = buffer.get_u8()?;
-        let name = buffer.get_string_utf8()?;
-        let map = buffer.get_string_utf8()?;
-        let folder = buffer.get_string_utf8()?;
-        let game = buffer.get_string_utf8()?;
-        let mut appid = buffer.get_u16()? as u32;
-        let players = buffer.get_u8()?;
-        let max_players = buffer.get_u8()?;
-        let bots = buffer.get_u8()?;
-        let server_type = match buffer.get_u8()? {
-            100 => Server::Dedicated, //'d'
-            108 => Server::NonDedicated, //'l'
-            112 => Server::TV, //'p'
-            _ => Err(GDError::UnknownEnumCast)?
-        };
-        let environment_type = match buffer.get_u8()? {
-            108 => Environment::Linux, //'l'
-            119 => Environment::Windows, //'w'
-            109 | 111 => Environment::Mac, //'m' or 'o'
-            _ => Err(GDError::UnknownEnumCast)?
-        };
-        let has_password = buffer.get_u8()? == 1;
-        let vac_secured = buffer.get_u8()? == 1;
-        let the_ship = match *app == SteamID::TS.as_app() {
-            false => None,
-            true => Some(TheShip {
-                mode: buffer.get_u8()?,
-                witnesses: buffer.get_u8()?,
-                duration: buffer.get_u8()?
-            })
-        };
-        let version = buffer.get_string_utf8()?;
-        let extra_data = match buffer.get_u8() {
-            Err(_) => None,
-            Ok(value) => Some(ExtraData {
-                port: match (value & 0x80) > 0 {
-                    false => None,
-                    true => Some(buffer.get_u16()?)
-                },
-                steam_id: match (value & 0x10) > 0 {
-                    false => None,
-                    true => Some(buffer.get_u64()?)
-                },
-                tv_port: match (value & 0x40) > 0 {
-                    false => None,
-                    true => Some(buffer.get_u16()?)
-                },
-                tv_name: match (value & 0x40) > 0 {
-                    false => None,
-                    true => Some(buffer.get_string_utf8()?)
-                },
-                keywords: match (value & 0x20) > 0 {
-                    false => None,
-                    true => Some(buffer.get_string_utf8()?)
-                },
-                game_id: match (value & 0x01) > 0 {
-                    false => None,
-                    true => {
-                        let gid = buffer.get_u64()?;
-                        appid = (gid & ((1 << 24) - 1)) as u32;
-
-                        Some(gid)
-                    }
-                }
-            })
+    /// Ask for a specific request only, the response is left in the buffer, also returns the round-trip time
+    /// of the request that got answered.
+    async fn get_request_data(&mut self, app: &App, protocol: u8, kind: Request) -> GDResult<Duration> {
+        let request = request_packet(kind, None);
+
+        let start = Instant::now();
+        self.socket.send(&request).await.map_err(|e| e.at(initial_stage(kind)))?;
+        self.receive(app, protocol, &request).await.map_err(|e| e.at(initial_stage(kind)))?;
+
+        let request = match challenge_request(kind, &self.buffer).map_err(|e| e.at(initial_stage(kind)))? {
+            None => return Ok(start.elapsed()),
+            Some(request) => request
         };
 
-        Ok(ServerInfo {
-            protocol,
-            name,
-            map,
-            folder,
-            game,
-            appid,
-            players,
-            max_players,
-            bots,
-            server_type,
-            environment_type,
-            has_password,
-            vac_secured,
-            the_ship,
-            version,
-            extra_data,
-            is_mod: false,
-            mod_data: None
+        let start = Instant::now();
+        self.socket.send(&request).await?;
+        self.receive(app, protocol, &request).await?;
+        Ok(start.elapsed())
+    }
+
+    /// Gets and parses a request's response, also returns its round-trip time.
+    async fn get<T>(&mut self, app: &App, protocol: u8, kind: Request, parse: impl FnOnce(&mut Bufferer) -> GDResult<T>) -> GDResult<(T, Duration)> {
+        let ping = self.get_request_data(app, protocol, kind).await.map_err(|e| e.at(request_stage(kind)))?;
+        Ok((parse_response(kind, &self.buffer, parse)?, ping))
+    }
+}
+
+fn parse_goldsrc_server_info(buffer: &mut Bufferer) -> GDResult<ServerInfo> {
+    buffer.get_u8()?; //get the header (useless info)
+    buffer.get_str_utf8()?; //get the server address (useless info)
+    let name = buffer.get_string_utf8()?;
+    let map = buffer.get_string_utf8()?;
+    let folder = buffer.get_string_utf8()?;
+    let game = buffer.get_string_utf8()?;
+    let players = buffer.get_u8()?;
+    let max_players = buffer.get_u8()?;
+    let protocol = buffer.get_u8()?;
+    let server_type = match buffer.get_u8()? {
+        68 => Server::Dedicated, //'D'
+        76 => Server::NonDedicated, //'L'
+        80 => Server::TV, //'P'
+        value => Err(GDErrorKind::UnknownEnumCast(format!("Unknown server type: {}.", value as char)))?
+    };
+    let environment_type = match buffer.get_u8()? {
+        76 => Environment::Linux, //'L'
+        87 => Environment::Windows, //'W'
+        value => Err(GDErrorKind::UnknownEnumCast(format!("Unknown environment type: {}.", value as char)))?
+    };
+    let has_password = buffer.get_u8()? == 1;
+    let is_mod = buffer.get_u8()? == 1;
+    let mod_data = match is_mod {
+        false => None,
+        true => Some(ModData {
+            link: buffer.get_string_utf8()?,
+            download_link: buffer.get_string_utf8()?,
+            version: buffer.get_i32()?,
+            size: buffer.get_i32()?,
+            multiplayer_only: buffer.get_u8()? == 1,
+            has_own_dll: buffer.get_u8()? == 1
         })
+    };
+    let vac_secured = buffer.get_u8()? == 1;
+    let bots = buffer.get_u8()?;
+
+    Ok(ServerInfo {
+        protocol,
+        name,
+        map,
+        folder,
+        game,
+        appid: 0, //not present in the obsolete response
+        players,
+        max_players,
+        bots,
+        server_type,
+        environment_type,
+        has_password,
+        vac_secured,
+        the_ship: None,
+        version: "".to_string(), //a version field only for the mod
+        extra_data: None,
+        is_mod,
+        mod_data
+    })
+}
+
+/// Parse the server information's.
+fn parse_server_info(app: &App, buffer: &mut Bufferer) -> GDResult<ServerInfo> {
+    if let App::GoldSrc(force) = app {
+        if *force {
+            return parse_goldsrc_server_info(buffer);
+        }
     }
 
-    /// Get the server player's.
-    fn get_server_players(&mut self, app: &App, protocol: u8) -> GDResult<Vec<ServerPlayer>> {
-        let mut buffer = self.get_request_data(&app, protocol, Request::PLAYERS)?;
-
-        let count = buffer.get_u8()? as usize;
-        let mut players: Vec<ServerPlayer> = Vec::with_capacity(count);
-
-        for _ in 0..count {
-            buffer.move_position_ahead(1); //skip the index byte
-            players.push(ServerPlayer {
-                name: buffer.get_string_utf8()?,
-                score: buffer.get_u32()?,
-                duration: buffer.get_f32()?,
-                deaths: match *app == SteamID::TS.as_app() {
-                    false => None,
-                    true => Some(buffer.get_u32()?)
-                },
-                money: match *app == SteamID::TS.as_app() {
-                    false => None,
-                    true => Some(buffer.get_u32()?)
+    let protocol = buffer.get_u8()?;
+    let name = buffer.get_string_utf8()?;
+    let map = buffer.get_string_utf8()?;
+    let folder = buffer.get_string_utf8()?;
+    let game = buffer.get_string_utf8()?;
+    let mut appid = buffer.get_u16()? as u32;
+    let players = buffer.get_u8()?;
+    let max_players = buffer.get_u8()?;
+    let bots = buffer.get_u8()?;
+    let server_type = match buffer.get_u8()? {
+        100 => Server::Dedicated, //'d'
+        108 => Server::NonDedicated, //'l'
+        112 => Server::TV, //'p'
+        value => Err(GDErrorKind::UnknownEnumCast(format!("Unknown server type: {}.", value as char)))?
+    };
+    let environment_type = match buffer.get_u8()? {
+        108 => Environment::Linux, //'l'
+        119 => Environment::Windows, //'w'
+        109 | 111 => Environment::Mac, //'m' or 'o'
+        value => Err(GDErrorKind::UnknownEnumCast(format!("Unknown environment type: {}.", value as char)))?
+    };
+    let has_password = buffer.get_u8()? == 1;
+    let vac_secured = buffer.get_u8()? == 1;
+    let the_ship = match *app == SteamID::TS.as_app() {
+        false => None,
+        true => Some(TheShip {
+            mode: buffer.get_u8()?,
+            witnesses: buffer.get_u8()?,
+            duration: buffer.get_u8()?
+        })
+    };
+    let version = buffer.get_string_utf8()?;
+    let extra_data = match buffer.get_u8() {
+        Err(_) => None,
+        Ok(value) => Some(ExtraData {
+            port: match (value & 0x80) > 0 {
+                false => None,
+                true => Some(buffer.get_u16()?)
+            },
+            steam_id: match (value & 0x10) > 0 {
+                false => None,
+                true => Some(buffer.get_u64()?)
+            },
+            tv_port: match (value & 0x40) > 0 {
+                false => None,
+                true => Some(buffer.get_u16()?)
+            },
+            tv_name: match (value & 0x40) > 0 {
+                false => None,
+                true => Some(buffer.get_string_utf8()?)
+            },
+            keywords: match (value & 0x20) > 0 {
+                false => None,
+                true => Some(buffer.get_string_utf8()?)
+            },
+            game_id: match (value & 0x01) > 0 {
+                false => None,
+                true => {
+                    let gid = buffer.get_u64()?;
+                    appid = (gid & ((1 << 24) - 1)) as u32;
+
+                    Some(gid)
                 }
-            });
-        }
+            }
+        })
+    };
+
+    Ok(ServerInfo {
+        protocol,
+        name,
+        map,
+        folder,
+        game,
+        appid,
+        players,
+        max_players,
+        bots,
+        server_type,
+        environment_type,
+        has_password,
+        vac_secured,
+        the_ship,
+        version,
+        extra_data,
+        is_mod: false,
+        mod_data: None
+    })
+}
 
-        Ok(players)
+/// Parse the server player's.
+fn parse_server_players(app: &App, buffer: &mut Bufferer) -> GDResult<Vec<ServerPlayer>> {
+    let count = buffer.get_u8()? as usize;
+    let mut players: Vec<ServerPlayer> = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        buffer.move_position_ahead(1); //skip the index byte
+        players.push(ServerPlayer {
+            name: buffer.get_string_utf8()?,
+            score: buffer.get_i32()?,
+            duration: buffer.get_f32()?,
+            deaths: match *app == SteamID::TS.as_app() {
+                false => None,
+                true => Some(buffer.get_i32()?)
+            },
+            money: match *app == SteamID::TS.as_app() {
+                false => None,
+                true => Some(buffer.get_i32()?)
+            }
+        });
     }
 
-    /// Get the server's rules.
-    fn get_server_rules(&mut self, app: &App, protocol: u8) -> GDResult<Vec<ServerRule>> {
-        let mut buffer = self.get_request_data(&app, protocol, Request::RULES)?;
+    Ok(players)
+}
 
-        let count = buffer.get_u16()? as usize;
-        let mut rules: Vec<ServerRule> = Vec::with_capacity(count);
+/// Parse the server's rules.
+fn parse_server_rules(buffer: &mut Bufferer) -> GDResult<Vec<ServerRule>> {
+    let count = buffer.get_u16()? as usize;
+    let mut rules: Vec<ServerRule> = Vec::with_capacity(count);
 
-        for _ in 0..count {
-            rules.push(ServerRule {
-                name: buffer.get_string_utf8()?,
-                value: buffer.get_string_utf8()?
-            })
-        }
+    for _ in 0..count {
+        rules.push(ServerRule {
+            name: buffer.get_string_utf8()?,
+            value: buffer.get_string_utf8()?
+        })
+    }
 
-        Ok(rules)
+    Ok(rules)
+}
+
+/// Fails if the server's app id is not the expected one.
+fn check_app(app: &App, info: &ServerInfo) -> GDResult<()> {
+    if let App::Source(Some(appid)) = app {
+        if *appid != info.appid {
+            return Err(GDErrorKind::BadGame(format!("Expected {}, found {} instead!", *appid, info.appid)).into());
+        }
     }
+
+    Ok(())
 }
 
 /// Query a server by providing the address, the port, the app, gather and timeout settings.
 /// Providing None to the settings results in using the default values for them (GatherSettings::[default](GatheringSettings::default), TimeoutSettings::[default](TimeoutSettings::default)).
 pub fn query(address: &str, port: u16, app: App, gather_settings: Option<GatheringSettings>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
-    let response_gather_settings = gather_settings.unwrap_or(GatheringSettings::default());
-    get_response(address, port, app, response_gather_settings, timeout_settings)
+    retry(timeout_settings, |timeout_settings| query_with_socket(UdpSocket::new(address, port)?, app.clone(), gather_settings.clone(), timeout_settings))
 }
 
-fn get_response(address: &str, port: u16, app: App, gather_settings: GatheringSettings, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
-    let mut client = ValveProtocol::new(address, port, timeout_settings)?;
+/// Query a server by its socket address (IPv4 or IPv6).
+pub fn query_addr(address: &SocketAddr, app: App, gather_settings: Option<GatheringSettings>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    retry(timeout_settings, |timeout_settings| query_with_socket(UdpSocket::new_with_address(*address)?, app.clone(), gather_settings.clone(), timeout_settings))
+}
 
-    let info = client.get_server_info(&app)?;
-    let protocol = info.protocol;
+/// Query a server through an already created socket.
+pub fn query_with_socket<S: Socket>(socket: S, app: App, gather_settings: Option<GatheringSettings>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let response_gather_settings = gather_settings.unwrap_or_default();
+    get_response(DeadlineSocket::new(socket, &timeout_settings), app, response_gather_settings, timeout_settings)
+}
 
-    if let App::Source(x) = &app {
-        if let Some(appid) = x {
-            if *appid != info.appid {
-                return Err(GDError::BadGame(format!("Expected {}, found {} instead!", *appid, info.appid)));
-            }
-        }
+/// Fails with a secondary request's error, unless gathering leniently, then it's kept as the result.
+fn gather<T>(result: GDResult<T>, lenient: bool) -> GDResult<GDResult<T>> {
+    match result {
+        Err(error) if !lenient => Err(error),
+        result => Ok(result)
     }
+}
+
+fn get_response<S: Socket>(socket: S, app: App, gather_settings: GatheringSettings, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let mut client = ValveProtocol::new(socket, timeout_settings, gather_settings.split_retransmissions)?;
+
+    let (info, ping) = client.get(&app, 0, Request::Info, |buffer| parse_server_info(&app, buffer))?;
+    let protocol = info.protocol;
+    check_app(&app, &info)?;
 
     Ok(Response {
         info,
         players: match gather_settings.players {
             false => None,
-            true => Some(client.get_server_players(&app, protocol)?)
+            true => Some(gather(client.get(&app, protocol, Request::Players, |buffer| parse_server_players(&app, buffer)).map(|(players, _)| players), gather_settings.lenient)?)
         },
         rules: match gather_settings.rules {
             false => None,
-            true => Some(client.get_server_rules(&app, protocol)?)
+            true => Some(gather(client.get(&app, protocol, Request::Rules, parse_server_rules).map(|(rules, _)| rules), gather_settings.lenient)?)
+        },
+        ping
+    })
+}
+
+/// Asynchronously query a server, same as [query].
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, app: App, gather_settings: Option<GatheringSettings>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let response_gather_settings = gather_settings.unwrap_or_default();
+    retry_async(timeout_settings, |timeout_settings| {
+        let (app, gather_settings) = (app.clone(), response_gather_settings.clone());
+        async move {
+            get_response_async(AsyncUdpSocket::new(address, port).await?, app, gather_settings, timeout_settings).await
         }
+    }).await
+}
+
+/// Asynchronously query a server by its socket address (IPv4 or IPv6).
+#[cfg(feature = "async")]
+pub async fn query_addr_async(address: &SocketAddr, app: App, gather_settings: Option<GatheringSettings>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    query_async(&address.ip().to_string(), address.port(), app, gather_settings, timeout_settings).await
+}
+
+/// Asynchronously query a server through an already created socket.
+#[cfg(feature = "async")]
+pub async fn query_with_socket_async<S: AsyncSocket + Send>(socket: S, app: App, gather_settings: Option<GatheringSettings>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    with_deadline(timeout_settings, |timeout_settings| get_response_async(socket, app, gather_settings.unwrap_or_default(), timeout_settings)).await
+}
+
+#[cfg(feature = "async")]
+async fn get_response_async<S: AsyncSocket + Send>(socket: S, app: App, gather_settings: GatheringSettings, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let mut client = AsyncValveProtocol::new(socket, timeout_settings, gather_settings.split_retransmissions)?;
+
+    let (info, ping) = client.get(&app, 0, Request::Info, |buffer| parse_server_info(&app, buffer)).await?;
+    let protocol = info.protocol;
+    check_app(&app, &info)?;
+
+    Ok(Response {
+        info,
+        players: match gather_settings.players {
+            false => None,
+            true => Some(gather(client.get(&app, protocol, Request::Players, |buffer| parse_server_players(&app, buffer)).await.map(|(players, _)| players), gather_settings.lenient)?)
+        },
+        rules: match gather_settings.rules {
+            false => None,
+            true => Some(gather(client.get(&app, protocol, Request::Rules, parse_server_rules).await.map(|(rules, _)| rules), gather_settings.lenient)?)
+        },
+        ping
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::socket::MockSocket;
+    use super::*;
+
+    fn info_packet() -> Vec<u8> {
+        let mut packet = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x49, 17];
+        packet.extend(b"A server\0de_dust2\0cstrike\0Counter-Strike: Source\0");
+        packet.extend([240, 0, 5, 32, 1, b'd', b'l', 0, 1]);
+        packet.extend(b"1.0.0.70\0");
+        packet
+    }
+
+    #[test]
+    fn info_through_a_mock_socket() {
+        let challenge = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x0B, 0x0C, 0x0D];
+        let mut socket = MockSocket::new(vec![challenge, info_packet()]);
+
+        let gather_settings = GatheringSettings {
+            players: false,
+            rules: false,
+            split_retransmissions: 0,
+            lenient: false
+        };
+
+        let response = query_with_socket(&mut socket, App::Source(Some(240)), Some(gather_settings), None).unwrap();
+        assert_eq!(response.info.name, "A server");
+        assert_eq!(response.info.map, "de_dust2");
+        assert_eq!(response.info.players, 5);
+        assert!(response.info.vac_secured);
+
+        assert_eq!(socket.sent().len(), 2);
+        assert!(socket.sent()[1].ends_with(b"Source Engine Query\0\x0A\x0B\x0C\x0D"));
+        assert_eq!(socket.remaining(), 0);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_info_through_a_mock_socket() {
+        let challenge = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x0B, 0x0C, 0x0D];
+        let mut socket = MockSocket::new(vec![challenge, info_packet()]);
+
+        let gather_settings = GatheringSettings {
+            players: false,
+            rules: false,
+            split_retransmissions: 0,
+            lenient: false
+        };
+
+        let response = query_with_socket_async(&mut socket, App::Source(Some(240)), Some(gather_settings), None).await.unwrap();
+        assert_eq!(response.info.name, "A server");
+        assert_eq!(socket.sent().len(), 2);
+        assert_eq!(socket.remaining(), 0);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_socket_deadline() {
+        let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = AsyncUdpSocket::new("127.0.0.1", server.local_addr().unwrap().port()).await.unwrap();
+
+        //no read timeout, only the deadline stops waiting for the server that never answers
+        let retry = crate::protocols::types::RetrySettings::new(0, Duration::from_millis(1), Some(Duration::from_millis(50))).unwrap();
+        let timeout_settings = TimeoutSettings::new_with_retry(None, None, retry).unwrap();
+
+        let error = query_with_socket_async(socket, App::Source(None), None, Some(timeout_settings)).await.unwrap_err();
+        assert!(error.is_timeout());
+    }
+
+    /// A bzip2 compressed A2S_RULES response (with 2 rules), 38 bytes when decompressed.
+    static COMPRESSED_RULES: [u8; 78] = [
+        0x42, 0x5A, 0x68, 0x39, 0x31, 0x41, 0x59, 0x26, 0x53, 0x59, 0xE4, 0x1E, 0x1E, 0x01, 0x00, 0x00, 0x13, 0x4F, 0x80, 0xD0,
+        0x00, 0x48, 0x40, 0x02, 0x00, 0x00, 0x00, 0xA2, 0xA6, 0x5D, 0x20, 0x00, 0x00, 0xA0, 0x00, 0x22, 0x9A, 0x68, 0xD3, 0xD4,
+        0x00, 0xF4, 0x85, 0x30, 0x9A, 0x68, 0x0D, 0x31, 0x3A, 0x5E, 0x94, 0x40, 0x95, 0x02, 0x2F, 0xE2, 0x75, 0xA2, 0xAD, 0xD3,
+        0x0B, 0x4C, 0xC9, 0x42, 0x3B, 0xDC, 0xC5, 0xBE, 0x2E, 0xE4, 0x8A, 0x70, 0xA1, 0x21, 0xC8, 0x3C, 0x3C, 0x02];
+    static COMPRESSED_RULES_CRC32: u32 = 0x35C1DF0F;
+
+    /// Splits the compressed rules in 3 packets.
+    fn compressed_split_packets(crc32: u32) -> Vec<Vec<u8>> {
+        COMPRESSED_RULES.chunks(30).enumerate().map(|(number, chunk)| {
+            let mut packet = vec![0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x80, 3, number as u8, 0xE0, 0x04];
+            if number == 0 {
+                packet.extend(38u32.to_le_bytes());
+                packet.extend(crc32.to_le_bytes());
+            }
+
+            packet.extend(chunk);
+            packet
+        }).collect()
+    }
+
+    /// Receives the packets, returning the (reassembled) response.
+    fn receive_rules(packets: Vec<Vec<u8>>) -> GDResult<Vec<u8>> {
+        let mut socket = MockSocket::new(packets);
+        let mut client = ValveProtocol::new(&mut socket, None, 0)?;
+        client.receive(&App::Source(None), 17, &[])?;
+
+        Ok(client.buffer)
+    }
+
+    /// A socket that replays its packets, a `None` being a packet that didn't arrive (a timeout).
+    struct LossySocket {
+        packets: Vec<Option<Vec<u8>>>,
+        sent: usize
+    }
+
+    impl Socket for LossySocket {
+        fn apply_timeout(&self, _timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+            Ok(())
+        }
+
+        fn send(&mut self, _data: &[u8]) -> GDResult<()> {
+            self.sent += 1;
+            Ok(())
+        }
+
+        fn receive(&mut self, _size: Option<usize>) -> GDResult<Vec<u8>> {
+            match self.packets.is_empty() {
+                true => None,
+                false => self.packets.remove(0)
+            }.ok_or_else(|| GDErrorKind::PacketReceive("Timed out.".to_string()).into())
+        }
+    }
+
+    #[test]
+    fn compressed_split_out_of_order_and_duplicated() {
+        let mut packets = compressed_split_packets(COMPRESSED_RULES_CRC32);
+        packets.swap(0, 2);
+        packets.insert(1, packets[0].clone());
+
+        let data = receive_rules(packets).unwrap();
+        let packet = Packet::new(&data).unwrap();
+        assert_eq!(packet.kind, 0x45);
+
+        let rules = parse_server_rules(&mut Bufferer::new_with_data(Endianess::Little, packet.payload)).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].name, "mp_timelimit");
+        assert_eq!(rules[1].value, "30");
+    }
+
+    #[test]
+    fn compressed_split_bad_crc32() {
+        let packets = compressed_split_packets(COMPRESSED_RULES_CRC32 + 1);
+        assert!(matches!(receive_rules(packets).map_err(|e| e.kind().clone()), Err(GDErrorKind::Decompress(_))));
+    }
+
+    #[test]
+    fn split_bad_number_ignored() {
+        let mut packets = compressed_split_packets(COMPRESSED_RULES_CRC32);
+
+        //a packet numbered past the total is dropped, the response still gets complete
+        let mut stray = packets[1].clone();
+        stray[9] = 3;
+        packets.insert(0, stray);
+        assert_eq!(Packet::new(&receive_rules(packets.clone()).unwrap()).unwrap().kind, 0x45);
+
+        //but it doesn't stand in for a missing one
+        packets.remove(2);
+        assert!(matches!(receive_rules(packets).map_err(|e| e.kind().clone()), Err(GDErrorKind::PacketReceive(_))));
+    }
+
+    #[test]
+    fn split_stray_packets_ignored() {
+        let mut packets = compressed_split_packets(COMPRESSED_RULES_CRC32);
+
+        //the last packet of an earlier response (another id) and one of the same id, but of another total
+        let mut stray = packets[2].clone();
+        stray[4] = 0x06;
+        packets.insert(0, stray.clone());
+        stray[4] = 0x07;
+        stray[8] = 4;
+        packets.insert(2, stray);
+
+        assert_eq!(Packet::new(&receive_rules(packets).unwrap()).unwrap().kind, 0x45);
+    }
+
+    #[test]
+    fn split_missing_packet() {
+        let mut packets = compressed_split_packets(COMPRESSED_RULES_CRC32);
+        packets.remove(1);
+
+        match receive_rules(packets).map_err(|e| e.kind().clone()) {
+            Err(GDErrorKind::PacketReceive(details)) => assert!(details.contains("missing packets [1] of 3")),
+            other => panic!("Expected a missing packet error, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn split_retransmission() {
+        let mut packets: Vec<Option<Vec<u8>>> = compressed_split_packets(COMPRESSED_RULES_CRC32).into_iter().map(Some).collect();
+        packets.extend(packets.clone());
+        packets[1] = None;
+
+        let mut socket = LossySocket { packets: packets.clone(), sent: 0 };
+        assert!(ValveProtocol::new(&mut socket, None, 0).unwrap().receive(&App::Source(None), 17, &[]).is_err());
+
+        let mut socket = LossySocket { packets, sent: 0 };
+        let mut client = ValveProtocol::new(&mut socket, None, 1).unwrap();
+        client.receive(&App::Source(None), 17, &[]).unwrap();
+        assert_eq!(Packet::new(&client.buffer).unwrap().kind, 0x45);
+        assert_eq!(socket.sent, 1);
+    }
+
+    #[test]
+    fn wrong_app() {
+        let mut socket = MockSocket::new(vec![info_packet()]);
+        assert!(query_with_socket(&mut socket, App::Source(Some(10)), None, None).is_err());
+    }
+
+    #[test]
+    fn failed_stage() {
+        let mut socket = MockSocket::new(vec![info_packet()]);
+
+        let gather_settings = GatheringSettings {
+            players: true,
+            rules: false,
+            split_retransmissions: 0,
+            lenient: false
+        };
+
+        //the players' challenge doesn't come
+        let error = query_with_socket(&mut socket, App::Source(Some(240)), Some(gather_settings.clone()), None).unwrap_err();
+        assert_eq!(error.stage(), Some(Stage::Challenge));
+        assert!(matches!(error.kind(), GDErrorKind::PacketReceive(_)));
+        assert!(error.is_retryable());
+
+        //the players don't come
+        let challenge = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x0B, 0x0C, 0x0D];
+        let mut socket = MockSocket::new(vec![info_packet(), challenge]);
+
+        let error = query_with_socket(&mut socket, App::Source(Some(240)), Some(gather_settings), None).unwrap_err();
+        assert_eq!(error.stage(), Some(Stage::Players));
+    }
+
+    #[test]
+    fn lenient_rules_failure() {
+        let mut socket = MockSocket::new(vec![info_packet()]);
+
+        let gather_settings = GatheringSettings {
+            players: false,
+            rules: true,
+            split_retransmissions: 0,
+            lenient: true
+        };
+
+        let response = query_with_socket(&mut socket, App::Source(Some(240)), Some(gather_settings), None).unwrap();
+        assert_eq!(response.info.name, "A server");
+        assert!(response.players.is_none());
+        assert_eq!(response.rules.unwrap().unwrap_err().stage(), Some(Stage::Challenge));
+    }
+
+    #[test]
+    fn lenient_failure_kept_by_the_game_response() {
+        let mut socket = MockSocket::new(vec![info_packet()]);
+
+        let gather_settings = GatheringSettings {
+            players: false,
+            rules: true,
+            split_retransmissions: 0,
+            lenient: true
+        };
+
+        let response = query_with_socket(&mut socket, App::Source(Some(240)), Some(gather_settings), None).unwrap();
+        let response = crate::protocols::valve::game::Response::new_from_valve_response(response);
+        assert!(response.players_error.is_none());
+        assert!(response.rules.is_empty());
+        assert_eq!(response.rules_error.unwrap().stage(), Some(Stage::Challenge));
+    }
+}
@@ -1,6 +1,11 @@
 
+use std::time::Duration;
+use crate::{GDError, GDResult};
+use crate::protocols::types::GenericResponse;
+
 /// The type of the server.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Server {
     Dedicated,
     NonDedicated,
@@ -9,6 +14,7 @@ pub enum Server {
 
 /// The Operating System that the server is on.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Environment {
     Linux,
     Windows,
@@ -17,14 +23,20 @@ pub enum Environment {
 
 /// A query response.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
     pub info: ServerInfo,
-    pub players: Option<Vec<ServerPlayer>>,
-    pub rules: Option<Vec<ServerRule>>
+    /// The players, if gathered, an error only when gathering leniently.
+    pub players: Option<GDResult<Vec<ServerPlayer>>>,
+    /// The rules, if gathered, an error only when gathering leniently.
+    pub rules: Option<GDResult<Vec<ServerRule>>>,
+    /// The A2S_INFO round-trip time.
+    pub ping: Duration
 }
 
 /// General server information's.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ServerInfo {
     /// Protocol used by the server.
     pub protocol: u8,
@@ -66,21 +78,23 @@ pub struct ServerInfo {
 
 /// A server player.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ServerPlayer {
     /// Player's name.
     pub name: String,
     /// General score.
-    pub score: u32,
+    pub score: i32,
     /// How long they've been on the server for.
     pub duration: f32,
     /// Only for [the ship](https://developer.valvesoftware.com/wiki/The_Ship): deaths count
-    pub deaths: Option<u32>, //the_ship
+    pub deaths: Option<i32>, //the_ship
     /// Only for [the ship](https://developer.valvesoftware.com/wiki/The_Ship): money amount
-    pub money: Option<u32>, //the_ship
+    pub money: Option<i32>, //the_ship
 }
 
 /// A server rule.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ServerRule {
     pub name: String,
     pub value: String
@@ -88,6 +102,7 @@ pub struct ServerRule {
 
 /// Only present for [the ship](https://developer.valvesoftware.com/wiki/The_Ship).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TheShip {
     pub mode: u8,
     pub witnesses: u8,
@@ -96,6 +111,7 @@ pub struct TheShip {
 
 /// Some extra data that the server might provide or not.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExtraData {
     /// The server's game port number.
     pub port: Option<u16>,
@@ -113,37 +129,52 @@ pub struct ExtraData {
 
 /// Data related to GoldSrc Mod response.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModData {
     pub link: String,
     pub download_link: String,
-    pub version: u32,
-    pub size: u32,
+    pub version: i32,
+    pub size: i32,
     pub multiplayer_only: bool,
     pub has_own_dll: bool
 }
 
-pub(crate) fn get_optional_extracted_data(data: Option<ExtraData>) -> (Option<u16>, Option<u64>, Option<u16>, Option<String>, Option<String>) {
+/// The fields of [ExtraData] that the game responses are interested in (port, steam id, tv port, tv name and keywords).
+pub(crate) type ExtractedData = (Option<u16>, Option<u64>, Option<u16>, Option<String>, Option<String>);
+
+pub(crate) fn get_optional_extracted_data(data: Option<ExtraData>) -> ExtractedData {
     match data {
         None => (None, None, None, None, None),
         Some(ed) => (ed.port, ed.steam_id, ed.tv_port, ed.tv_name, ed.keywords)
     }
 }
 
+/// Splits gathered players or rules into the list (empty if not gathered or failed) and the error of
+/// a lenient gathering.
+pub(crate) fn split_gathered<T>(gathered: Option<GDResult<Vec<T>>>) -> (Vec<T>, Option<GDError>) {
+    match gathered {
+        None => (Vec::new(), None),
+        Some(Ok(items)) => (items, None),
+        Some(Err(error)) => (Vec::new(), Some(error))
+    }
+}
+
 /// The type of the request, see the [protocol](https://developer.valvesoftware.com/wiki/Server_queries).
 #[derive(PartialEq, Copy, Clone)]
 #[repr(u8)]
 pub(crate) enum Request {
     /// Known as `A2S_INFO`
-    INFO = 0x54,
+    Info = 0x54,
     /// Known as `A2S_PLAYERS`
-    PLAYERS = 0x55,
+    Players = 0x55,
     /// Known as `A2S_RULES`
-    RULES = 0x56
+    Rules = 0x56
 }
 
 /// Supported steam apps id's
 #[repr(u32)]
-#[derive(PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SteamID {
     /// Counter-Strike
     CS = 10,
@@ -213,8 +244,15 @@ impl SteamID {
     }
 }
 
+impl From<SteamID> for App {
+    fn from(id: SteamID) -> Self {
+        id.as_app()
+    }
+}
+
 /// App type.
-#[derive(PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum App {
     /// A Source game, the argument represents the wanted response steam app id, if its **None**,
     /// let the query find it, if its **Some**, the query fails if the response id is not the
@@ -226,17 +264,28 @@ pub enum App {
 }
 
 /// What data to gather, purely used only with the query function.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GatheringSettings {
     pub players: bool,
-    pub rules: bool
+    pub rules: bool,
+    /// How many times to send a request again when some packets of its split response are missing (the
+    /// socket's read timeout passed while waiting for them).
+    pub split_retransmissions: u8,
+    /// If the players or the rules request failing shouldn't fail the whole query, their errors being
+    /// put in the response instead (the server's info is still needed).
+    pub lenient: bool
 }
 
 impl Default for GatheringSettings {
-    /// Default values are true for both the players and the rules.
+    /// Default values are true for both the players and the rules, with no split retransmissions and not
+    /// lenient.
     fn default() -> Self {
         Self {
             players: true,
-            rules: true
+            rules: true,
+            split_retransmissions: 0,
+            lenient: false
         }
     }
 }
@@ -244,13 +293,17 @@ impl Default for GatheringSettings {
 /// Generic response types that are used by many games, they are the protocol ones, but without the
 /// unnecessary bits (example: the **The Ship**-only fields).
 pub mod game {
-    use crate::protocols::valve::types::get_optional_extracted_data;
+    use std::time::Duration;
+    use crate::protocols::types::{GenericPlayer, GenericResponse, SpecificResponse};
+    use crate::GDError;
+    use crate::protocols::valve::types::{get_optional_extracted_data, split_gathered};
     use super::{Server, ServerRule, ServerPlayer};
 
     #[derive(Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Player {
         pub name: String,
-        pub score: u32,
+        pub score: i32,
         pub duration: f32
     }
 
@@ -265,6 +318,7 @@ pub mod game {
     }
 
     #[derive(Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Response {
         pub protocol: u8,
         pub name: String,
@@ -272,6 +326,8 @@ pub mod game {
         pub game: String,
         pub players: u8,
         pub players_details: Vec<Player>,
+        /// Why the players couldn't be gathered, when gathering leniently.
+        pub players_error: Option<GDError>,
         pub max_players: u8,
         pub bots: u8,
         pub server_type: Server,
@@ -283,12 +339,17 @@ pub mod game {
         pub tv_port: Option<u16>,
         pub tv_name: Option<String>,
         pub keywords: Option<String>,
-        pub rules: Vec<ServerRule>
+        pub rules: Vec<ServerRule>,
+        /// Why the rules couldn't be gathered, when gathering leniently.
+        pub rules_error: Option<GDError>,
+        pub ping: Duration
     }
 
     impl Response {
         pub fn new_from_valve_response(response: super::Response) -> Self {
             let (port, steam_id, tv_port, tv_name, keywords) = get_optional_extracted_data(response.info.extra_data);
+            let (players, players_error) = split_gathered(response.players);
+            let (rules, rules_error) = split_gathered(response.rules);
 
             Self {
                 protocol: response.info.protocol,
@@ -296,7 +357,8 @@ pub mod game {
                 map: response.info.map,
                 game: response.info.game,
                 players: response.info.players,
-                players_details: response.players.unwrap_or(vec![]).iter().map(Player::from_valve_response).collect(),
+                players_details: players.iter().map(Player::from_valve_response).collect(),
+                players_error,
                 max_players: response.info.max_players,
                 bots: response.info.bots,
                 server_type: response.info.server_type,
@@ -308,9 +370,39 @@ pub mod game {
                 tv_port,
                 tv_name,
                 keywords,
-                rules: response.rules.unwrap_or(vec![])
+                rules,
+                rules_error,
+                ping: response.ping
             }
         }
     }
+
+    impl From<Response> for GenericResponse {
+        fn from(response: Response) -> Self {
+            Self {
+                name: Some(response.name.clone()),
+                description: None,
+                game: Some(response.game.clone()),
+                game_version: Some(response.version.clone()),
+                map: Some(response.map.clone()),
+                players_maximum: response.max_players as u32,
+                players_online: response.players as u32,
+                players_bots: Some(response.bots as u32),
+                has_password: Some(response.has_password),
+                players: response.players_details.iter().map(|player| GenericPlayer {
+                    name: player.name.clone(),
+                    score: Some(player.score)
+                }).collect(),
+                ping: Some(response.ping),
+                inner: SpecificResponse::Valve(response)
+            }
+        }
+    }
+}
+
+impl From<Response> for GenericResponse {
+    fn from(response: Response) -> Self {
+        game::Response::new_from_valve_response(response).into()
+    }
 }
 
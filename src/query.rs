@@ -0,0 +1,255 @@
+//! A builder for the protocols' queries, so that new options don't change any signature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use gamedig::Query;
+//! use gamedig::protocols::valve::SteamID;
+//!
+//! let response = Query::valve("91.216.250.10")
+//!     .port(27015)
+//!     .app(SteamID::TF2)
+//!     .players(false)
+//!     .read_timeout(Duration::from_secs(2))
+//!     .run();
+//! ```
+
+use std::time::Duration;
+use crate::GDResult;
+use crate::protocols::minecraft;
+use crate::protocols::minecraft::{BedrockResponse, LegacyGroup};
+use crate::protocols::types::{RetrySettings, TimeoutSettings};
+use crate::protocols::valve;
+use crate::protocols::valve::{App, GatheringSettings};
+
+/// A query's builder, the protocol (with its own options) is chosen by its constructor (example:
+/// [Query::valve]), the options that aren't set have their defaults.
+#[derive(Debug, Clone)]
+pub struct Query<P> {
+    address: String,
+    port: Option<u16>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    retry: Option<RetrySettings>,
+    protocol: P
+}
+
+/// The Valve protocol's options.
+#[derive(Debug, Clone)]
+pub struct Valve {
+    app: App,
+    gather_settings: GatheringSettings
+}
+
+/// The Minecraft Java protocol (there are no other options).
+#[derive(Debug, Clone)]
+pub struct MinecraftJava;
+
+/// The Minecraft Bedrock protocol (there are no other options).
+#[derive(Debug, Clone)]
+pub struct MinecraftBedrock;
+
+/// The Minecraft legacy protocols' options.
+#[derive(Debug, Clone)]
+pub struct MinecraftLegacy {
+    group: Option<LegacyGroup>
+}
+
+impl<P> Query<P> {
+    fn new(address: &str, protocol: P) -> Self {
+        Self {
+            address: address.to_string(),
+            port: None,
+            read_timeout: None,
+            write_timeout: None,
+            retry: None,
+            protocol
+        }
+    }
+
+    /// The port to query (default: the protocol's one).
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// The read timeout (default: 4 seconds).
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// The write timeout (default: 4 seconds).
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// How to retry the query (default: no retries and no deadline).
+    pub fn retry(mut self, retry: RetrySettings) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// The timeout settings, None if nothing was set (so that the protocol uses its defaults).
+    fn timeout_settings(&self) -> GDResult<Option<TimeoutSettings>> {
+        if self.read_timeout.is_none() && self.write_timeout.is_none() && self.retry.is_none() {
+            return Ok(None);
+        }
+
+        let default = TimeoutSettings::default();
+        TimeoutSettings::new_with_retry(self.read_timeout.or_else(|| default.get_read()),
+                                        self.write_timeout.or_else(|| default.get_write()),
+                                        self.retry.clone().unwrap_or_default()).map(Some)
+    }
+}
+
+impl Query<Valve> {
+    /// Query a server that uses the Valve protocol (default port: 27015, default app: any Source one).
+    pub fn valve(address: &str) -> Self {
+        Query::new(address, Valve {
+            app: App::Source(None),
+            gather_settings: GatheringSettings::default()
+        })
+    }
+
+    /// The app (a [SteamID](crate::protocols::valve::SteamID) or an [App]) the server has to be running.
+    pub fn app<A: Into<App>>(mut self, app: A) -> Self {
+        self.protocol.app = app.into();
+        self
+    }
+
+    /// If the players should be gathered (default: true).
+    pub fn players(mut self, players: bool) -> Self {
+        self.protocol.gather_settings.players = players;
+        self
+    }
+
+    /// If the rules should be gathered (default: true).
+    pub fn rules(mut self, rules: bool) -> Self {
+        self.protocol.gather_settings.rules = rules;
+        self
+    }
+
+    /// How many times to send a request again when some packets of its split response are missing (default: 0).
+    pub fn split_retransmissions(mut self, split_retransmissions: u8) -> Self {
+        self.protocol.gather_settings.split_retransmissions = split_retransmissions;
+        self
+    }
+
+    /// If failing to gather the players or the rules shouldn't fail the query (default: false).
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.protocol.gather_settings.lenient = lenient;
+        self
+    }
+
+    /// Query the server.
+    pub fn run(&self) -> GDResult<valve::Response> {
+        valve::query(&self.address, self.port.unwrap_or(27015), self.protocol.app.clone(), Some(self.protocol.gather_settings.clone()), self.timeout_settings()?)
+    }
+
+    /// Asynchronously query the server.
+    #[cfg(feature = "async")]
+    pub async fn run_async(&self) -> GDResult<valve::Response> {
+        valve::query_async(&self.address, self.port.unwrap_or(27015), self.protocol.app.clone(), Some(self.protocol.gather_settings.clone()), self.timeout_settings()?).await
+    }
+}
+
+impl Query<MinecraftJava> {
+    /// Query a Minecraft Java server (default port: 25565).
+    pub fn minecraft_java(address: &str) -> Self {
+        Query::new(address, MinecraftJava)
+    }
+
+    /// Query the server.
+    pub fn run(&self) -> GDResult<minecraft::Response> {
+        minecraft::query_java(&self.address, self.port.unwrap_or(25565), self.timeout_settings()?)
+    }
+
+    /// Asynchronously query the server.
+    #[cfg(feature = "async")]
+    pub async fn run_async(&self) -> GDResult<minecraft::Response> {
+        minecraft::query_java_async(&self.address, self.port.unwrap_or(25565), self.timeout_settings()?).await
+    }
+}
+
+impl Query<MinecraftBedrock> {
+    /// Query a Minecraft Bedrock server (default port: 19132).
+    pub fn minecraft_bedrock(address: &str) -> Self {
+        Query::new(address, MinecraftBedrock)
+    }
+
+    /// Query the server.
+    pub fn run(&self) -> GDResult<BedrockResponse> {
+        minecraft::query_bedrock(&self.address, self.port.unwrap_or(19132), self.timeout_settings()?)
+    }
+
+    /// Asynchronously query the server.
+    #[cfg(feature = "async")]
+    pub async fn run_async(&self) -> GDResult<BedrockResponse> {
+        minecraft::query_bedrock_async(&self.address, self.port.unwrap_or(19132), self.timeout_settings()?).await
+    }
+}
+
+impl Query<MinecraftLegacy> {
+    /// Query a Minecraft legacy server (default port: 25565), trying every group (1.6 -> 1.4 -> Beta 1.8)
+    /// unless one is given.
+    pub fn minecraft_legacy(address: &str) -> Self {
+        Query::new(address, MinecraftLegacy {
+            group: None
+        })
+    }
+
+    /// The legacy group of the server.
+    pub fn group(mut self, group: LegacyGroup) -> Self {
+        self.protocol.group = Some(group);
+        self
+    }
+
+    /// Query the server.
+    pub fn run(&self) -> GDResult<minecraft::Response> {
+        let port = self.port.unwrap_or(25565);
+        match &self.protocol.group {
+            None => minecraft::query_legacy(&self.address, port, self.timeout_settings()?),
+            Some(group) => minecraft::query_legacy_specific(group.clone(), &self.address, port, self.timeout_settings()?)
+        }
+    }
+
+    /// Asynchronously query the server.
+    #[cfg(feature = "async")]
+    pub async fn run_async(&self) -> GDResult<minecraft::Response> {
+        let port = self.port.unwrap_or(25565);
+        match &self.protocol.group {
+            None => minecraft::query_legacy_async(&self.address, port, self.timeout_settings()?).await,
+            Some(group) => minecraft::query_legacy_specific_async(group.clone(), &self.address, port, self.timeout_settings()?).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocols::valve::SteamID;
+    use super::*;
+
+    #[test]
+    fn timeout_settings() {
+        assert!(Query::valve("127.0.0.1").timeout_settings().unwrap().is_none());
+
+        let settings = Query::minecraft_java("127.0.0.1").read_timeout(Duration::from_secs(1)).timeout_settings().unwrap().unwrap();
+        assert_eq!(settings.get_read(), Some(Duration::from_secs(1)));
+        assert_eq!(settings.get_write(), Some(Duration::from_secs(4)));
+
+        assert!(Query::minecraft_bedrock("127.0.0.1").write_timeout(Duration::new(0, 0)).run().is_err());
+    }
+
+    #[test]
+    fn valve_options() {
+        let query = Query::valve("127.0.0.1").app(SteamID::TF2).players(false).lenient(true);
+
+        assert!(matches!(query.protocol.app, App::Source(Some(440))));
+        assert!(!query.protocol.gather_settings.players);
+        assert!(query.protocol.gather_settings.rules);
+        assert!(query.protocol.gather_settings.lenient);
+    }
+}
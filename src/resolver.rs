@@ -0,0 +1,260 @@
+//! Hostname resolving.
+//!
+//! Queries resolve hostnames through a [Resolver], the default one being the [SystemResolver], a different
+//! one can be passed (example: to use a specific DNS server or to fake the lookups in tests).
+
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::{GDErrorKind, GDResult};
+
+/// A SRV record's target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvTarget {
+    /// The host to connect to.
+    pub host: String,
+    /// The port to connect to.
+    pub port: u16
+}
+
+/// Resolves hostnames to ip addresses (A/AAAA) and looks up SRV records.
+pub trait Resolver {
+    /// Resolve a host (which can also be an ip) to its ip addresses.
+    fn resolve(&self, host: &str) -> GDResult<Vec<IpAddr>>;
+
+    /// Look up a SRV record (example: `_minecraft._tcp.play.example.net`), returns None if there isn't one.
+    fn resolve_srv(&self, name: &str) -> GDResult<Option<SrvTarget>>;
+}
+
+/// Resolves hosts using the system's resolver and SRV records by asking the system's DNS server (the first
+/// `nameserver` in `/etc/resolv.conf`), if there isn't one, no SRV records are found.
+#[derive(Debug, Clone, Default)]
+pub struct SystemResolver;
+
+static DNS_PORT: u16 = 53;
+static DNS_TIMEOUT: Duration = Duration::from_secs(2);
+static DNS_PACKET_SIZE: usize = 1024;
+static RECORD_TYPE_SRV: u16 = 33;
+static RECORD_CLASS_IN: u16 = 1;
+
+impl SystemResolver {
+    fn nameserver() -> Option<IpAddr> {
+        std::fs::read_to_string("/etc/resolv.conf").ok()?
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("nameserver"))
+            .find_map(|address| address.trim().parse().ok())
+    }
+}
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str) -> GDResult<Vec<IpAddr>> {
+        if let Ok(ip) = host.parse() {
+            return Ok(vec![ip]);
+        }
+
+        let addresses = (host, 0).to_socket_addrs()
+            .map_err(|e| GDErrorKind::InvalidInput(format!("Couldn't resolve {host}: {e}")).context(e))?;
+
+        Ok(addresses.map(|address| address.ip()).collect())
+    }
+
+    fn resolve_srv(&self, name: &str) -> GDResult<Option<SrvTarget>> {
+        let nameserver = match SystemResolver::nameserver() {
+            None => return Ok(None),
+            Some(nameserver) => nameserver
+        };
+
+        let bind_address = match nameserver {
+            IpAddr::V4(_) => "0.0.0.0:0",
+            IpAddr::V6(_) => "[::]:0"
+        };
+
+        let socket = UdpSocket::bind(bind_address).map_err(|e| GDErrorKind::SocketBind(e.to_string()).context(e))?;
+        socket.set_read_timeout(Some(DNS_TIMEOUT)).map_err(|e| GDErrorKind::SocketBind(e.to_string()).context(e))?;
+
+        //the id just has to differ between requests, the time is good enough for that
+        let id = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos() as u16).unwrap_or(0);
+
+        socket.send_to(&srv_request(id, name)?, SocketAddr::new(nameserver, DNS_PORT))
+            .map_err(|e| GDErrorKind::PacketSend(e.to_string()).context(e))?;
+
+        let mut buf = vec![0; DNS_PACKET_SIZE];
+        let size = socket.recv(&mut buf).map_err(|e| GDErrorKind::PacketReceive(e.to_string()).context(e))?;
+
+        parse_srv_response(id, &buf[..size])
+    }
+}
+
+/// Resolve a host to its first ip address (an ip is returned as it is, without asking the resolver).
+pub(crate) fn resolve_ip(resolver: &dyn Resolver, host: &str) -> GDResult<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return Ok(ip);
+    }
+
+    resolver.resolve(host)?.into_iter().next()
+        .ok_or_else(|| GDErrorKind::InvalidInput(format!("{host} has no addresses.")).into())
+}
+
+fn srv_request(id: u16, name: &str) -> GDResult<Vec<u8>> {
+    let mut buf = Vec::with_capacity(name.len() + 18);
+
+    buf.extend(id.to_be_bytes());
+    buf.extend([0x01, 0x00]); //recursion desired
+    buf.extend([0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]); //one question, no other records
+
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(GDErrorKind::InvalidInput(format!("Invalid domain name: {name}")).into());
+        }
+
+        buf.push(label.len() as u8);
+        buf.extend(label.as_bytes());
+    }
+
+    buf.push(0);
+    buf.extend(RECORD_TYPE_SRV.to_be_bytes());
+    buf.extend(RECORD_CLASS_IN.to_be_bytes());
+
+    Ok(buf)
+}
+
+fn read_u16(data: &[u8], position: usize) -> GDResult<u16> {
+    data.get(position..position + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| GDErrorKind::PacketUnderflow("Unexpectedly short DNS response.".to_string()).into())
+}
+
+/// Reads a (possibly compressed) domain name, returns it and the position after it.
+fn read_name(data: &[u8], mut position: usize) -> GDResult<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+
+    //every pointer has to point backwards, so there can't be more jumps than bytes
+    for _ in 0..data.len() {
+        let length = *data.get(position)
+            .ok_or_else(|| GDErrorKind::PacketUnderflow("Unexpectedly short DNS name.".to_string()))? as usize;
+
+        if length == 0 {
+            return Ok((labels.join("."), end.unwrap_or(position + 1)));
+        }
+
+        if length & 0xC0 == 0xC0 {
+            let pointer = (read_u16(data, position)? & 0x3FFF) as usize;
+            if pointer >= position {
+                return Err(GDErrorKind::PacketBad("Bad DNS name pointer.".to_string()).into());
+            }
+
+            end.get_or_insert(position + 2);
+            position = pointer;
+            continue;
+        }
+
+        let label = data.get(position + 1..position + 1 + length)
+            .ok_or_else(|| GDErrorKind::PacketUnderflow("Unexpectedly short DNS label.".to_string()))?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        position += length + 1;
+    }
+
+    Err(GDErrorKind::PacketBad("Bad DNS name.".to_string()).into())
+}
+
+/// Parses the response, picking the record with the lowest priority and the highest weight.
+fn parse_srv_response(id: u16, data: &[u8]) -> GDResult<Option<SrvTarget>> {
+    if read_u16(data, 0)? != id {
+        return Err(GDErrorKind::PacketBad("DNS response id doesn't match the request.".to_string()).into());
+    }
+
+    match read_u16(data, 2)? & 0x000F {
+        0 => {}
+        3 => return Ok(None), //the name doesn't exist
+        code => return Err(GDErrorKind::PacketBad(format!("DNS server replied with error code {code}.")).into())
+    }
+
+    let questions = read_u16(data, 4)?;
+    let answers = read_u16(data, 6)?;
+
+    let mut position = 12;
+    for _ in 0..questions {
+        position = read_name(data, position)?.1 + 4;
+    }
+
+    let mut best: Option<(u16, u16, SrvTarget)> = None;
+    for _ in 0..answers {
+        position = read_name(data, position)?.1;
+
+        let record_type = read_u16(data, position)?;
+        let data_length = read_u16(data, position + 8)? as usize;
+        let data_position = position + 10;
+        position = data_position + data_length;
+
+        if record_type != RECORD_TYPE_SRV {
+            continue;
+        }
+
+        let priority = read_u16(data, data_position)?;
+        let weight = read_u16(data, data_position + 2)?;
+        let target = SrvTarget {
+            port: read_u16(data, data_position + 4)?,
+            host: read_name(data, data_position + 6)?.0
+        };
+
+        let is_better = match &best {
+            None => true,
+            Some((best_priority, best_weight, _)) => priority < *best_priority || (priority == *best_priority && weight > *best_weight)
+        };
+
+        if is_better {
+            best = Some((priority, weight, target));
+        }
+    }
+
+    Ok(best.map(|(_, _, target)| target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request() {
+        let request = srv_request(0x1234, "_minecraft._tcp.mc.net").unwrap();
+
+        assert_eq!(&request[..4], &[0x12, 0x34, 0x01, 0x00]);
+        assert_eq!(&request[12..], b"\x0a_minecraft\x04_tcp\x02mc\x03net\x00\x00\x21\x00\x01");
+        assert!(srv_request(0, "bad..name").is_err());
+    }
+
+    #[test]
+    fn srv_response() {
+        let mut response = srv_request(7, "_minecraft._tcp.mc.net").unwrap();
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 2;
+
+        //a worse (higher priority) record, the name being a pointer to the question's one
+        response.extend([0xC0, 0x0C, 0x00, 0x21, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3C, 0x00, 0x0C]);
+        response.extend([0x00, 0x14, 0x00, 0x00, 0x63, 0xDD]);
+        response.extend(b"\x03bad\xC0\x1C");
+
+        //the target's name ends with a pointer to `mc.net`
+        response.extend([0xC0, 0x0C, 0x00, 0x21, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3C, 0x00, 0x0D]);
+        response.extend([0x00, 0x0A, 0x00, 0x05, 0x63, 0xDE]);
+        response.extend(b"\x04play\xC0\x1C");
+
+        let target = parse_srv_response(7, &response).unwrap().unwrap();
+        assert_eq!(target, SrvTarget {
+            host: "play.mc.net".to_string(),
+            port: 25566
+        });
+
+        assert!(parse_srv_response(8, &response).is_err());
+
+        response[3] = 0x83;
+        assert_eq!(parse_srv_response(7, &response).unwrap(), None);
+    }
+
+    #[test]
+    fn ip_literals() {
+        assert_eq!(SystemResolver.resolve("127.0.0.1").unwrap(), vec![IpAddr::from([127, 0, 0, 1])]);
+        assert_eq!(SystemResolver.resolve("::1").unwrap(), vec!["::1".parse::<IpAddr>().unwrap()]);
+    }
+}
@@ -0,0 +1,7 @@
+//! Services that are currently implemented.
+//!
+//! A service is something that isn't a game server (like a voice server), but can be queried
+//! for information about itself.
+
+/// Reference: [ServerQuery Manual](https://yat.qa/ressourcen/server-query-kommentare/)
+pub mod teamspeak3;
@@ -0,0 +1,459 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net;
+use crate::{GDErrorKind, GDResult};
+use crate::protocols::types::TimeoutSettings;
+use crate::services::teamspeak3::types::{Channel, Client, Credentials, Properties, Response, ServerInfo};
+use crate::utils::address_and_port_as_string;
+
+#[cfg(feature = "async")]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+#[cfg(feature = "async")]
+use crate::socket::with_timeout;
+
+/// The default ServerQuery port.
+pub static DEFAULT_PORT: u16 = 10011;
+/// The default virtual server (voice) port.
+pub static DEFAULT_VIRTUAL_SERVER_PORT: u16 = 9987;
+
+static GREETING: &str = "TS3";
+
+/// The escaped characters and their escape sequences.
+static ESCAPES: [(char, &str); 11] = [
+    ('\\', "\\\\"), ('/', "\\/"), (' ', "\\s"), ('|', "\\p"), ('\x07', "\\a"), ('\x08', "\\b"),
+    ('\x0C', "\\f"), ('\n', "\\n"), ('\r', "\\r"), ('\t', "\\t"), ('\x0B', "\\v")
+];
+
+/// Escapes a value to be sent in a command.
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match ESCAPES.iter().find(|(character, _)| *character == c) {
+            Some((_, sequence)) => escaped.push_str(sequence),
+            None => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+/// Unescapes a value received from the server, unknown escape sequences are kept as they are.
+pub fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            None => unescaped.push('\\'),
+            Some(next) => match ESCAPES.iter().find(|(_, sequence)| sequence.ends_with(next)) {
+                Some((character, _)) => unescaped.push(*character),
+                None => {
+                    unescaped.push('\\');
+                    unescaped.push(next);
+                }
+            }
+        }
+    }
+
+    unescaped
+}
+
+/// Parses a data line into its entries (separated by `|`), each having its properties (separated by spaces).
+fn parse_entries(line: &str) -> Vec<Properties> {
+    line.split('|').map(|entry| {
+        entry.split(' ')
+            .filter(|property| !property.is_empty())
+            .map(|property| match property.split_once('=') {
+                None => (unescape(property), String::new()),
+                Some((key, value)) => (unescape(key), unescape(value))
+            })
+            .collect()
+    }).collect()
+}
+
+/// Checks if the line is the status line that ends every command's response (`error id=0 msg=ok`),
+/// if so, returns the command's result.
+fn parse_status(line: &str) -> Option<GDResult<()>> {
+    let properties = line.strip_prefix("error ")?;
+    let properties = parse_entries(properties).into_iter().next().unwrap_or_default();
+
+    let id = properties.get("id").and_then(|id| id.parse().ok()).unwrap_or(u32::MAX);
+    Some(match id {
+        0 => Ok(()),
+        _ => Err(GDErrorKind::ServiceError(id, properties.get("msg").cloned().unwrap_or_default()).into())
+    })
+}
+
+fn check_greeting(line: &str) -> GDResult<()> {
+    match line == GREETING {
+        true => Ok(()),
+        false => Err(GDErrorKind::ProtocolFormat("The server isn't a TeamSpeak 3 ServerQuery interface.".to_string()).into())
+    }
+}
+
+fn login_command(credentials: &Credentials) -> String {
+    format!("login client_login_name={} client_login_password={}", escape(&credentials.username), escape(&credentials.password))
+}
+
+fn use_command(virtual_server_port: u16) -> String {
+    format!("use port={virtual_server_port}")
+}
+
+fn take_string(properties: &mut Properties, key: &str) -> String {
+    properties.remove(key).unwrap_or_default()
+}
+
+fn take_number<T: std::str::FromStr + Default>(properties: &mut Properties, key: &str) -> GDResult<T>
+    where T::Err: std::error::Error + Send + Sync + 'static {
+    match properties.remove(key) {
+        None => Ok(T::default()),
+        Some(value) => value.parse()
+            .map_err(|e| GDErrorKind::TypeParse(format!("Couldn't parse {key}.")).context(e))
+    }
+}
+
+fn get_server_info(mut entries: Vec<Properties>) -> GDResult<ServerInfo> {
+    if entries.is_empty() {
+        return Err(GDErrorKind::PacketBad("The server info is missing.".to_string()).into());
+    }
+
+    let mut properties = entries.swap_remove(0);
+    Ok(ServerInfo {
+        name: take_string(&mut properties, "virtualserver_name"),
+        welcome_message: take_string(&mut properties, "virtualserver_welcomemessage"),
+        platform: take_string(&mut properties, "virtualserver_platform"),
+        version: take_string(&mut properties, "virtualserver_version"),
+        port: take_number(&mut properties, "virtualserver_port")?,
+        clients_online: take_number(&mut properties, "virtualserver_clientsonline")?,
+        clients_maximum: take_number(&mut properties, "virtualserver_maxclients")?,
+        channels_online: take_number(&mut properties, "virtualserver_channelsonline")?,
+        uptime: take_number(&mut properties, "virtualserver_uptime")?,
+        has_password: take_string(&mut properties, "virtualserver_flag_password") == "1",
+        properties
+    })
+}
+
+fn get_client(mut properties: Properties) -> GDResult<Client> {
+    Ok(Client {
+        id: take_number(&mut properties, "clid")?,
+        channel_id: take_number(&mut properties, "cid")?,
+        database_id: take_number(&mut properties, "client_database_id")?,
+        nickname: take_string(&mut properties, "client_nickname"),
+        is_query_client: take_string(&mut properties, "client_type") == "1",
+        properties
+    })
+}
+
+fn get_channel(mut properties: Properties) -> GDResult<Channel> {
+    Ok(Channel {
+        id: take_number(&mut properties, "cid")?,
+        parent_id: take_number(&mut properties, "pid")?,
+        order: take_number(&mut properties, "channel_order")?,
+        name: take_string(&mut properties, "channel_name"),
+        clients_count: take_number(&mut properties, "total_clients")?,
+        properties
+    })
+}
+
+fn get_response(server: Vec<Properties>, clients: Vec<Properties>, channels: Vec<Properties>) -> GDResult<Response> {
+    Ok(Response {
+        server: get_server_info(server)?,
+        clients: clients.into_iter()
+            .filter(|properties| !properties.is_empty())
+            .map(get_client)
+            .collect::<GDResult<Vec<Client>>>()?,
+        channels: channels.into_iter()
+            .filter(|properties| !properties.is_empty())
+            .map(get_channel)
+            .collect::<GDResult<Vec<Channel>>>()?
+    })
+}
+
+/// Lines are terminated by `\n\r`, so we trim both.
+fn line_from_bytes(bytes: &[u8]) -> GDResult<String> {
+    if bytes.is_empty() {
+        return Err(GDErrorKind::PacketReceive("The server closed the connection.".to_string()).into());
+    }
+
+    Ok(String::from_utf8_lossy(bytes).trim_matches(|c| c == '\r' || c == '\n').to_string())
+}
+
+/// Connects to the ServerQuery interface, returns the reading and the writing halves of the connection.
+fn connect(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<(net::TcpStream, net::TcpStream)> {
+    let socket = net::TcpStream::connect(address_and_port_as_string(address, port))
+        .map_err(|e| GDErrorKind::SocketConnect(e.to_string()).context(e))?;
+
+    let settings = timeout_settings.unwrap_or_default();
+    //a zero duration is the only error, TimeoutSettings::new refuses it, but deserialized settings may have it
+    socket.set_read_timeout(settings.get_read()).map_err(|e| GDErrorKind::InvalidInput(e.to_string()).context(e))?;
+    socket.set_write_timeout(settings.get_write()).map_err(|e| GDErrorKind::InvalidInput(e.to_string()).context(e))?;
+
+    let writer = socket.try_clone().map_err(|e| GDErrorKind::SocketConnect(e.to_string()).context(e))?;
+    Ok((socket, writer))
+}
+
+struct ServerQuery<R: Read, W: Write> {
+    reader: BufReader<R>,
+    writer: W
+}
+
+impl<R: Read, W: Write> ServerQuery<R, W> {
+    fn new(reader: R, writer: W) -> GDResult<Self> {
+        let mut server_query = Self {
+            reader: BufReader::new(reader),
+            writer
+        };
+
+        check_greeting(&server_query.read_line()?)?;
+        server_query.read_line()?; //the welcome message
+
+        Ok(server_query)
+    }
+
+    fn read_line(&mut self) -> GDResult<String> {
+        let mut buf = Vec::new();
+        self.reader.read_until(b'\n', &mut buf).map_err(|e| GDErrorKind::PacketReceive(e.to_string()).context(e))?;
+
+        line_from_bytes(&buf)
+    }
+
+    fn send_line(&mut self, line: &str) -> GDResult<()> {
+        self.writer.write_all(format!("{line}\n").as_bytes()).map_err(|e| GDErrorKind::PacketSend(e.to_string()).context(e))
+    }
+
+    /// Sends a command and returns its data entries.
+    fn command(&mut self, command: &str) -> GDResult<Vec<Properties>> {
+        self.send_line(command)?;
+
+        let mut entries = Vec::new();
+        loop {
+            let line = self.read_line()?;
+
+            match parse_status(&line) {
+                Some(status) => return status.map(|_| entries),
+                None if line.is_empty() => {},
+                None => entries.extend(parse_entries(&line))
+            }
+        }
+    }
+}
+
+/// Query a TeamSpeak 3 server through its ServerQuery interface, logging in (if given credentials) and
+/// selecting the virtual server by its (voice) port.
+pub fn query(address: &str, port: u16, virtual_server_port: u16, credentials: Option<Credentials>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let (reader, writer) = connect(address, port, timeout_settings)?;
+    query_with_stream(reader, writer, virtual_server_port, credentials)
+}
+
+/// Query a TeamSpeak 3 server through an already opened connection, given its reading and writing
+/// halves (the timeouts being up to them), same as [query].
+pub fn query_with_stream<R: Read, W: Write>(reader: R, writer: W, virtual_server_port: u16, credentials: Option<Credentials>) -> GDResult<Response> {
+    let mut server_query = ServerQuery::new(reader, writer)?;
+
+    if let Some(credentials) = credentials {
+        server_query.command(&login_command(&credentials))?;
+    }
+
+    server_query.command(&use_command(virtual_server_port))?;
+
+    let server = server_query.command("serverinfo")?;
+    let clients = server_query.command("clientlist")?;
+    let channels = server_query.command("channellist")?;
+
+    //we don't care if it fails, we already got everything
+    server_query.send_line("quit").ok();
+
+    get_response(server, clients, channels)
+}
+
+#[cfg(feature = "async")]
+struct AsyncServerQuery {
+    reader: tokio::io::BufReader<tokio::net::tcp::OwnedReadHalf>,
+    writer: tokio::net::tcp::OwnedWriteHalf,
+    timeout_settings: TimeoutSettings
+}
+
+#[cfg(feature = "async")]
+impl AsyncServerQuery {
+    async fn connect(address: &str, port: u16, timeout_settings: Option<TimeoutSettings>) -> GDResult<Self> {
+        let timeout_settings = timeout_settings.unwrap_or_default();
+        let socket = with_timeout(timeout_settings.get_write(), tokio::net::TcpStream::connect(address_and_port_as_string(address, port)), GDErrorKind::SocketConnect).await?;
+
+        let (reader, writer) = socket.into_split();
+        let mut server_query = Self {
+            reader: tokio::io::BufReader::new(reader),
+            writer,
+            timeout_settings
+        };
+
+        check_greeting(&server_query.read_line().await?)?;
+        server_query.read_line().await?; //the welcome message
+
+        Ok(server_query)
+    }
+
+    async fn read_line(&mut self) -> GDResult<String> {
+        let mut buf = Vec::new();
+        with_timeout(self.timeout_settings.get_read(), self.reader.read_until(b'\n', &mut buf), GDErrorKind::PacketReceive).await?;
+
+        line_from_bytes(&buf)
+    }
+
+    async fn send_line(&mut self, line: &str) -> GDResult<()> {
+        with_timeout(self.timeout_settings.get_write(), self.writer.write_all(format!("{line}\n").as_bytes()), GDErrorKind::PacketSend).await
+    }
+
+    /// Sends a command and returns its data entries.
+    async fn command(&mut self, command: &str) -> GDResult<Vec<Properties>> {
+        self.send_line(command).await?;
+
+        let mut entries = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+
+            match parse_status(&line) {
+                Some(status) => return status.map(|_| entries),
+                None if line.is_empty() => {},
+                None => entries.extend(parse_entries(&line))
+            }
+        }
+    }
+}
+
+/// Asynchronously query a TeamSpeak 3 server through its ServerQuery interface.
+#[cfg(feature = "async")]
+pub async fn query_async(address: &str, port: u16, virtual_server_port: u16, credentials: Option<Credentials>, timeout_settings: Option<TimeoutSettings>) -> GDResult<Response> {
+    let mut server_query = AsyncServerQuery::connect(address, port, timeout_settings).await?;
+
+    if let Some(credentials) = credentials {
+        server_query.command(&login_command(&credentials)).await?;
+    }
+
+    server_query.command(&use_command(virtual_server_port)).await?;
+
+    let server = server_query.command("serverinfo").await?;
+    let clients = server_query.command("clientlist").await?;
+    let channels = server_query.command("channellist").await?;
+
+    //we don't care if it fails, we already got everything
+    server_query.send_line("quit").await.ok();
+
+    get_response(server, clients, channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn escaping() {
+        let value = "a name/with|special\\chars\n";
+        let escaped = escape(value);
+
+        assert_eq!(escaped, "a\\sname\\/with\\pspecial\\\\chars\\n");
+        assert_eq!(unescape(&escaped), value);
+        assert_eq!(unescape("unknown\\x"), "unknown\\x");
+    }
+
+    #[test]
+    fn status() {
+        assert!(parse_status("virtualserver_name=A").is_none());
+        assert!(parse_status("error id=0 msg=ok").unwrap().is_ok());
+
+        match parse_status("error id=520 msg=invalid\\sloginname\\sor\\spassword").map(|status| status.map_err(|e| e.kind().clone())) {
+            Some(Err(GDErrorKind::ServiceError(id, message))) => {
+                assert_eq!(id, 520);
+                assert_eq!(message, "invalid loginname or password");
+            }
+            _ => panic!("Expected a service error.")
+        }
+    }
+
+    #[test]
+    fn entries() {
+        let entries = parse_entries("clid=1 cid=2 client_nickname=First\\sOne client_type=0|clid=5 cid=2 client_nickname=serveradmin client_type=1");
+        let clients = entries.into_iter().map(get_client).collect::<GDResult<Vec<Client>>>().unwrap();
+
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients[0].nickname, "First One");
+        assert!(!clients[0].is_query_client);
+        assert_eq!(clients[1].id, 5);
+        assert!(clients[1].is_query_client);
+    }
+
+    #[test]
+    fn query_against_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"TS3\n\rWelcome to the TeamSpeak 3 ServerQuery interface.\n\r").unwrap();
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut commands = Vec::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+
+                let line = line.trim().to_string();
+                let response = match line.split(' ').next().unwrap() {
+                    "serverinfo" => "virtualserver_name=A\\sServer virtualserver_port=9987 virtualserver_clientsonline=1 virtualserver_maxclients=32 virtualserver_flag_password=0\n\rerror id=0 msg=ok\n\r",
+                    "clientlist" => "clid=1 cid=1 client_database_id=2 client_nickname=Someone client_type=0\n\rerror id=0 msg=ok\n\r",
+                    "channellist" => "cid=1 pid=0 channel_order=0 channel_name=Lobby total_clients=1\n\rerror id=0 msg=ok\n\r",
+                    "quit" => "error id=0 msg=ok\n\r",
+                    _ => "error id=0 msg=ok\n\r"
+                };
+
+                stream.write_all(response.as_bytes()).unwrap();
+                commands.push(line.clone());
+                if line == "quit" {
+                    break;
+                }
+            }
+
+            let mut rest = Vec::new();
+            stream.read_to_end(&mut rest).ok();
+            commands
+        });
+
+        let response = query("127.0.0.1", port, 9987, Some(Credentials::new("server admin", "pass")), None).unwrap();
+        assert_eq!(response.server.name, "A Server");
+        assert_eq!(response.server.clients_maximum, 32);
+        assert!(!response.server.has_password);
+        assert_eq!(response.clients[0].nickname, "Someone");
+        assert_eq!(response.channels[0].name, "Lobby");
+
+        let commands = server.join().unwrap();
+        assert_eq!(commands[0], "login client_login_name=server\\sadmin client_login_password=pass");
+        assert_eq!(commands[1], "use port=9987");
+    }
+
+    #[test]
+    fn query_through_a_stream() {
+        let responses = b"TS3\n\rWelcome.\n\rerror id=0 msg=ok\n\r\
+            virtualserver_name=A\\sServer\n\rerror id=0 msg=ok\n\r\
+            clid=1 cid=1 client_nickname=Someone\n\rerror id=0 msg=ok\n\r\
+            cid=1 channel_name=Lobby\n\rerror id=0 msg=ok\n\r";
+        let mut commands = Vec::new();
+
+        let response = query_with_stream(&responses[..], &mut commands, 9987, None).unwrap();
+        assert_eq!(response.server.name, "A Server");
+        assert_eq!(response.clients[0].nickname, "Someone");
+        assert_eq!(response.channels[0].name, "Lobby");
+        assert_eq!(commands, b"use port=9987\nserverinfo\nclientlist\nchannellist\nquit\n");
+
+        //a closed connection in the middle of a response
+        assert!(query_with_stream(&responses[..40], std::io::sink(), 9987, None).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use crate::protocols::types::{GenericPlayer, GenericResponse, SpecificResponse};
+
+/// The properties of a single entry, as sent by the server (already unescaped).
+pub type Properties = HashMap<String, String>;
+
+/// ServerQuery login credentials.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Credentials {
+    pub username: String,
+    pub password: String
+}
+
+impl Credentials {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            password: password.to_string()
+        }
+    }
+}
+
+/// The virtual server's information (`serverinfo`).
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServerInfo {
+    /// Name of the virtual server.
+    pub name: String,
+    /// The server's welcome message.
+    pub welcome_message: String,
+    /// The platform the server runs on.
+    pub platform: String,
+    /// The server's version.
+    pub version: String,
+    /// The virtual server's voice port.
+    pub port: u16,
+    /// Number of clients connected (query clients included).
+    pub clients_online: u32,
+    /// Maximum number of clients.
+    pub clients_maximum: u32,
+    /// Number of channels.
+    pub channels_online: u32,
+    /// For how long the server has been up (in seconds).
+    pub uptime: u64,
+    /// Indicates whether the server requires a password.
+    pub has_password: bool,
+    /// Every other property the server reported.
+    pub properties: Properties
+}
+
+/// A connected client (`clientlist`).
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Client {
+    /// The client's id.
+    pub id: u32,
+    /// The id of the channel the client is in.
+    pub channel_id: u32,
+    /// The client's database id.
+    pub database_id: u32,
+    /// The client's nickname.
+    pub nickname: String,
+    /// Indicates whether the client is a query client (and not a voice one).
+    pub is_query_client: bool,
+    /// Every other property the server reported.
+    pub properties: Properties
+}
+
+/// A channel (`channellist`).
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channel {
+    /// The channel's id.
+    pub id: u32,
+    /// The parent channel's id (0 if none).
+    pub parent_id: u32,
+    /// The channel's sort order.
+    pub order: u32,
+    /// The channel's name.
+    pub name: String,
+    /// Number of clients in the channel.
+    pub clients_count: u32,
+    /// Every other property the server reported.
+    pub properties: Properties
+}
+
+/// A query response.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    pub server: ServerInfo,
+    pub clients: Vec<Client>,
+    pub channels: Vec<Channel>
+}
+
+impl From<Response> for GenericResponse {
+    fn from(response: Response) -> Self {
+        Self {
+            name: Some(response.server.name.clone()),
+            description: Some(response.server.welcome_message.clone()),
+            game: None,
+            game_version: Some(response.server.version.clone()),
+            map: None,
+            players_maximum: response.server.clients_maximum,
+            players_online: response.server.clients_online,
+            players_bots: None,
+            has_password: Some(response.server.has_password),
+            players: response.clients.iter()
+                .filter(|client| !client.is_query_client)
+                .map(|client| GenericPlayer {
+                    name: client.nickname.clone(),
+                    score: None
+                }).collect(),
+            ping: None,
+            inner: SpecificResponse::TeamSpeak3(response)
+        }
+    }
+}
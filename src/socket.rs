@@ -1,88 +1,607 @@
+//! The transports that the queries go through.
+//!
+//! Every protocol's query has a `query_with_socket` variant which takes anything that implements [Socket],
+//! so the packets can be routed through a proxy or replayed from memory (with the [MockSocket]), the async
+//! queries have a `query_with_socket_async` one which takes an `AsyncSocket` (the [MockSocket] is one too).
+
+use std::collections::VecDeque;
 use std::io::{Read, Write};
 use std::net;
-use crate::{GDError, GDResult};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Instant;
+use crate::{GDError, GDErrorKind, GDResult, Stage};
 use crate::protocols::types::TimeoutSettings;
-use crate::utils::address_and_port_as_string;
+use crate::utils::{address_and_port_as_string, deadline_passed};
+
+#[cfg(feature = "async")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 static DEFAULT_PACKET_SIZE: usize = 1024;
 
+/// A transport that packets are sent and received through.
 pub trait Socket {
-    fn new(address: &str, port: u16) -> GDResult<Self> where Self: Sized;
-
+    /// Applies the timeout settings (None being the default ones).
     fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()>;
 
+    /// Sends a packet.
     fn send(&mut self, data: &[u8]) -> GDResult<()>;
+    /// Receives a packet (for a stream, everything until it's closed), the size is how big it's expected to be at most.
     fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>>;
+
+    /// Same as [receive](Socket::receive), but into `buffer` (replacing its content), so that a buffer can be
+    /// reused for many packets instead of allocating one for each.
+    fn receive_into(&mut self, buffer: &mut Vec<u8>, size: Option<usize>) -> GDResult<()> {
+        *buffer = self.receive(size)?;
+        Ok(())
+    }
+
+    /// Receives exactly `size` bytes (used by the protocols that read a stream packet by packet), by default
+    /// this is a [receive](Socket::receive) that has to return that many.
+    fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        let data = self.receive(Some(size))?;
+        match data.len() == size {
+            true => Ok(data),
+            false => Err(GDErrorKind::PacketUnderflow(format!("Expected {size} bytes, received {}.", data.len())).into())
+        }
+    }
+}
+
+impl<S: Socket + ?Sized> Socket for &mut S {
+    fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        (**self).apply_timeout(timeout_settings)
+    }
+
+    fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        (**self).send(data)
+    }
+
+    fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        (**self).receive(size)
+    }
+
+    fn receive_into(&mut self, buffer: &mut Vec<u8>, size: Option<usize>) -> GDResult<()> {
+        (**self).receive_into(buffer, size)
+    }
+
+    fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        (**self).receive_exact(size)
+    }
+}
+
+/// A socket whose every operation is limited by the query's deadline (see
+/// [RetrySettings](crate::protocols::types::RetrySettings)), the queries wrap their socket in it.
+pub(crate) struct DeadlineSocket<S: Socket> {
+    socket: S,
+    timeout_settings: TimeoutSettings,
+    deadline: Option<Instant>
+}
+
+impl<S: Socket> DeadlineSocket<S> {
+    pub(crate) fn new(socket: S, timeout_settings: &Option<TimeoutSettings>) -> Self {
+        let timeout_settings = timeout_settings.clone().unwrap_or_default();
+        let deadline = timeout_settings.get_retry().get_deadline().map(|deadline| Instant::now() + deadline);
+
+        Self {
+            socket,
+            timeout_settings,
+            deadline
+        }
+    }
+
+    /// Applies the timeouts that are left until the deadline.
+    fn limit(&self) -> GDResult<()> {
+        match self.deadline {
+            None => Ok(()),
+            Some(deadline) => match self.timeout_settings.until(deadline) {
+                None => Err(deadline_passed()),
+                Some(timeout_settings) => self.socket.apply_timeout(Some(timeout_settings))
+            }
+        }
+    }
+}
+
+impl<S: Socket> Socket for DeadlineSocket<S> {
+    fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        self.socket.apply_timeout(timeout_settings)
+    }
+
+    fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        self.limit()?;
+        self.socket.send(data)
+    }
+
+    fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        self.limit()?;
+        self.socket.receive(size)
+    }
+
+    fn receive_into(&mut self, buffer: &mut Vec<u8>, size: Option<usize>) -> GDResult<()> {
+        self.limit()?;
+        self.socket.receive_into(buffer, size)
+    }
+
+    fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        self.limit()?;
+        self.socket.receive_exact(size)
+    }
 }
 
 pub struct TcpSocket {
     socket: net::TcpStream
 }
 
-impl Socket for TcpSocket {
-    fn new(address: &str, port: u16) -> GDResult<Self> {
+impl TcpSocket {
+    pub fn new(address: &str, port: u16) -> GDResult<Self> {
         let complete_address = address_and_port_as_string(address, port);
-        let socket = net::TcpStream::connect(complete_address).map_err(|e| GDError::SocketConnect(e.to_string()))?;
+        let socket = net::TcpStream::connect(complete_address).map_err(|e| GDErrorKind::SocketConnect(e.to_string()).context(e).at(Stage::Connect))?;
 
         Ok(Self {
             socket
         })
     }
+}
 
+impl Socket for TcpSocket {
     fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
-        let settings = timeout_settings.unwrap_or(TimeoutSettings::default());
-        self.socket.set_read_timeout(settings.get_read()).unwrap();   //unwrapping because TimeoutSettings::new
-        self.socket.set_write_timeout(settings.get_write()).unwrap(); //checks if these are 0 and throws an error
+        let settings = timeout_settings.unwrap_or_default();
+        //a zero duration is the only error, TimeoutSettings::new refuses it, but deserialized settings may have it
+        self.socket.set_read_timeout(settings.get_read()).map_err(|e| GDErrorKind::InvalidInput(e.to_string()).context(e))?;
+        self.socket.set_write_timeout(settings.get_write()).map_err(|e| GDErrorKind::InvalidInput(e.to_string()).context(e))?;
 
         Ok(())
     }
 
     fn send(&mut self, data: &[u8]) -> GDResult<()> {
-        self.socket.write(&data).map_err(|e| GDError::PacketSend(e.to_string()))?;
+        self.socket.write(data).map_err(|e| GDErrorKind::PacketSend(e.to_string()).context(e))?;
         Ok(())
     }
 
     fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
         let mut buf = Vec::with_capacity(size.unwrap_or(DEFAULT_PACKET_SIZE));
-        self.socket.read_to_end(&mut buf).map_err(|e| GDError::PacketReceive(e.to_string()))?;
+        self.receive_into(&mut buf, size)?;
+
+        Ok(buf)
+    }
+
+    fn receive_into(&mut self, buffer: &mut Vec<u8>, _size: Option<usize>) -> GDResult<()> {
+        buffer.clear();
+        self.socket.read_to_end(buffer).map_err(|e| GDErrorKind::PacketReceive(e.to_string()).context(e))?;
+
+        Ok(())
+    }
+
+    fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        let mut buf = vec![0; size];
+        self.socket.read_exact(&mut buf).map_err(|e| GDErrorKind::PacketReceive(e.to_string()).context(e))?;
 
         Ok(buf)
     }
 }
 
+/// The address to bind to for sending to the target (an IPv6 target needs an IPv6 socket).
+fn unspecified_address_for(target: &SocketAddr) -> &'static str {
+    match target {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0"
+    }
+}
+
 pub struct UdpSocket {
     socket: net::UdpSocket,
-    complete_address: String
+    address: SocketAddr
 }
 
-impl Socket for UdpSocket {
-    fn new(address: &str, port: u16) -> GDResult<Self> {
-        let complete_address = address_and_port_as_string(address, port);
-        let socket = net::UdpSocket::bind("0.0.0.0:0").map_err(|e| GDError::SocketBind(e.to_string()))?;
+impl UdpSocket {
+    pub fn new(address: &str, port: u16) -> GDResult<Self> {
+        let address = address_and_port_as_string(address, port).to_socket_addrs()
+            .map_err(|e| GDErrorKind::SocketConnect(e.to_string()).context(e).at(Stage::Connect))?
+            .next()
+            .ok_or_else(|| GDError::from(GDErrorKind::SocketConnect("The address has no ips.".to_string())).at(Stage::Connect))?;
+
+        Self::new_with_address(address)
+    }
+
+    pub fn new_with_address(address: SocketAddr) -> GDResult<Self> {
+        let socket = net::UdpSocket::bind(unspecified_address_for(&address)).map_err(|e| GDErrorKind::SocketBind(e.to_string()).context(e).at(Stage::Connect))?;
 
         Ok(Self {
             socket,
-            complete_address
+            address
         })
     }
+}
 
+impl Socket for UdpSocket {
     fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
-        let settings = timeout_settings.unwrap_or(TimeoutSettings::default());
-        self.socket.set_read_timeout(settings.get_read()).unwrap();   //unwrapping because TimeoutSettings::new
-        self.socket.set_write_timeout(settings.get_write()).unwrap(); //checks if these are 0 and throws an error
+        let settings = timeout_settings.unwrap_or_default();
+        //a zero duration is the only error, TimeoutSettings::new refuses it, but deserialized settings may have it
+        self.socket.set_read_timeout(settings.get_read()).map_err(|e| GDErrorKind::InvalidInput(e.to_string()).context(e))?;
+        self.socket.set_write_timeout(settings.get_write()).map_err(|e| GDErrorKind::InvalidInput(e.to_string()).context(e))?;
 
         Ok(())
     }
 
     fn send(&mut self, data: &[u8]) -> GDResult<()> {
-        self.socket.send_to(&data, &self.complete_address).map_err(|e| GDError::PacketSend(e.to_string()))?;
+        self.socket.send_to(data, self.address).map_err(|e| GDErrorKind::PacketSend(e.to_string()).context(e))?;
         Ok(())
     }
 
     fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
-        let mut buf: Vec<u8> = vec![0; size.unwrap_or(DEFAULT_PACKET_SIZE)];
-        let (number_of_bytes_received, _) = self.socket.recv_from(&mut buf).map_err(|e| GDError::PacketReceive(e.to_string()))?;
+        let mut buf = Vec::new();
+        self.receive_into(&mut buf, size)?;
+
+        Ok(buf)
+    }
+
+    fn receive_into(&mut self, buffer: &mut Vec<u8>, size: Option<usize>) -> GDResult<()> {
+        buffer.resize(size.unwrap_or(DEFAULT_PACKET_SIZE), 0);
+        let (number_of_bytes_received, _) = self.socket.recv_from(buffer).map_err(|e| GDErrorKind::PacketReceive(e.to_string()).context(e))?;
+        buffer.truncate(number_of_bytes_received);
+
+        Ok(())
+    }
+}
+
+/// An in-memory socket that replays recorded packets (one per [receive](Socket::receive)) and records the
+/// sent ones, to test the protocols without a server.
+#[derive(Debug, Clone, Default)]
+pub struct MockSocket {
+    responses: VecDeque<Vec<u8>>,
+    sent: Vec<Vec<u8>>
+}
+
+impl MockSocket {
+    /// Make a socket that replies with these packets, in order.
+    pub fn new(responses: Vec<Vec<u8>>) -> Self {
+        Self {
+            responses: responses.into(),
+            sent: Vec::new()
+        }
+    }
+
+    /// The packets that have been sent through the socket.
+    pub fn sent(&self) -> &[Vec<u8>] {
+        &self.sent
+    }
+
+    /// How many of the recorded packets haven't been received yet.
+    pub fn remaining(&self) -> usize {
+        self.responses.len()
+    }
+
+    /// Running out of packets is like a receive timing out (as if the next packet got lost).
+    fn no_more_packets() -> GDError {
+        let details = "No more recorded packets.";
+        GDErrorKind::PacketReceive(details.to_string()).context(std::io::Error::new(std::io::ErrorKind::TimedOut, details))
+    }
+}
+
+impl Socket for MockSocket {
+    fn apply_timeout(&self, _timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        Ok(())
+    }
+
+    fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        self.sent.push(data.to_vec());
+        Ok(())
+    }
+
+    fn receive(&mut self, _size: Option<usize>) -> GDResult<Vec<u8>> {
+        self.responses.pop_front().ok_or_else(MockSocket::no_more_packets)
+    }
+
+    /// Reads the packets as a stream: the bytes can span multiple packets and the rest of a packet is kept.
+    fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        let mut buf = Vec::with_capacity(size);
+
+        while buf.len() < size {
+            let mut packet = self.responses.pop_front().ok_or_else(MockSocket::no_more_packets)?;
+            let taken = packet.len().min(size - buf.len());
+            buf.extend(packet.drain(..taken));
+
+            if !packet.is_empty() {
+                self.responses.push_front(packet);
+            }
+        }
+
+        Ok(buf)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+pub trait AsyncSocket {
+    fn apply_timeout(&mut self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()>;
+
+    async fn send(&mut self, data: &[u8]) -> GDResult<()>;
+    async fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>>;
+
+    /// Same as [receive](AsyncSocket::receive), but into `buffer` (replacing its content), so that a buffer can
+    /// be reused for many packets instead of allocating one for each.
+    async fn receive_into(&mut self, buffer: &mut Vec<u8>, size: Option<usize>) -> GDResult<()> {
+        *buffer = self.receive(size).await?;
+        Ok(())
+    }
+
+    /// Receives exactly `size` bytes, same as [Socket::receive_exact].
+    async fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        let data = self.receive(Some(size)).await?;
+        match data.len() == size {
+            true => Ok(data),
+            false => Err(GDErrorKind::PacketUnderflow(format!("Expected {size} bytes, received {}.", data.len())).into())
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<S: AsyncSocket + Send + ?Sized> AsyncSocket for &mut S {
+    fn apply_timeout(&mut self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        (**self).apply_timeout(timeout_settings)
+    }
+
+    async fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        (**self).send(data).await
+    }
+
+    async fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        (**self).receive(size).await
+    }
+
+    async fn receive_into(&mut self, buffer: &mut Vec<u8>, size: Option<usize>) -> GDResult<()> {
+        (**self).receive_into(buffer, size).await
+    }
+
+    async fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        (**self).receive_exact(size).await
+    }
+}
+
+/// The [MockSocket] can also replay the packets of the async queries.
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl AsyncSocket for MockSocket {
+    fn apply_timeout(&mut self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        Socket::apply_timeout(self, timeout_settings)
+    }
+
+    async fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        Socket::send(self, data)
+    }
+
+    async fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        Socket::receive(self, size)
+    }
+
+    async fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        Socket::receive_exact(self, size)
+    }
+}
+
+/// Runs the future with the given timeout (if any), a timeout is mapped to the given error.
+#[cfg(feature = "async")]
+pub(crate) async fn with_timeout<T, F>(duration: Option<std::time::Duration>, future: F, error: fn(String) -> GDErrorKind) -> GDResult<T>
+    where F: std::future::Future<Output = std::io::Result<T>> {
+    let result = match duration {
+        None => future.await,
+        Some(duration) => tokio::time::timeout(duration, future).await
+            .map_err(|e| error(e.to_string()).context(e))?
+    };
+
+    result.map_err(|e| error(e.to_string()).context(e))
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncTcpSocket {
+    socket: tokio::net::TcpStream,
+    timeout_settings: TimeoutSettings
+}
+
+#[cfg(feature = "async")]
+impl AsyncTcpSocket {
+    pub async fn new(address: &str, port: u16) -> GDResult<Self> {
+        let complete_address = address_and_port_as_string(address, port);
+        let socket = tokio::net::TcpStream::connect(complete_address).await.map_err(|e| GDErrorKind::SocketConnect(e.to_string()).context(e).at(Stage::Connect))?;
+
+        Ok(Self {
+            socket,
+            timeout_settings: TimeoutSettings::default()
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl AsyncSocket for AsyncTcpSocket {
+    fn apply_timeout(&mut self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        self.timeout_settings = timeout_settings.unwrap_or_default();
+        Ok(())
+    }
+
+    async fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        with_timeout(self.timeout_settings.get_write(), self.socket.write_all(data), GDErrorKind::PacketSend).await
+    }
+
+    async fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        let mut buf = Vec::with_capacity(size.unwrap_or(DEFAULT_PACKET_SIZE));
+        self.receive_into(&mut buf, size).await?;
+
+        Ok(buf)
+    }
+
+    async fn receive_into(&mut self, buffer: &mut Vec<u8>, _size: Option<usize>) -> GDResult<()> {
+        buffer.clear();
+        with_timeout(self.timeout_settings.get_read(), self.socket.read_to_end(buffer), GDErrorKind::PacketReceive).await?;
+
+        Ok(())
+    }
+
+    async fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        let mut buf = vec![0; size];
+        with_timeout(self.timeout_settings.get_read(), self.socket.read_exact(&mut buf), GDErrorKind::PacketReceive).await?;
+
+        Ok(buf)
+    }
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncUdpSocket {
+    socket: tokio::net::UdpSocket,
+    address: SocketAddr,
+    timeout_settings: TimeoutSettings
+}
+
+#[cfg(feature = "async")]
+impl AsyncUdpSocket {
+    pub async fn new(address: &str, port: u16) -> GDResult<Self> {
+        let address = tokio::net::lookup_host(address_and_port_as_string(address, port)).await
+            .map_err(|e| GDErrorKind::SocketConnect(e.to_string()).context(e).at(Stage::Connect))?
+            .next()
+            .ok_or_else(|| GDError::from(GDErrorKind::SocketConnect("The address has no ips.".to_string())).at(Stage::Connect))?;
+
+        let socket = tokio::net::UdpSocket::bind(unspecified_address_for(&address)).await.map_err(|e| GDErrorKind::SocketBind(e.to_string()).context(e).at(Stage::Connect))?;
+
+        Ok(Self {
+            socket,
+            address,
+            timeout_settings: TimeoutSettings::default()
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl AsyncSocket for AsyncUdpSocket {
+    fn apply_timeout(&mut self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        self.timeout_settings = timeout_settings.unwrap_or_default();
+        Ok(())
+    }
+
+    async fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        with_timeout(self.timeout_settings.get_write(), self.socket.send_to(data, self.address), GDErrorKind::PacketSend).await?;
+        Ok(())
+    }
+
+    async fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.receive_into(&mut buf, size).await?;
+
+        Ok(buf)
+    }
+
+    async fn receive_into(&mut self, buffer: &mut Vec<u8>, size: Option<usize>) -> GDResult<()> {
+        buffer.resize(size.unwrap_or(DEFAULT_PACKET_SIZE), 0);
+        let (number_of_bytes_received, _) = with_timeout(self.timeout_settings.get_read(), self.socket.recv_from(buffer), GDErrorKind::PacketReceive).await?;
+        buffer.truncate(number_of_bytes_received);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::protocols::types::RetrySettings;
+    use super::*;
+
+    #[test]
+    fn udp_ipv6_send_and_receive() {
+        let server = net::UdpSocket::bind("[::1]:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let mut socket = UdpSocket::new("::1", port).unwrap();
+        socket.send(&[1, 2, 3]).unwrap();
+
+        let mut buf = [0; 3];
+        let (_, client_address) = server.recv_from(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+        assert!(client_address.is_ipv6());
+
+        server.send_to(&[4, 5], client_address).unwrap();
+        assert_eq!(socket.receive(None).unwrap(), vec![4, 5]);
+    }
+
+    #[test]
+    fn udp_receive_into_reuses_the_buffer() {
+        let server = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let mut socket = UdpSocket::new("127.0.0.1", port).unwrap();
+        socket.send(&[1]).unwrap();
+        let (_, client_address) = server.recv_from(&mut [0; 1]).unwrap();
+
+        server.send_to(&[1, 2, 3], client_address).unwrap();
+        server.send_to(&[4, 5], client_address).unwrap();
+
+        let mut buffer = Vec::with_capacity(64);
+        let allocation = buffer.as_ptr();
+
+        socket.receive_into(&mut buffer, Some(64)).unwrap();
+        assert_eq!(buffer, vec![1, 2, 3]);
+        socket.receive_into(&mut buffer, Some(64)).unwrap();
+        assert_eq!(buffer, vec![4, 5]);
+        assert_eq!(buffer.as_ptr(), allocation);
+    }
+
+    #[test]
+    fn mock_replays_and_records() {
+        let mut mock = MockSocket::new(vec![vec![1, 2, 3], vec![4, 5], vec![6]]);
+        let socket: &mut dyn Socket = &mut mock;
+
+        socket.send(&[9]).unwrap();
+        assert_eq!(socket.receive(None).unwrap(), vec![1, 2, 3]);
+        assert_eq!(socket.receive_exact(1).unwrap(), vec![4]);
+        assert_eq!(socket.receive_exact(2).unwrap(), vec![5, 6]);
+        assert!(socket.receive(None).is_err());
+
+        assert_eq!(mock.sent(), &[vec![9]]);
+    }
+
+    #[test]
+    fn deadline_limits_the_timeouts() {
+        let server = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        //the server never replies, so the receive waits until the deadline and not the 4 seconds read timeout
+        let retry = RetrySettings::new(0, Duration::default(), Some(Duration::from_millis(100))).unwrap();
+        let timeout_settings = Some(TimeoutSettings::new_with_retry(Some(Duration::from_secs(4)), None, retry).unwrap());
+
+        let start = Instant::now();
+        let mut socket = DeadlineSocket::new(UdpSocket::new("127.0.0.1", port).unwrap(), &timeout_settings);
+        socket.apply_timeout(timeout_settings).unwrap();
+
+        socket.send(&[1]).unwrap();
+        assert!(socket.receive(None).is_err());
+        assert!(socket.receive(None).is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_udp_send_and_receive() {
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let mut socket = AsyncUdpSocket::new("127.0.0.1", port).await.unwrap();
+        socket.send(&[1, 2, 3]).await.unwrap();
+
+        let mut buf = [0; 3];
+        let (_, client_address) = server.recv_from(&mut buf).await.unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+
+        server.send_to(&[4, 5], client_address).await.unwrap();
+        assert_eq!(socket.receive(None).await.unwrap(), vec![4, 5]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_udp_receive_timeout() {
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let mut socket = AsyncUdpSocket::new("127.0.0.1", port).await.unwrap();
+        socket.apply_timeout(Some(TimeoutSettings::new(Some(Duration::from_millis(50)), None).unwrap())).unwrap();
 
-        Ok(buf[..number_of_bytes_received].to_vec())
+        assert!(socket.receive(None).await.is_err());
     }
 }
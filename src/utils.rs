@@ -1,11 +1,13 @@
-use crate::{GDResult, GDError};
+use std::time::{Duration, Instant};
+use crate::{GDError, GDErrorKind, GDResult};
+use crate::protocols::types::{RetrySettings, TimeoutSettings};
 
 pub fn error_by_expected_size(expected: usize, size: usize) -> GDResult<()> {
     if size < expected {
-        Err(GDError::PacketUnderflow("Unexpectedly short packet.".to_string()))
+        Err(GDErrorKind::PacketUnderflow("Unexpectedly short packet.".to_string()).into())
     }
     else if size > expected {
-        Err(GDError::PacketOverflow("Unexpectedly long packet.".to_string()))
+        Err(GDErrorKind::PacketOverflow("Unexpectedly long packet.".to_string()).into())
     }
     else {
         Ok(())
@@ -13,18 +15,115 @@ pub fn error_by_expected_size(expected: usize, size: usize) -> GDResult<()> {
 }
 
 pub fn address_and_port_as_string(address: &str, port: u16) -> String {
-    address.to_string() + ":" + &*port.to_string()
+    //an IPv6 address has to be in brackets to be followed by a port
+    match address.parse::<std::net::Ipv6Addr>() {
+        Ok(_) => format!("[{address}]:{port}"),
+        Err(_) => address.to_string() + ":" + &*port.to_string()
+    }
 }
 
 pub fn u8_lower_upper(n: u8) -> (u8, u8) {
     (n & 15, n >> 4)
 }
 
+/// The error of a query that didn't finish before its deadline.
+pub fn deadline_passed() -> GDError {
+    let details = "The query's deadline passed.";
+    GDErrorKind::PacketReceive(details.to_string()).context(std::io::Error::new(std::io::ErrorKind::TimedOut, details))
+}
+
+/// How long to wait before retrying a failed attempt, None if it shouldn't be retried.
+fn retry_wait(retry: &RetrySettings, attempt: u8, deadline: Option<Instant>, error: &GDError) -> Option<Duration> {
+    if attempt >= retry.get_retries() || !error.is_retryable() {
+        return None;
+    }
+
+    let wait = retry.backoff_before(attempt as u32);
+    match deadline {
+        Some(deadline) if Instant::now() + wait >= deadline => None, //there wouldn't be time left for it
+        _ => Some(wait)
+    }
+}
+
+/// Runs a query (given the timeout settings of the attempt) until it succeeds or can't be retried anymore,
+/// as the [RetrySettings] say.
+pub fn retry<T>(timeout_settings: Option<TimeoutSettings>, mut query: impl FnMut(Option<TimeoutSettings>) -> GDResult<T>) -> GDResult<T> {
+    let settings = timeout_settings.unwrap_or_default();
+    let deadline = settings.get_retry().get_deadline().map(|deadline| Instant::now() + deadline);
+
+    let mut attempt = 0;
+    loop {
+        let error = match query(Some(settings.for_attempt(deadline))) {
+            Ok(response) => return Ok(response),
+            Err(error) => error
+        };
+
+        match retry_wait(settings.get_retry(), attempt, deadline, &error) {
+            None => return Err(error),
+            Some(wait) => std::thread::sleep(wait)
+        }
+
+        attempt += 1;
+    }
+}
+
+/// Asynchronously runs a query, same as [retry], the deadline also stops an attempt that is in progress.
+#[cfg(feature = "async")]
+pub async fn retry_async<T, F, Fut>(timeout_settings: Option<TimeoutSettings>, mut query: F) -> GDResult<T>
+    where F: FnMut(Option<TimeoutSettings>) -> Fut, Fut: std::future::Future<Output = GDResult<T>> {
+    let settings = timeout_settings.unwrap_or_default();
+    let deadline = settings.get_retry().get_deadline().map(|deadline| Instant::now() + deadline);
+
+    let mut attempt = 0;
+    loop {
+        let attempt_query = query(Some(settings.for_attempt(deadline)));
+        let result = match deadline {
+            None => attempt_query.await,
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), attempt_query).await
+                .unwrap_or_else(|_| Err(deadline_passed()))
+        };
+
+        let error = match result {
+            Ok(response) => return Ok(response),
+            Err(error) => error
+        };
+
+        match retry_wait(settings.get_retry(), attempt, deadline, &error) {
+            None => return Err(error),
+            Some(wait) => tokio::time::sleep(wait).await
+        }
+
+        attempt += 1;
+    }
+}
+
+/// Asynchronously runs a query (given its timeout settings), stopping it when the deadline of the
+/// [RetrySettings] passes, as the sync queries' [DeadlineSocket](crate::socket::DeadlineSocket) does.
+#[cfg(feature = "async")]
+pub(crate) async fn with_deadline<T, F, Fut>(timeout_settings: Option<TimeoutSettings>, query: F) -> GDResult<T>
+    where F: FnOnce(Option<TimeoutSettings>) -> Fut, Fut: std::future::Future<Output = GDResult<T>> {
+    let deadline = timeout_settings.as_ref().and_then(|settings| settings.get_retry().get_deadline());
+    let query = query(timeout_settings);
+
+    match deadline {
+        None => query.await,
+        Some(deadline) => tokio::time::timeout(deadline, query).await
+            .unwrap_or_else(|_| Err(deadline_passed()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::io;
+    use std::time::Duration;
+    use crate::{GDError, GDErrorKind};
+    use crate::protocols::types::{RetrySettings, TimeoutSettings};
+
     #[test]
     fn address_and_port_as_string() {
         assert_eq!(super::address_and_port_as_string("192.168.0.1", 27015), "192.168.0.1:27015");
+        assert_eq!(super::address_and_port_as_string("::1", 27015), "[::1]:27015");
+        assert_eq!(super::address_and_port_as_string("[::1]", 27015), "[::1]:27015");
     }
 
     #[test]
@@ -32,6 +131,69 @@ mod tests {
         assert_eq!(super::u8_lower_upper(171), (11, 10));
     }
 
+    fn timed_out() -> GDError {
+        GDErrorKind::PacketReceive("Timed out.".to_string()).context(io::Error::new(io::ErrorKind::TimedOut, "Timed out."))
+    }
+
+    #[test]
+    fn retry() {
+        let retry = RetrySettings::new(2, Duration::from_millis(1), None).unwrap();
+        let settings = TimeoutSettings::new_with_retry(None, None, retry).unwrap();
+
+        //the network errors are retried, as many times as the settings say
+        let mut attempts = 0;
+        let result: Result<(), _> = super::retry(Some(settings.clone()), |_| {
+            attempts += 1;
+            Err(timed_out())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result = super::retry(Some(settings.clone()), |attempt_settings| {
+            attempts += 1;
+            assert_eq!(attempt_settings.unwrap().get_retry().get_retries(), 0);
+            match attempts {
+                1 => Err(GDErrorKind::PacketSend("Reset.".to_string()).context(io::Error::new(io::ErrorKind::ConnectionReset, "Reset."))),
+                _ => Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+
+        //a bad response or a refused connection isn't
+        let mut attempts = 0;
+        let result: Result<(), _> = super::retry(Some(settings.clone()), |_| {
+            attempts += 1;
+            Err(GDErrorKind::PacketBad("Bad.".to_string()).into())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = super::retry(Some(settings), |_| {
+            attempts += 1;
+            Err(GDErrorKind::SocketConnect("Refused.".to_string()).context(io::Error::new(io::ErrorKind::ConnectionRefused, "Refused.")))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn retry_deadline() {
+        //the backoff would go past the deadline, so there's no retry
+        let retry = RetrySettings::new(5, Duration::from_millis(200), Some(Duration::from_millis(100))).unwrap();
+        let settings = TimeoutSettings::new_with_retry(None, None, retry).unwrap();
+
+        let mut attempts = 0;
+        let result: Result<(), _> = super::retry(Some(settings), |attempt_settings| {
+            attempts += 1;
+            assert!(attempt_settings.unwrap().get_retry().get_deadline().unwrap() <= Duration::from_millis(100));
+            Err(timed_out())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
     #[test]
     fn error_by_expected_size() {
         assert!(super::error_by_expected_size(69, 69).is_ok());
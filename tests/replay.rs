@@ -0,0 +1,192 @@
+//! Replays the synthetic responses in `tests/fixtures` (written from the protocols' specifications, not
+//! captured) through the protocols, so that the parsing can be tested without a network (the fixtures are
+//! described in `tests/fixtures/README.md`).
+
+use gamedig::GDResult;
+use gamedig::protocols::minecraft::{self, GameMode, LegacyGroup};
+use gamedig::protocols::types::TimeoutSettings;
+use gamedig::protocols::valve::{self, App};
+use gamedig::socket::{MockSocket, Socket};
+use gamedig::ts;
+
+/// Reads a fixture's packets: every group of hex lines (separated by blank lines) is a packet, `#` starts a comment.
+fn read_fixture(name: &str) -> Vec<Vec<u8>> {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    let content = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Couldn't read {path}: {e}"));
+
+    let mut packets = vec![];
+    let mut packet = vec![];
+    for line in content.lines().map(|line| line.split('#').next().unwrap().trim()) {
+        if line.is_empty() {
+            if !packet.is_empty() {
+                packets.push(std::mem::take(&mut packet));
+            }
+
+            continue;
+        }
+
+        packet.extend(line.split_whitespace().map(|byte| u8::from_str_radix(byte, 16).unwrap()));
+    }
+
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+
+    packets
+}
+
+fn replay(name: &str) -> MockSocket {
+    MockSocket::new(read_fixture(name))
+}
+
+/// Makes the reply (if any) to a sent packet.
+type Reply = Box<dyn Fn(&[u8]) -> Option<Vec<u8>>>;
+
+/// A socket that replays a fixture, but first answers with what `reply` makes from a sent packet, as some
+/// replies can't be written ahead (the pongs echo the pings' timestamps).
+struct EchoSocket {
+    socket: MockSocket,
+    reply: Reply,
+    pending: Vec<u8>
+}
+
+impl Socket for EchoSocket {
+    fn apply_timeout(&self, timeout_settings: Option<TimeoutSettings>) -> GDResult<()> {
+        self.socket.apply_timeout(timeout_settings)
+    }
+
+    fn send(&mut self, data: &[u8]) -> GDResult<()> {
+        if let Some(reply) = (self.reply)(data) {
+            self.pending.extend(reply);
+        }
+
+        self.socket.send(data)
+    }
+
+    fn receive(&mut self, size: Option<usize>) -> GDResult<Vec<u8>> {
+        match self.pending.is_empty() {
+            true => self.socket.receive(size),
+            false => Ok(std::mem::take(&mut self.pending))
+        }
+    }
+
+    fn receive_exact(&mut self, size: usize) -> GDResult<Vec<u8>> {
+        let taken = self.pending.len().min(size);
+        let mut data: Vec<u8> = self.pending.drain(..taken).collect();
+
+        if data.len() < size {
+            data.extend(self.socket.receive_exact(size - data.len())?);
+        }
+
+        Ok(data)
+    }
+}
+
+#[test]
+fn valve_goldsrc_split() {
+    let mut socket = replay("valve/goldsrc_split.hex");
+    let response = valve::query_with_socket(&mut socket, App::GoldSrc(false), None, None).unwrap();
+    assert_eq!(socket.remaining(), 0);
+
+    assert_eq!(response.info.name, "Crossfire 24/7");
+    assert_eq!(response.info.map, "crossfire");
+    assert_eq!(response.info.max_players, 16);
+    assert_eq!(response.info.extra_data.unwrap().port, Some(27015));
+
+    let players = response.players.unwrap().unwrap();
+    assert_eq!(players.len(), 3);
+    assert_eq!(players[2].name, "[BOT] Otis");
+    assert_eq!(players[1].duration, 960.25);
+
+    let rules = response.rules.unwrap().unwrap();
+    assert_eq!(rules.len(), 24);
+    assert_eq!(rules[10].name, "sv_stepsize");
+    assert_eq!(rules[23].value, "0");
+}
+
+#[test]
+fn valve_source_compressed_split() {
+    let mut socket = replay("valve/source_compressed_split.hex");
+    let response = valve::query_with_socket(&mut socket, App::Source(Some(440)), None, None).unwrap();
+    assert_eq!(socket.remaining(), 0);
+
+    assert_eq!(response.info.name, "Team Fortress 2 Server");
+    assert_eq!(response.info.extra_data.unwrap().keywords, Some("alltalk,increased_maxplayers".to_string()));
+    assert_eq!(response.players.unwrap().unwrap().len(), 2);
+
+    let rules = response.rules.unwrap().unwrap();
+    assert_eq!(rules.len(), 50);
+    assert_eq!(rules[47].name, "sm_cvar_47");
+    assert_eq!(rules[49].value, "alltalk,increased_maxplayers");
+}
+
+#[test]
+fn valve_the_ship() {
+    let mut socket = replay("valve/the_ship.hex");
+    let response = valve::query_with_socket(&mut socket, App::Source(Some(2400)), None, None).unwrap();
+    assert_eq!(socket.remaining(), 0);
+
+    let response = ts::Response::new_from_valve_response(response).unwrap();
+    assert_eq!(response.name, "The Ship Server");
+    assert_eq!((response.mode, response.witnesses, response.duration), (0, 1, 3));
+
+    assert_eq!(response.players_details.len(), 2);
+    assert_eq!(response.players_details[1].name, "Hunter");
+    assert_eq!(response.players_details[1].deaths, 0);
+    assert_eq!(response.players_details[1].money, 4100);
+    assert_eq!(response.rules.len(), 2);
+}
+
+#[test]
+fn minecraft_java() {
+    let socket = EchoSocket {
+        socket: replay("minecraft/java.hex"),
+        //the ping packet (length, id 1, payload) is answered with the same pong packet
+        reply: Box::new(|sent: &[u8]| (sent.get(1) == Some(&0x01)).then(|| sent.to_vec())),
+        pending: vec![]
+    };
+
+    let response = minecraft::query_java_with_socket(socket, None).unwrap();
+    assert_eq!(response.version_name, "1.19.2");
+    assert_eq!(response.version_protocol, 760);
+    assert_eq!((response.online_players, response.max_players), (2, 100));
+    assert_eq!(response.sample_players.unwrap()[1].name, "jeb_");
+    assert_eq!(response.enforces_secure_chat, Some(true));
+}
+
+#[test]
+fn minecraft_bedrock() {
+    let pong = read_fixture("minecraft/bedrock.hex").remove(0);
+    let socket = EchoSocket {
+        socket: MockSocket::default(),
+        //the pong has the ping's nonce
+        reply: Box::new(move |sent: &[u8]| {
+            let mut pong = pong.clone();
+            pong[1..9].copy_from_slice(&sent[1..9]);
+            Some(pong)
+        }),
+        pending: vec![]
+    };
+
+    let response = minecraft::query_bedrock_with_socket(socket, None).unwrap();
+    assert_eq!(response.name, "Dedicated Server");
+    assert_eq!(response.version_name, "1.19.40");
+    assert_eq!((response.online_players, response.max_players), (3, 10));
+    assert_eq!(response.map, Some("Bedrock level".to_string()));
+    assert!(matches!(response.game_mode, Some(GameMode::Survival)));
+}
+
+#[test]
+fn minecraft_legacy() {
+    let response = minecraft::query_legacy_specific_with_socket(LegacyGroup::V1_6, replay("minecraft/legacy_v1_6.hex"), None).unwrap();
+    assert_eq!((response.version_name.as_str(), response.version_protocol), ("1.6.4", 78));
+    assert_eq!((response.online_players, response.max_players), (5, 20));
+
+    let response = minecraft::query_legacy_specific_with_socket(LegacyGroup::V1_4, replay("minecraft/legacy_v1_4.hex"), None).unwrap();
+    assert_eq!(response.description, "A Minecraft Server");
+    assert_eq!((response.online_players, response.max_players), (4, 20));
+
+    let response = minecraft::query_legacy_specific_with_socket(LegacyGroup::VB1_8, replay("minecraft/legacy_bv1_8.hex"), None).unwrap();
+    assert_eq!(response.description, "A Beta Server");
+    assert_eq!((response.online_players, response.max_players), (1, 8));
+}